@@ -0,0 +1,679 @@
+//! Loading of assets on a background thread
+
+use crate::{
+    Asset,
+    AssetCache,
+    AssetErr,
+    AssetRef,
+};
+
+use std::{
+    any::TypeId,
+    collections::{HashMap, VecDeque},
+    fmt,
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc,
+        Condvar,
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver},
+    },
+    thread,
+    time::Duration,
+};
+
+
+type Job = Box<dyn FnOnce() + Send>;
+
+type LoadResult<A> = Result<AssetRef<'static, A>, AssetErr<A>>;
+
+/// How expensive an asset is to load.
+///
+/// This is a hint given by [`Asset::COST`], used by [`BackgroundLoader`] to
+/// schedule loads: tiny assets are loaded first and in batches, and huge
+/// assets are loaded one at a time per stage, so that a huge file does not
+/// delay a lot of small ones.
+///
+/// [`Asset::COST`]: trait.Asset.html#associatedconstant.COST
+/// [`BackgroundLoader`]: struct.BackgroundLoader.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LoadCost {
+    /// Small files that are quick to load, such as configuration files.
+    Tiny,
+
+    /// The default.
+    Medium,
+
+    /// Large files that are long to load, such as videos or big archives.
+    Huge,
+}
+
+impl Default for LoadCost {
+    #[inline]
+    fn default() -> Self {
+        LoadCost::Medium
+    }
+}
+
+/// The number of tiny jobs run by a thread in a row.
+const TINY_BATCH: usize = 16;
+
+#[derive(Default)]
+pub(crate) struct QueueState {
+    tiny: VecDeque<Job>,
+    medium: VecDeque<Job>,
+    huge: VecDeque<Job>,
+    huge_running: bool,
+    closed: bool,
+}
+
+impl QueueState {
+    /// Takes the next jobs to run, with whether they are huge.
+    pub(crate) fn take(&mut self) -> Option<(Vec<Job>, bool)> {
+        if !self.tiny.is_empty() {
+            let n = self.tiny.len().min(TINY_BATCH);
+            Some((self.tiny.drain(..n).collect(), false))
+        } else if let Some(job) = self.medium.pop_front() {
+            Some((vec![job], false))
+        } else if self.huge_running {
+            None
+        } else {
+            let job = self.huge.pop_front()?;
+            self.huge_running = true;
+            Some((vec![job], true))
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tiny.is_empty() && self.medium.is_empty() && self.huge.is_empty()
+    }
+}
+
+/// A queue of jobs shared by the threads of a pool.
+#[derive(Default)]
+pub(crate) struct Queue {
+    pub(crate) state: Mutex<QueueState>,
+    cond: Condvar,
+}
+
+impl Queue {
+    pub(crate) fn push(&self, cost: LoadCost, job: Job) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+
+        match cost {
+            LoadCost::Tiny => state.tiny.push_back(job),
+            LoadCost::Medium => state.medium.push_back(job),
+            LoadCost::Huge => state.huge.push_back(job),
+        }
+        self.cond.notify_one();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.cond.notify_all();
+    }
+
+    fn run(&self) {
+        loop {
+            let (jobs, huge) = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if let Some(next) = state.take() {
+                        break next;
+                    }
+                    if state.closed && state.is_empty() {
+                        return;
+                    }
+                    state = self.cond.wait(state).unwrap();
+                }
+            };
+
+            let _guard = if huge { Some(HugeGuard(self)) } else { None };
+            for job in jobs {
+                // A panicking loader must not kill the thread. The panic is
+                // reported to the waiting handle, as the sender of the result
+                // is dropped.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            }
+        }
+    }
+}
+
+/// Allows the next huge job to run, even if the current one panicked.
+struct HugeGuard<'a>(&'a Queue);
+
+impl Drop for HugeGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.0.state.lock() {
+            state.huge_running = false;
+        }
+        self.0.cond.notify_all();
+    }
+}
+
+/// A set of threads that run jobs from a shared queue.
+struct Pool {
+    queue: Arc<Queue>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl Pool {
+    fn new(name: &str, threads: usize) -> Self {
+        let queue = Arc::new(Queue::default());
+
+        let handles = (0..threads.max(1)).map(|_| {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(name.into())
+                .spawn(move || queue.run())
+                .expect("failed to spawn thread")
+        }).collect();
+
+        Pool { queue, handles }
+    }
+
+    #[inline]
+    fn send(&self, cost: LoadCost, job: Job) {
+        self.queue.push(cost, job);
+    }
+
+    /// Waits for the threads to finish all jobs.
+    fn join(&mut self) {
+        self.queue.close();
+
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+type FlightKey = (TypeId, Box<str>);
+
+type Flights = Arc<Mutex<HashMap<FlightKey, Arc<Flight>>>>;
+
+/// A load in progress, that can be waited for.
+#[derive(Default)]
+struct Flight {
+    done: Mutex<bool>,
+    cond: Condvar,
+}
+
+/// Marks a load as finished when dropped.
+struct FlightGuard {
+    flights: Flights,
+    key: FlightKey,
+    flight: Arc<Flight>,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut flights) = self.flights.lock() {
+            if flights.get(&self.key).map_or(false, |f| Arc::ptr_eq(f, &self.flight)) {
+                flights.remove(&self.key);
+            }
+        }
+
+        if let Ok(mut done) = self.flight.done.lock() {
+            *done = true;
+        }
+        self.flight.cond.notify_all();
+    }
+}
+
+/// Threads dedicated to loading assets.
+///
+/// Assets are requested with [`load`], which returns immediately with a
+/// [`PendingHandle`] that can be polled until the asset is loaded. This allows
+/// engines with a strict main thread to never wait for the disk.
+///
+/// Loading is done in two stages, each with its own threads: reading files
+/// (I/O), then converting their content to assets (decoding). By default,
+/// each stage has a single thread, but this can be changed with
+/// [`BackgroundLoader::builder`], as the best number of concurrent reads
+/// depends a lot on the storage (e.g. HDDs prefer sequential reads). Loads
+/// are scheduled according to the [`LoadCost`] of their asset type.
+///
+/// Because the loading threads have to access the cache, it is required to
+/// be `'static` (see for example `Box::leak` or the `lazy_static` crate).
+///
+/// When the `BackgroundLoader` is dropped, it waits for the threads to finish
+/// the requested loads.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, BackgroundLoader, loader};
+///
+/// struct X(i32);
+///
+/// impl From<i32> for X {
+///     fn from(x: i32) -> X { X(x) }
+/// }
+///
+/// impl Asset for X {
+///     const EXT: &'static str = "x";
+///     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+/// }
+///
+/// let cache: &'static AssetCache = Box::leak(Box::new(AssetCache::new("assets")?));
+/// let loader = BackgroundLoader::new(cache);
+///
+/// let mut pending = loader.load::<X>("test.cache");
+///
+/// // Do something useful while the asset is loading
+/// while pending.poll().is_none() {
+///     std::thread::yield_now();
+/// }
+///
+/// let x = pending.wait()?;
+/// assert_eq!(x.read().0, 42);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`load`]: #method.load
+/// [`PendingHandle`]: struct.PendingHandle.html
+/// [`BackgroundLoader::builder`]: #method.builder
+/// [`LoadCost`]: enum.LoadCost.html
+pub struct BackgroundLoader {
+    cache: &'static AssetCache,
+    io: Pool,
+    decode: Pool,
+    flights: Flights,
+}
+
+impl BackgroundLoader {
+    /// Starts new threads to load assets in the given cache, with one thread
+    /// for I/O and one for decoding.
+    #[inline]
+    pub fn new(cache: &'static AssetCache) -> Self {
+        Self::builder(cache).build()
+    }
+
+    /// Returns a builder to configure the number of loading threads.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use assets_manager::{AssetCache, BackgroundLoader};
+    ///
+    /// let cache: &'static AssetCache = Box::leak(Box::new(AssetCache::new("assets")?));
+    /// let loader = BackgroundLoader::builder(cache)
+    ///     .max_io_concurrency(4)
+    ///     .max_decode_concurrency(2)
+    ///     .build();
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn builder(cache: &'static AssetCache) -> BackgroundLoaderBuilder {
+        BackgroundLoaderBuilder {
+            cache,
+            io: 1,
+            decode: 1,
+        }
+    }
+
+    /// Requests the loading of an asset.
+    ///
+    /// If the asset is already in the cache, the returned handle is
+    /// immediately ready. Otherwise, the asset is loaded by the background
+    /// threads. Requests with the same [`LoadCost`] are started in order, so
+    /// with a single thread per stage, assets are loaded after all previously
+    /// requested ones of the same cost.
+    ///
+    /// If the returned handle is dropped before the background thread starts
+    /// loading the asset, the loading is cancelled. This way, requests made
+    /// for a level that was abandoned in the meantime do not waste time.
+    ///
+    /// [`LoadCost`]: enum.LoadCost.html
+    pub fn load<A: Asset>(&self, id: &str) -> PendingHandle<A>
+    where
+        AssetErr<A>: Send,
+    {
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        self.request(id, cancelled.clone(), move |result| {
+            let _ = sender.send(result);
+        });
+
+        PendingHandle {
+            receiver,
+            result: None,
+            cancelled,
+        }
+    }
+
+    /// Requests the loading of all assets of a directory.
+    ///
+    /// The directory is listed immediately, as with
+    /// [`AssetCache::load_dir_lazy`], then each of its assets is requested as
+    /// with [`load`]. The returned [`PendingDir`] yields assets as soon as
+    /// they are loaded, so a list can be filled progressively instead of
+    /// waiting for the whole directory.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory.
+    ///
+    /// [`AssetCache::load_dir_lazy`]: struct.AssetCache.html#method.load_dir_lazy
+    /// [`load`]: #method.load
+    /// [`PendingDir`]: struct.PendingDir.html
+    pub fn load_dir<A: Asset>(&self, id: &str) -> io::Result<PendingDir<A>>
+    where
+        AssetErr<A>: Send,
+    {
+        let dir = self.cache.load_dir_lazy::<A>(id)?;
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        for id in dir.ids() {
+            let sender = sender.clone();
+            let owned_id = id.clone();
+            self.request(id, cancelled.clone(), move |result| {
+                let _ = sender.send((owned_id, result));
+            });
+        }
+
+        Ok(PendingDir {
+            receiver,
+            remaining: dir.len(),
+            cancelled,
+        })
+    }
+
+    /// Gives the result of loading `id` to `send`, either immediately if the
+    /// asset is cached, or from the background threads.
+    fn request<A, F>(&self, id: &str, cancelled: Arc<AtomicBool>, send: F)
+    where
+        A: Asset,
+        F: FnOnce(LoadResult<A>) + Send + 'static,
+    {
+        if let Some(asset) = self.cache.load_cached(id) {
+            send(Ok(asset));
+            return;
+        }
+
+        let cache = self.cache;
+        let id = id.to_owned();
+        let decode = self.decode.queue.clone();
+        let flight = self.start_flight::<A>(&id);
+
+        self.io.send(A::COST, Box::new(move || {
+            if cancelled.load(Ordering::Acquire) {
+                return;
+            }
+            // The asset may have been loaded since the request
+            if let Some(asset) = cache.load_cached(&id) {
+                send(Ok(asset));
+                return;
+            }
+
+            let (ext, content) = cache.read_raw::<A>(&id);
+            let job = Box::new(move || {
+                let _flight = flight;
+                if !cancelled.load(Ordering::Acquire) {
+                    send(cache.add_raw(id, &ext, content));
+                }
+            });
+
+            decode.push(A::COST, job);
+        }));
+    }
+
+    /// Registers a new load in progress, which ends when the returned guard
+    /// is dropped.
+    fn start_flight<A: Asset>(&self, id: &str) -> FlightGuard {
+        let key: FlightKey = (TypeId::of::<A>(), id.into());
+        let flight = Arc::new(Flight::default());
+        self.flights.lock().unwrap().insert(key.clone(), flight.clone());
+
+        FlightGuard {
+            flights: self.flights.clone(),
+            key,
+            flight,
+        }
+    }
+
+    /// Waits for an asset requested with [`load`] to be loaded.
+    ///
+    /// This is useful when an asset was prefetched, but is needed before its
+    /// loading is finished: waiting for it avoids loading it a second time
+    /// synchronously.
+    ///
+    /// If `timeout` is `Some`, waits at most for the given duration.
+    ///
+    /// Returns `None` if the asset is still not in the cache at the end,
+    /// which happens if the asset was not requested, if its loading failed
+    /// or was cancelled, or if the timeout expired.
+    ///
+    /// [`load`]: #method.load
+    pub fn wait_for<A: Asset>(&self, id: &str, timeout: Option<Duration>) -> Option<AssetRef<'static, A>> {
+        if let Some(asset) = self.cache.load_cached(id) {
+            return Some(asset);
+        }
+
+        let key: FlightKey = (TypeId::of::<A>(), id.into());
+        let flight = self.flights.lock().unwrap().get(&key).cloned();
+
+        if let Some(flight) = flight {
+            let done = flight.done.lock().unwrap();
+            match timeout {
+                Some(timeout) => drop(flight.cond.wait_timeout_while(done, timeout, |done| !*done)),
+                None => drop(flight.cond.wait_while(done, |done| !*done)),
+            }
+        }
+
+        self.cache.load_cached(id)
+    }
+}
+
+impl Drop for BackgroundLoader {
+    fn drop(&mut self) {
+        // I/O threads must be stopped first, as they send jobs to decoding
+        // threads
+        self.io.join();
+        self.decode.join();
+    }
+}
+
+impl fmt::Debug for BackgroundLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackgroundLoader")
+            .field("cache", &self.cache.path())
+            .field("io_threads", &self.io.handles.len())
+            .field("decode_threads", &self.decode.handles.len())
+            .finish()
+    }
+}
+
+
+/// A builder for a [`BackgroundLoader`].
+///
+/// It can be obtained by calling [`BackgroundLoader::builder`].
+///
+/// [`BackgroundLoader`]: struct.BackgroundLoader.html
+/// [`BackgroundLoader::builder`]: struct.BackgroundLoader.html#method.builder
+#[derive(Debug)]
+pub struct BackgroundLoaderBuilder {
+    cache: &'static AssetCache,
+    io: usize,
+    decode: usize,
+}
+
+impl BackgroundLoaderBuilder {
+    /// Sets the maximum number of files read at the same time, ie the number
+    /// of I/O threads.
+    ///
+    /// Defaults to 1. A value of 0 is treated as 1.
+    #[inline]
+    pub fn max_io_concurrency(mut self, threads: usize) -> Self {
+        self.io = threads;
+        self
+    }
+
+    /// Sets the maximum number of assets decoded at the same time, ie the
+    /// number of decoding threads.
+    ///
+    /// Defaults to 1. A value of 0 is treated as 1.
+    #[inline]
+    pub fn max_decode_concurrency(mut self, threads: usize) -> Self {
+        self.decode = threads;
+        self
+    }
+
+    /// Starts the threads and returns the `BackgroundLoader`.
+    pub fn build(self) -> BackgroundLoader {
+        BackgroundLoader {
+            cache: self.cache,
+            io: Pool::new("assets_manager io", self.io),
+            decode: Pool::new("assets_manager decoder", self.decode),
+            flights: Arc::default(),
+        }
+    }
+}
+
+
+/// An asset being loaded by a [`BackgroundLoader`].
+///
+/// It can be obtained by calling [`BackgroundLoader::load`].
+///
+/// Dropping a `PendingHandle` cancels the loading if it has not started yet.
+///
+/// [`BackgroundLoader`]: struct.BackgroundLoader.html
+/// [`BackgroundLoader::load`]: struct.BackgroundLoader.html#method.load
+pub struct PendingHandle<A: Asset> {
+    receiver: Receiver<Result<AssetRef<'static, A>, AssetErr<A>>>,
+    result: Option<Result<AssetRef<'static, A>, AssetErr<A>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<A: Asset> PendingHandle<A> {
+    /// Returns the result of the loading if it is finished.
+    ///
+    /// This function never blocks.
+    pub fn poll(&mut self) -> Option<&Result<AssetRef<'static, A>, AssetErr<A>>> {
+        if self.result.is_none() {
+            self.result = self.receiver.try_recv().ok();
+        }
+        self.result.as_ref()
+    }
+
+    /// Returns `true` if the loading is finished.
+    #[inline]
+    pub fn is_ready(&mut self) -> bool {
+        self.poll().is_some()
+    }
+
+    /// Blocks the current thread until the loading is finished and returns its
+    /// result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the loading thread panicked while loading the asset.
+    pub fn wait(mut self) -> Result<AssetRef<'static, A>, AssetErr<A>> {
+        match self.result.take() {
+            Some(result) => result,
+            None => self.receiver.recv().expect("the loading thread panicked"),
+        }
+    }
+}
+
+impl<A: Asset> Drop for PendingHandle<A> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+impl<A: Asset> fmt::Debug for PendingHandle<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingHandle")
+            .field("ready", &self.result.is_some())
+            .finish()
+    }
+}
+
+
+/// The assets of a directory being loaded by a [`BackgroundLoader`].
+///
+/// It can be obtained by calling [`BackgroundLoader::load_dir`]. Assets are
+/// yielded in the order they finish loading, with their id, either without
+/// blocking with [`poll`] or by iterating.
+///
+/// Dropping a `PendingDir` cancels the loading of assets that have not
+/// started yet.
+///
+/// [`BackgroundLoader`]: struct.BackgroundLoader.html
+/// [`BackgroundLoader::load_dir`]: struct.BackgroundLoader.html#method.load_dir
+/// [`poll`]: #method.poll
+pub struct PendingDir<A: Asset> {
+    receiver: Receiver<(String, LoadResult<A>)>,
+    remaining: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<A: Asset> PendingDir<A> {
+    /// Returns the next loaded asset if there is one.
+    ///
+    /// This function never blocks.
+    pub fn poll(&mut self) -> Option<(String, LoadResult<A>)> {
+        let next = self.receiver.try_recv().ok()?;
+        self.remaining -= 1;
+        Some(next)
+    }
+
+    /// Returns the number of assets that were not yielded yet.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Returns `true` if all assets were yielded.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// Blocks the current thread until the next asset is loaded.
+///
+/// # Panics
+///
+/// Panics if the loading thread panicked while loading an asset.
+impl<A: Asset> Iterator for PendingDir<A> {
+    type Item = (String, LoadResult<A>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let next = self.receiver.recv().expect("the loading thread panicked");
+        self.remaining -= 1;
+        Some(next)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<A: Asset> Drop for PendingDir<A> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+impl<A: Asset> fmt::Debug for PendingDir<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingDir")
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
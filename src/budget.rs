@@ -0,0 +1,141 @@
+//! Memory budgets for groups of assets
+
+use crate::cache::Key;
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    fmt,
+};
+
+use crate::RandomState;
+
+
+enum Rule {
+    Prefix(Box<str>),
+    Type(TypeId),
+}
+
+impl Rule {
+    fn matches(&self, key: &Key) -> bool {
+        match self {
+            Rule::Prefix(prefix) => key.id().starts_with(&**prefix),
+            Rule::Type(type_id) => key.type_id() == *type_id,
+        }
+    }
+}
+
+/// The budget groups of a cache.
+pub(crate) struct Budgets {
+    rules: Vec<(Rule, Box<str>)>,
+    limits: HashMap<Box<str>, usize, RandomState>,
+}
+
+impl Budgets {
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            limits: HashMap::with_hasher(RandomState::new()),
+        }
+    }
+
+    pub fn tag_prefix(&mut self, prefix: &str, group: &str) {
+        self.rules.push((Rule::Prefix(prefix.into()), group.into()));
+    }
+
+    pub fn tag_type(&mut self, type_id: TypeId, group: &str) {
+        self.rules.push((Rule::Type(type_id), group.into()));
+    }
+
+    pub fn set_limit(&mut self, group: &str, bytes: usize) {
+        self.limits.insert(group.into(), bytes);
+    }
+
+    pub fn limit(&self, group: &str) -> Option<usize> {
+        self.limits.get(group).copied()
+    }
+
+    /// Returns the group of an asset, given by the first rule that matches it.
+    pub fn group_of(&self, key: &Key) -> Option<&str> {
+        self.rules.iter()
+            .find(|(rule, _)| rule.matches(key))
+            .map(|(_, group)| &**group)
+    }
+
+    /// Builds a report of each group from the assets and their size, sorted
+    /// by name.
+    pub fn report<'a>(&self, assets: impl Iterator<Item = (&'a Key, usize)>) -> Vec<GroupReport> {
+        let mut groups = HashMap::<&str, GroupReport, RandomState>::default();
+
+        for (_, group) in &self.rules {
+            groups.entry(group).or_insert_with(|| GroupReport::new(group, self.limit(group)));
+        }
+
+        for (key, size) in assets {
+            if let Some(group) = self.group_of(key) {
+                let report = groups.get_mut(group).unwrap();
+                report.count += 1;
+                report.estimated_bytes += size;
+            }
+        }
+
+        let mut groups: Vec<_> = groups.into_iter().map(|(_, report)| report).collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        groups
+    }
+}
+
+
+/// Statistics about a budget group of assets.
+///
+/// It can be obtained with [`AssetCache::budget_report`].
+///
+/// [`AssetCache::budget_report`]: struct.AssetCache.html#method.budget_report
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct GroupReport {
+    /// The name of the group.
+    pub name: String,
+
+    /// The number of assets of the group in the cache.
+    pub count: usize,
+
+    /// An estimation of the memory used by the assets of the group.
+    ///
+    /// See [`TypeReport::estimated_bytes`] for how it is computed.
+    ///
+    /// [`TypeReport::estimated_bytes`]: struct.TypeReport.html#structfield.estimated_bytes
+    pub estimated_bytes: usize,
+
+    /// The budget of the group in bytes, if one was set.
+    pub budget: Option<usize>,
+}
+
+impl GroupReport {
+    fn new(name: &str, budget: Option<usize>) -> Self {
+        Self {
+            name: name.to_owned(),
+            count: 0,
+            estimated_bytes: 0,
+            budget,
+        }
+    }
+
+    /// Returns `true` if the group uses more memory than its budget.
+    pub fn is_over_budget(&self) -> bool {
+        self.budget.map_or(false, |budget| self.estimated_bytes > budget)
+    }
+}
+
+impl fmt::Display for GroupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} assets, ~{} bytes", self.name, self.count, self.estimated_bytes)?;
+        if let Some(budget) = self.budget {
+            write!(f, " / {} bytes", budget)?;
+            if self.is_over_budget() {
+                f.write_str(" (over budget)")?;
+            }
+        }
+        Ok(())
+    }
+}
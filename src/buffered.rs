@@ -0,0 +1,116 @@
+//! Double-buffered access to assets
+
+use crate::{
+    AssetGuard,
+    AssetRef,
+    lock::Mutex,
+};
+
+use std::{fmt, sync::Arc};
+
+
+/// A reference to an asset that also keeps the value it had at the previous
+/// frame.
+///
+/// It is created with [`AssetRef::buffered`].
+///
+/// This is useful for engines with a render thread one frame behind the
+/// update thread: the update thread can see reloaded values with [`read`]
+/// while the render thread keeps using the value of the last frame with
+/// [`read_previous`], so both threads never see a reload in the middle of
+/// their frame.
+///
+/// The previous value is updated by [`flip`], which is typically called at
+/// the end of each frame, before reloading assets.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, loader};
+///
+/// #[derive(Clone)]
+/// struct X(i32);
+///
+/// impl From<i32> for X {
+///     fn from(x: i32) -> X { X(x) }
+/// }
+///
+/// impl Asset for X {
+///     const EXT: &'static str = "x";
+///     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+/// }
+///
+/// let cache = AssetCache::new("assets")?;
+/// let x = cache.load::<X>("test.cache")?.buffered();
+///
+/// loop {
+///     // Update thread
+///     let current = x.read();
+///
+///     // Render thread
+///     let previous = x.read_previous();
+///     # assert_eq!(current.0, previous.0);
+///     # drop(current);
+///
+///     // At the end of the frame
+///     x.flip();
+///     # #[cfg(feature = "hot-reloading")]
+///     cache.hot_reload()?;
+///     # break;
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetRef::buffered`]: struct.AssetRef.html#method.buffered
+/// [`read`]: #method.read
+/// [`read_previous`]: #method.read_previous
+/// [`flip`]: #method.flip
+pub struct Buffered<'a, A> {
+    asset: AssetRef<'a, A>,
+    previous: Mutex<Arc<A>>,
+}
+
+impl<'a, A: Clone> Buffered<'a, A> {
+    #[inline]
+    pub(crate) fn new(asset: AssetRef<'a, A>) -> Self {
+        Self {
+            previous: Mutex::new(asset.snapshot()),
+            asset,
+        }
+    }
+
+    /// Locks the current value of the asset for reading.
+    #[inline]
+    pub fn read(&self) -> AssetGuard<'a, A> {
+        self.asset.read()
+    }
+
+    /// Returns the value of the asset at the last call to [`flip`].
+    ///
+    /// [`flip`]: #method.flip
+    #[inline]
+    pub fn read_previous(&self) -> Arc<A> {
+        self.previous.lock().clone()
+    }
+
+    /// Records the current value of the asset as the previous one.
+    pub fn flip(&self) {
+        let current = self.asset.snapshot();
+        *self.previous.lock() = current;
+    }
+
+    /// Returns the underlying reference to the asset.
+    #[inline]
+    pub fn asset_ref(&self) -> AssetRef<'a, A> {
+        self.asset
+    }
+}
+
+impl<A: fmt::Debug> fmt::Debug for Buffered<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Buffered")
+            .field("current", &self.asset)
+            .field("previous", &*self.previous.lock())
+            .finish()
+    }
+}
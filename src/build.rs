@@ -0,0 +1,205 @@
+//! Helpers for build scripts.
+//!
+//! # Validation
+//!
+//! Content errors in assets are usually only found at runtime, when the
+//! asset is loaded. Calling [`check_assets`] from a build script brings them
+//! to compile time: the build fails with a list of all malformed files.
+//!
+//! As build scripts cannot use the types of the crate they build, asset types
+//! to check have to be available to the build script, for example by putting
+//! them in a separate crate or by including their definition with
+//! `include!`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! // build.rs
+//! use assets_manager::{Asset, loader};
+//!
+//! # struct Level;
+//! # impl Asset for Level {
+//! #     const EXT: &'static str = "ron";
+//! #     type Loader = loader::ParseLoader;
+//! # }
+//! # impl std::str::FromStr for Level {
+//! #     type Err = std::num::ParseIntError;
+//! #     fn from_str(s: &str) -> Result<Self, Self::Err> { s.parse::<i32>().map(|_| Level) }
+//! # }
+//! fn main() {
+//!     assets_manager::build::check_assets::<Level>("assets/levels");
+//! }
+//! ```
+//!
+//! # Generation of ids
+//!
+//! [`generate`] writes a Rust file with a constant for each asset id found in
+//! a directory, so renamed or removed files become compile errors instead of
+//! runtime load failures.
+//!
+//! ```no_run
+//! // build.rs
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("asset_ids.rs");
+//! assets_manager::build::generate("assets", &out).unwrap();
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! mod asset_ids {
+//!     include!(concat!(env!("OUT_DIR"), "/asset_ids.rs"));
+//! }
+//!
+//! let hero = cache.load::<Texture>(asset_ids::textures::HERO)?;
+//! ```
+//!
+//! [`check_assets`]: fn.check_assets.html
+//! [`generate`]: fn.generate.html
+
+use crate::{
+    Asset,
+    AssetCache,
+    source::{DirEntry, FileSystem, Source},
+};
+
+use std::{
+    fmt::Write,
+    fs,
+    io,
+    path::Path,
+};
+
+
+/// Checks that all assets of type `A` in a directory and its subdirectories
+/// are valid.
+///
+/// The build script is set to be re-run when the directory changes.
+///
+/// # Panics
+///
+/// Panics with a list of errors if the directory cannot be read or if an
+/// asset fails to load, which makes the build fail.
+pub fn check_assets<A: Asset>(path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let cache = match AssetCache::new(path) {
+        Ok(cache) => cache,
+        Err(err) => panic!("cannot read assets directory {:?}: {}", path, err),
+    };
+
+    let errors = match cache.validate::<A>("") {
+        Ok(errors) => errors,
+        Err(err) => panic!("cannot read assets directory {:?}: {}", path, err),
+    };
+
+    if !errors.is_empty() {
+        let mut msg = format!("{} invalid assets of type {}:\n", errors.len(), std::any::type_name::<A>());
+        for (id, err) in errors {
+            let _ = writeln!(msg, "  {}: {}", id, err);
+        }
+        panic!("{}", msg);
+    }
+}
+
+
+/// Writes a Rust file with a constant for each asset id in a directory.
+///
+/// Each subdirectory becomes a module, and each file a `&str` constant with
+/// the id of the asset, named after the file in upper case. For example, the
+/// file `textures/hero.png` gives `pub const HERO: &str = "textures.hero";`
+/// in module `textures`. Characters that cannot be used in Rust identifiers
+/// are replaced by `_`.
+///
+/// The output file is only written if its content changed, so it does not
+/// trigger needless rebuilds. The build script is set to be re-run when the
+/// directory changes.
+///
+/// # Errors
+///
+/// An error is returned if the directory cannot be read or if the output file
+/// cannot be written.
+pub fn generate(root: impl AsRef<Path>, out: impl AsRef<Path>) -> io::Result<()> {
+    let root = root.as_ref();
+    let out = out.as_ref();
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    let source = FileSystem::new(root)?;
+    let code = generate_code(&source)?;
+
+    if fs::read_to_string(out).ok().as_deref() != Some(&*code) {
+        fs::write(out, code)?;
+    }
+
+    Ok(())
+}
+
+/// Generates the code with the ids of the assets of a source.
+pub(crate) fn generate_code(source: &dyn Source) -> io::Result<String> {
+    let mut code = String::from("// Generated by assets_manager. Do not edit.\n");
+    generate_dir(source, "", 0, &mut code)?;
+    Ok(code)
+}
+
+fn generate_dir(source: &dyn Source, id: &str, depth: usize, code: &mut String) -> io::Result<()> {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+
+    source.read_dir(id, &mut |entry| match entry {
+        DirEntry::File(id, _) => files.push(id.to_owned()),
+        DirEntry::Directory(id) => dirs.push(id.to_owned()),
+    })?;
+
+    files.sort();
+    files.dedup();
+    dirs.sort();
+
+    let indent = "    ".repeat(depth);
+
+    for file in &files {
+        let name = identifier(last_part(file)).to_uppercase();
+        let _ = writeln!(code, "{}pub const {}: &str = {:?};", indent, name, file);
+    }
+
+    for dir in &dirs {
+        let mut name = identifier(last_part(dir)).to_lowercase();
+        if ["crate", "self", "super"].contains(&&*name) {
+            name.push('_');
+        } else if KEYWORDS.contains(&&*name) {
+            name.insert_str(0, "r#");
+        }
+
+        let _ = writeln!(code, "{}pub mod {} {{", indent, name);
+        generate_dir(source, dir, depth + 1, code)?;
+        let _ = writeln!(code, "{}}}", indent);
+    }
+
+    Ok(())
+}
+
+fn last_part(id: &str) -> &str {
+    match id.rfind('.') {
+        Some(pos) => &id[pos + 1..],
+        None => id,
+    }
+}
+
+/// Turns a file name into a valid Rust identifier.
+fn identifier(name: &str) -> String {
+    let mut ident: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if ident.chars().next().map_or(true, |c| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+
+    ident
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+    "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "async",
+    "await", "dyn", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
+];
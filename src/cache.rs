@@ -1,31 +1,139 @@
 //! Definition of the cache
 use crate::{
+    ANY_EXTENSION,
     Asset,
     AssetErr,
-    dirs::{CachedDir, DirReader},
-    loader::Loader,
-    lock::{RwLock, CacheEntry, AssetRef},
+    ContextAsset,
+    GroupReport,
+    budget::Budgets,
+    compound::CompoundAsset,
+    collision::{self, Collision, CollisionPolicy},
+    derived::{self, Definitions},
+    dirs::{CachedDir, DirReader, LazyDir},
+    loader::{LoadContext, Loader},
+    Merge,
+    lock::{Mutex, RwLock, CacheEntry, AssetRef},
+    mount::MountedSource,
+    registry::{Registry, RegistryEntry, RegistryError},
+    report::{DebugReport, Resolution, Stats},
+    scope::{AssetScope, ScopeCounts},
+    section::{self, Section, SectionErr, SectionError},
+    snapshot::Snapshot,
+    source::{DirEntry, FileSystem, Metadata, Source, SymlinkPolicy},
+    subscribers::Subscribers,
+    suggest,
+    untyped::{DynCtor, LoadDynError, TypeRegistry, UntypedRef},
+    warmup::{self, UsageTrace},
 };
 
+#[cfg(feature = "leak-detection")]
+use crate::report::LeakReport;
+
+#[cfg(any(feature = "json", feature = "ron"))]
+use crate::compose::{self, ComposeError};
+
 #[cfg(feature = "hot-reloading")]
 use crate::{
-    lock::Mutex,
-    hot_reloading::{HotReloader, WatchedPaths}
+    hot_reloading::{HotReloadHub, HotReloader, HotReloadStatus, RemovalPolicy, Supervisor, WatchMode, WatchedPaths, DEFAULT_POLL_INTERVAL},
 };
 
 use std::{
     any::TypeId,
-    borrow::Borrow,
-    collections::HashMap,
+    borrow::{Borrow, Cow},
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     fmt,
-    fs,
     io,
     path::{Path, PathBuf},
+    sync::{Arc, atomic::AtomicBool, mpsc::{channel, Receiver}},
+};
+
+#[cfg(feature = "hot-reloading")]
+use std::{
+    fs,
+    io::Read,
+    sync::atomic::Ordering,
+    time::Duration,
 };
 
 use crate::RandomState;
 
 
+pub(crate) type AssetMap = HashMap<Key, CacheEntry, RandomState>;
+
+/// The extensions of the files of a directory, by id.
+type FileExts = HashMap<String, Vec<String>, RandomState>;
+
+/// Buffers with a larger capacity are not kept after a read, so loading a big
+/// file once does not keep its memory allocated forever.
+const MAX_KEPT_CAPACITY: usize = 1 << 20;
+
+thread_local! {
+    static READ_BUFFER: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+}
+
+/// Computes the content hash of a file, as given by `AssetRef::content_hash`.
+///
+/// This is 64-bit FNV-1a.
+pub(crate) fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Reads data with `read` and gives it to `f`.
+///
+/// The data is read in a per-thread buffer, so repeated loads do not
+/// allocate a new one each time.
+pub(crate) fn read_with<R>(
+    read: impl FnOnce(&mut Vec<u8>) -> io::Result<()>,
+    f: impl FnOnce(io::Result<&[u8]>) -> R,
+) -> R {
+    READ_BUFFER.with(|buf| match buf.try_borrow_mut() {
+        Ok(mut buf) => {
+            buf.clear();
+            let res = read(&mut buf);
+            let result = f(res.map(|_| &buf[..]));
+
+            if buf.capacity() > MAX_KEPT_CAPACITY {
+                *buf = Vec::new();
+            }
+            result
+        },
+        // The buffer is already in use higher in the stack, so use a new one
+        Err(_) => {
+            let mut buf = Vec::new();
+            let res = read(&mut buf);
+            f(res.map(|_| &buf[..]))
+        },
+    })
+}
+
+/// Reads a file and gives its content to `f`.
+#[cfg(feature = "hot-reloading")]
+pub(crate) fn read_file<R>(path: &Path, f: impl FnOnce(io::Result<&[u8]>) -> R) -> R {
+    read_with(|buf| fs::File::open(path)?.read_to_end(buf).map(|_| ()), f)
+}
+
+
+/// Matches an id against a pattern, where `*` matches anything but `.` and
+/// `**` matches anything.
+fn glob_match(pattern: &[u8], id: &[u8]) -> bool {
+    match pattern {
+        [] => id.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=id.len()).any(|i| glob_match(rest, &id[i..])),
+        [b'*', rest @ ..] => {
+            let end = id.iter().position(|&c| c == b'.').unwrap_or(id.len());
+            (0..=end).any(|i| glob_match(rest, &id[i..]))
+        },
+        [c, rest @ ..] => id.first() == Some(c) && glob_match(rest, &id[1..]),
+    }
+}
+
+
 /// The key used to identify assets
 ///
 /// **Note**: This definition has to kept in sync with [`AccessKey`]'s one.
@@ -41,24 +149,27 @@ pub(crate) struct Key {
 impl Key {
     /// Creates a Key with the given type and id.
     #[inline]
-    fn new<T: Asset>(id: Box<str>) -> Self {
+    pub fn new<T: 'static>(id: Box<str>) -> Self {
         Self {
             id,
             type_id: TypeId::of::<T>(),
         }
     }
 
-    #[cfg(feature = "hot-reloading")]
     #[inline]
     pub fn new_with(id: Box<str>, type_id: TypeId) -> Self {
         Self { id, type_id }
     }
 
-    #[cfg(feature = "hot-reloading")]
     #[inline]
     pub fn id(&self) -> &str {
         &self.id
     }
+
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
 }
 
 /// A borrowed version of [`Key`]
@@ -74,12 +185,27 @@ pub(crate) struct AccessKey<'a> {
 impl<'a> AccessKey<'a> {
     /// Creates an AccessKey for the given type and id.
     #[inline]
-    fn new<T: Asset>(id: &'a str) -> Self {
+    pub fn new<T: 'static>(id: &'a str) -> Self {
         Self {
             id,
             type_id: TypeId::of::<T>(),
         }
     }
+
+    #[inline]
+    pub fn new_with(id: &'a str, type_id: TypeId) -> Self {
+        Self { id, type_id }
+    }
+
+    #[inline]
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
 }
 
 impl<'a> Borrow<AccessKey<'a>> for Key {
@@ -122,6 +248,12 @@ impl fmt::Debug for AccessKey<'_> {
 /// hard links can lead to suprising behaviour (especially with hot-reloading), and
 /// thus should be avoided
 ///
+/// By default, assets are read from the filesystem, but they can also be read
+/// from any [`Source`] with [`with_source`].
+///
+/// [`Source`]: source/trait.Source.html
+/// [`with_source`]: #method.with_source
+///
 /// # Example
 ///
 /// ```
@@ -167,15 +299,46 @@ impl fmt::Debug for AccessKey<'_> {
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
 pub struct AssetCache {
-    path: PathBuf,
+    /// The root of the filesystem, if the source is the filesystem.
+    path: Option<PathBuf>,
+    source: MountedSource,
 
-    pub(crate) assets: RwLock<HashMap<Key, CacheEntry, RandomState>>,
+    pub(crate) assets: RwLock<AssetMap>,
     dirs: RwLock<HashMap<Key, CachedDir, RandomState>>,
+    /// The extensions of files by id, for directories of assets that accept
+    /// any extension.
+    listings: RwLock<HashMap<String, FileExts, RandomState>>,
+    pub(crate) subscribers: Mutex<Subscribers>,
+    types: RwLock<TypeRegistry>,
+    pub(crate) stats: Mutex<Stats>,
+    pub(crate) scopes: Mutex<ScopeCounts>,
+    budgets: RwLock<Budgets>,
+    symlinks: SymlinkPolicy,
+    confined: bool,
+    strict_extensions: bool,
+    panic_on_missing: bool,
+    derived: Mutex<Definitions>,
+    usage: Mutex<Option<UsageTrace>>,
 
     #[cfg(feature = "hot-reloading")]
     reloader: Mutex<Option<HotReloader>>,
     #[cfg(feature = "hot-reloading")]
+    supervisor: Mutex<Supervisor>,
+    #[cfg(feature = "hot-reloading")]
     pub(crate) watched: Mutex<WatchedPaths>,
+    #[cfg(feature = "hot-reloading")]
+    poll_interval: Duration,
+    #[cfg(feature = "hot-reloading")]
+    watch_mode: WatchMode,
+    #[cfg(feature = "hot-reloading")]
+    paused: AtomicBool,
+    #[cfg(feature = "hot-reloading")]
+    pub(crate) removal_policy: RemovalPolicy,
+    collision_policy: CollisionPolicy,
+    /// Entries removed from the cache while references to them may still
+    /// exist. They are dropped when we get a mutable reference to the cache.
+    #[cfg(feature = "hot-reloading")]
+    pub(crate) detached: Mutex<Vec<CacheEntry>>,
 }
 
 impl AssetCache {
@@ -187,62 +350,521 @@ impl AssetCache {
     /// # Errors
     ///
     /// An error will be returned if `path` is not valid readable directory.
+    /// Its message gives the path and the reason why it cannot be used. You
+    /// can use [`new_or_create`] to create the directory if it is missing.
+    ///
+    /// [`new_or_create`]: #method.new_or_create
     #[inline]
     pub fn new<P: AsRef<Path>>(path: P) -> Result<AssetCache, io::Error> {
-        let path = path.as_ref().canonicalize()?;
-        let _ = path.read_dir()?;
+        let source = FileSystem::new(path)?;
+        let path = source.root().to_owned();
+        Ok(Self::build(Some(path), Box::new(source)))
+    }
+
+    /// Creates a new cache, creating the directory given by `path` (and its
+    /// parents) if it does not exist.
+    ///
+    /// This is useful for directories written by the application, such as
+    /// user content or mods.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if the directory cannot be created, or if
+    /// `path` is not valid readable directory.
+    pub fn new_or_create<P: AsRef<Path>>(path: P) -> Result<AssetCache, io::Error> {
+        let path = path.as_ref();
+        if !path.exists() {
+            std::fs::create_dir_all(path).map_err(|err| {
+                io::Error::new(err.kind(), format!("cannot create asset directory {:?}: {}", path, err))
+            })?;
+        }
+        Self::new(path)
+    }
+
+    /// Creates a new cache that reads its assets from the given source.
+    ///
+    /// Such a cache does not watch the filesystem: with a custom source,
+    /// [`reload_changed`] (and [`hot_reload`], when the feature is enabled)
+    /// applies the changes reported by the source instead.
+    ///
+    /// [`reload_changed`]: #method.reload_changed
+    /// [`hot_reload`]: #method.hot_reload
+    pub fn with_source<S: Source + 'static>(source: S) -> AssetCache {
+        Self::build(None, Box::new(source))
+    }
 
-        Ok(AssetCache {
+    fn build(path: Option<PathBuf>, source: Box<dyn Source>) -> AssetCache {
+        AssetCache {
             assets: RwLock::new(HashMap::with_hasher(RandomState::new())),
             dirs: RwLock::new(HashMap::with_hasher(RandomState::new())),
+            listings: RwLock::new(HashMap::with_hasher(RandomState::new())),
+            subscribers: Mutex::new(Subscribers::new()),
+            types: RwLock::new(TypeRegistry::new()),
+            stats: Mutex::new(Stats::new()),
+            scopes: Mutex::new(ScopeCounts::new()),
+            budgets: RwLock::new(Budgets::new()),
+            symlinks: SymlinkPolicy::default(),
+            confined: false,
+            strict_extensions: false,
+            panic_on_missing: false,
+            derived: Mutex::new(Definitions::new()),
+            usage: Mutex::new(None),
             path,
+            source: MountedSource::new(source),
 
             #[cfg(feature = "hot-reloading")]
             reloader: Mutex::new(None),
             #[cfg(feature = "hot-reloading")]
+            supervisor: Mutex::new(Supervisor::new()),
+            #[cfg(feature = "hot-reloading")]
             watched: Mutex::new(WatchedPaths::new()),
-        })
+            #[cfg(feature = "hot-reloading")]
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            #[cfg(feature = "hot-reloading")]
+            watch_mode: WatchMode::default(),
+            #[cfg(feature = "hot-reloading")]
+            paused: AtomicBool::new(false),
+            #[cfg(feature = "hot-reloading")]
+            removal_policy: RemovalPolicy::default(),
+            collision_policy: CollisionPolicy::default(),
+            #[cfg(feature = "hot-reloading")]
+            detached: Mutex::new(Vec::new()),
+        }
     }
 
     /// Gets the path of the cache's root.
     ///
     /// The path is currently given as absolute, but this may change in the future.
+    ///
+    /// If the cache was created with a custom source, the path is empty.
     pub fn path(&self) -> &Path {
-        &self.path
+        match &self.path {
+            Some(path) => path,
+            None => Path::new(""),
+        }
+    }
+
+    /// Changes the directory from which assets are read.
+    ///
+    /// This is useful to switch between sets of assets, for example when the
+    /// player selects another mod profile.
+    ///
+    /// Cached assets whose file has the same content in both directories are
+    /// kept as is. Others are reloaded from the new directory, or removed
+    /// from the cache if they cannot be loaded from it, in which case their
+    /// subscribers are dropped. Sections and assets loaded with a context
+    /// are always removed, as they cannot be reloaded on their own, and
+    /// virtual assets are recomputed.
+    ///
+    /// If hot-reloading was started, it is stopped, and restarts on the new
+    /// directory at the next call to [`hot_reload`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given path is not a valid readable
+    /// directory. In this case, the cache is left unchanged.
+    ///
+    /// [`hot_reload`]: #method.hot_reload
+    pub fn set_root<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let mut source = FileSystem::new(path)?;
+        source.set_symlink_policy(self.symlinks);
+        source.set_confined(self.confined);
+        let root = source.root().to_owned();
+
+        #[cfg(feature = "hot-reloading")]
+        {
+            self.reloader.get_mut().take();
+            self.supervisor.get_mut().reset();
+            let watched = self.watched.get_mut();
+            match &self.path {
+                Some(old_root) => watched.rebase(old_root, &root),
+                None => watched.clear(),
+            }
+            watched.set_filesystem(&source);
+        }
+
+        let old_source = self.source.replace_base(Box::new(source));
+        self.path = Some(root);
+        self.dirs.get_mut().clear();
+        self.listings.get_mut().clear();
+
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+        {
+            let types = self.types.get_mut();
+            let derived = self.derived.get_mut();
+            let mut old = Vec::new();
+            let mut new = Vec::new();
+
+            for key in self.assets.get_mut().keys() {
+                let (ext, reload) = match types.kind(key.type_id()) {
+                    Some(kind) => kind,
+                    // Virtual assets are recomputed below. Other entries
+                    // (sections and assets loaded with a context) cannot be
+                    // reloaded from the new source.
+                    None => {
+                        if !derived.contains(key.borrow()) {
+                            removed.push(Key::new_with(key.id().into(), key.type_id()));
+                        }
+                        continue;
+                    },
+                };
+
+                old.clear();
+                new.clear();
+                let unchanged = ext != ANY_EXTENSION
+                    && old_source.read(key.id(), ext, &mut old).is_ok()
+                    && self.source.read(key.id(), ext, &mut new).is_ok()
+                    && old == new;
+
+                if !unchanged {
+                    changed.push((key.id().to_owned(), key.type_id(), reload));
+                }
+            }
+        }
+
+        for (id, type_id, reload) in changed {
+            if !reload(self, &id) {
+                removed.push(Key::new_with(id.into(), type_id));
+            }
+        }
+        self.remove_entries(&removed);
+
+        self.derived.get_mut().invalidate_all();
+        self.update_virtuals();
+
+        Ok(())
+    }
+
+    /// Sets what to do with symbolic links in the asset directory.
+    ///
+    /// See [`SymlinkPolicy`] for the available options. The default is to
+    /// follow them. The policy is applied to later loads, directory reads and
+    /// hot-reloading, so it should be set before loading assets.
+    ///
+    /// This function does nothing for caches created with a custom source:
+    /// in this case, the policy should be set on the [`FileSystem`] itself.
+    ///
+    /// [`SymlinkPolicy`]: source/enum.SymlinkPolicy.html
+    /// [`FileSystem`]: source/struct.FileSystem.html
+    pub fn set_symlink_policy(&mut self, policy: SymlinkPolicy) {
+        self.symlinks = policy;
+        self.update_filesystem();
+    }
+
+    /// Sets whether assets must be confined to the asset directory.
+    ///
+    /// See [`FileSystem::set_confined`] for more details. This is disabled by
+    /// default, and is applied to later loads, directory reads and
+    /// hot-reloading.
+    ///
+    /// This function does nothing for caches created with a custom source.
+    ///
+    /// [`FileSystem::set_confined`]: source/struct.FileSystem.html#method.set_confined
+    pub fn set_confined(&mut self, confined: bool) {
+        self.confined = confined;
+        self.update_filesystem();
+    }
+
+    /// Sets whether several files with the same id are rejected for assets
+    /// that accept any extension.
+    ///
+    /// When the extension of a type of asset is [`ANY_EXTENSION`], its file is
+    /// the one with the given id, whatever its extension. If there are several
+    /// such files (e.g. `hero.png` and `hero.jpg`), the first one in
+    /// alphabetical order is used by default. When this mode is enabled,
+    /// loading the asset fails instead, with an error listing all the files,
+    /// which helps to catch mistakes when merging content.
+    ///
+    /// This is disabled by default, and has no effect on other types.
+    ///
+    /// [`ANY_EXTENSION`]: constant.ANY_EXTENSION.html
+    pub fn set_strict_extensions(&mut self, strict: bool) {
+        self.strict_extensions = strict;
+    }
+
+    /// Sets whether loading a missing asset panics.
+    ///
+    /// When enabled, an asset whose file does not exist and whose loader
+    /// fails because of that makes the cache panic instead of returning an
+    /// error. The message of the panic suggests the closest file in the same
+    /// directory, which helps to spot typos in ids. Loaders that accept
+    /// missing files, such as [`LoadOrDefault`], are not affected.
+    ///
+    /// This is meant for development, and is disabled by default. It can be
+    /// enabled only for debug builds:
+    ///
+    /// ```no_run
+    /// # use assets_manager::AssetCache;
+    /// let mut cache = AssetCache::new("assets")?;
+    /// cache.set_panic_on_missing(cfg!(debug_assertions));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`LoadOrDefault`]: loader/struct.LoadOrDefault.html
+    pub fn set_panic_on_missing(&mut self, panic: bool) {
+        self.panic_on_missing = panic;
+    }
+
+    /// Applies the options of the filesystem to its source.
+    fn update_filesystem(&mut self) {
+        let root = match &self.path {
+            Some(root) => root.clone(),
+            None => return,
+        };
+
+        let mut source = FileSystem::from_canonical_root(root);
+        source.set_symlink_policy(self.symlinks);
+        source.set_confined(self.confined);
+
+        #[cfg(feature = "hot-reloading")]
+        self.watched.get_mut().set_filesystem(&source);
+
+        self.source.replace_base(Box::new(source));
+        self.dirs.get_mut().clear();
+        self.listings.get_mut().clear();
+    }
+
+    /// Mounts a source under an id prefix.
+    ///
+    /// Assets whose id is `prefix` or starts with `prefix.` are then read from
+    /// `source`, with the prefix removed from their id. For example, after
+    /// `cache.mount("dlc1", source)`, `"dlc1.maps.forest"` is read as
+    /// `"maps.forest"` from `source`. This allows content packs to be mapped
+    /// to distinct namespaces at runtime.
+    ///
+    /// Assets and directories under the prefix are removed from the cache, as
+    /// they were read from another source. If several mounted prefixes match
+    /// an id, the last mounted one is used.
+    ///
+    /// Changes reported by the mounted source are applied by
+    /// [`reload_changed`], but mounted assets are not reloaded by
+    /// [`hot_reload`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix` is empty.
+    ///
+    /// [`reload_changed`]: #method.reload_changed
+    /// [`hot_reload`]: #method.hot_reload
+    pub fn mount<S: Source + 'static>(&mut self, prefix: &str, source: S) {
+        assert!(!prefix.is_empty(), "cannot mount a source with an empty prefix");
+
+        self.source.mount(prefix, Box::new(source));
+        self.invalidate_prefix(prefix);
+    }
+
+    /// Unmounts the last source mounted at `prefix` with [`mount`].
+    ///
+    /// All assets and directories under the prefix are removed from the cache
+    /// at once, and subscribers of removed assets are dropped, which closes
+    /// the channels returned by [`updates`]. As this function takes a mutable
+    /// reference, no [`AssetRef`] to a removed asset can still exist.
+    ///
+    /// Assets under the prefix will then be read from the previous source
+    /// that contains them.
+    ///
+    /// Returns `false` if no source was mounted at `prefix`.
+    ///
+    /// [`mount`]: #method.mount
+    /// [`updates`]: #method.updates
+    /// [`AssetRef`]: struct.AssetRef.html
+    pub fn unmount(&mut self, prefix: &str) -> bool {
+        if self.source.unmount(prefix).is_none() {
+            return false;
+        }
+
+        self.invalidate_prefix(prefix);
+        true
+    }
+
+    /// Removes all assets and directories whose id is `prefix` or starts with
+    /// `prefix.` from the cache.
+    ///
+    /// Subscribers of removed assets are dropped, which closes the channels
+    /// returned by [`updates`].
+    ///
+    /// [`updates`]: #method.updates
+    fn invalidate_prefix(&mut self, prefix: &str) {
+        self.drop_detached();
+
+        let is_under = |id: &str| {
+            id == prefix || (id.starts_with(prefix) && id.as_bytes()[prefix.len()] == b'.')
+        };
+
+        let removed: Vec<Key> = self.assets.get_mut().keys()
+            .filter(|key| is_under(key.id()))
+            .map(|key| Key::new_with(key.id().into(), key.type_id()))
+            .collect();
+        self.remove_entries(&removed);
+
+        self.dirs.get_mut().retain(|key, _| !is_under(key.id()));
+        self.listings.get_mut().clear();
+        self.derived.get_mut().retain(|key| !is_under(key.id()));
+
+        #[cfg(feature = "hot-reloading")]
+        self.watched.get_mut().remove_under(prefix);
+    }
+
+    /// Removes the given assets from the cache, and returns how many of them
+    /// were there.
+    ///
+    /// Everything attached to them is removed too: their subscribers, their
+    /// definition if they are virtual assets and their watched files.
+    fn remove_entries(&mut self, keys: &[Key]) -> usize {
+        let assets = self.assets.get_mut();
+        let count = keys.iter().filter(|key| assets.remove(*key).is_some()).count();
+        self.forget_entries(keys);
+        count
+    }
+
+    /// Removes what is attached to assets that are no longer in the cache.
+    ///
+    /// This is also used by the hot-reloading thread, which only has a shared
+    /// reference to the cache.
+    pub(crate) fn forget_entries(&self, keys: &[Key]) {
+        let mut subscribers = self.subscribers.lock();
+        let mut derived = self.derived.lock();
+        let mut scopes = self.scopes.lock();
+        for key in keys {
+            subscribers.remove(key.borrow());
+            derived.remove(key.borrow());
+            scopes.remove(key);
+        }
+
+        #[cfg(feature = "hot-reloading")]
+        self.watched.lock().remove_keys(keys);
+    }
+
+    /// Gets the source from which assets are read.
+    pub fn source(&self) -> &dyn Source {
+        &self.source
+    }
+
+    /// Returns informations about the file of an asset in the cache's source,
+    /// such as its size and its last modification time.
+    ///
+    /// This is useful for tools that display when an asset was last edited,
+    /// or to implement custom staleness checks. The file is not loaded.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file does not exist in the source.
+    pub fn source_metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        self.source.metadata(id, ext)
+    }
+
+    /// Explains how an asset of type `A` would be loaded, without loading
+    /// it.
+    ///
+    /// This gives the file that would be read, taking into account mounted
+    /// sources and extensions of types that have none, along with the loader
+    /// that would be used. This is useful for diagnostics, for example to
+    /// understand why an asset does not come from the expected file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use assets_manager::{Asset, AssetCache, loader};
+    ///
+    /// # struct X(i32);
+    /// # impl From<i32> for X { fn from(x: i32) -> X { X(x) } }
+    /// # impl Asset for X {
+    /// #     const EXT: &'static str = "x";
+    /// #     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+    /// # }
+    /// let cache = AssetCache::new("assets")?;
+    ///
+    /// let resolution = cache.resolve::<X>("test.cache");
+    /// assert!(resolution.path.unwrap().ends_with("test/cache.x"));
+    /// assert!(resolution.metadata.is_some());
+    /// assert!(!resolution.cached);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn resolve<A: Asset>(&self, id: &str) -> Resolution {
+        let ext = self.ext_of(id, A::EXT).into_owned();
+        let mount = self.source.mount_of(id).map(String::from);
+        let path = match (&self.path, &mount) {
+            (Some(_), None) => Some(self.path_of(id, &ext)),
+            _ => None,
+        };
+
+        Resolution {
+            id: id.to_owned(),
+            loader: std::any::type_name::<A::Loader>(),
+            path,
+            mount,
+            metadata: self.source.metadata(id, &ext).ok(),
+            cached: self.load_cached::<A>(id).is_some(),
+            ext,
+        }
     }
 
     pub(crate) fn path_of(&self, id: &str, ext: &str) -> PathBuf {
-        let mut path = self.path.clone();
+        let mut path = self.path().to_owned();
         path.extend(id.split('.'));
         path.set_extension(ext);
         path
     }
 
     /// Adds an asset to the cache
-    pub(crate) fn add_asset<A: Asset>(&self, id: String) -> Result<AssetRef<A>, AssetErr<A>> {
-        let path = self.path_of(&id, A::EXT);
-        let asset: A = self.load_from_fs(&path)?;
+    pub(crate) fn add_asset<A: Asset>(&self, id: String) -> Result<AssetRef<'_, A>, AssetErr<A>> {
+        let (asset, hash, ext) = self.load_new::<A>(&id)?;
+        Ok(self.insert_loaded(id, &ext, asset, hash))
+    }
 
-        let entry = CacheEntry::new(asset);
-        // Safety:
-        // We just created the asset with the good type
-        // The cache entry is garantied to live long enough
-        let asset = unsafe { entry.get_ref() };
+    /// Reads the raw content of an asset, to be loaded later with
+    /// `add_raw`.
+    ///
+    /// Returns the extension of the file along with its content.
+    pub(crate) fn read_raw<A: Asset>(&self, id: &str) -> (String, io::Result<Vec<u8>>) {
+        let ext = match self.checked_ext_of(id, A::EXT) {
+            Ok(ext) => ext.into_owned(),
+            Err(err) => return (String::new(), Err(err)),
+        };
+        let mut buf = Vec::new();
+        let content = self.source.read(id, &ext, &mut buf).map(|_| buf);
+        (ext, content)
+    }
+
+    /// Adds an asset to the cache from content read with `read_raw`.
+    pub(crate) fn add_raw<A: Asset>(&self, id: String, ext: &str, content: io::Result<Vec<u8>>) -> Result<AssetRef<'_, A>, AssetErr<A>> {
+        let hash = content.as_ref().ok().map(|content| content_hash(content));
+        let asset: A = self.decode(&id, ext, content.as_ref().map(|content| &content[..]))?;
+        Ok(self.insert_loaded(id, ext, asset, hash))
+    }
+
+    /// Stores a newly loaded asset in the cache.
+    fn insert_loaded<A: Asset>(&self, id: String, ext: &str, asset: A, hash: Option<u64>) -> AssetRef<'_, A> {
+        #[cfg(not(feature = "hot-reloading"))]
+        let _ = ext;
+
+        self.stats.lock().add_asset_type::<A>();
+        self.register_kind::<A>();
+        self.record_usage_of::<A>(&id);
 
         #[cfg(feature = "hot-reloading")]
-        {
+        if self.path.is_some() && !self.source.is_mounted(&id) {
+            let path = self.path_of(&id, ext);
             let mut watched = self.watched.lock();
             watched.add::<A>(path, id.clone());
         }
 
         let key = Key::new::<A>(id.into());
         let mut cache = self.assets.write();
-        cache.insert(key, entry);
 
-        Ok(asset)
+        // Another thread may have loaded the same asset in the meantime. In
+        // this case, we must keep the existing entry, as references to it may
+        // already exist.
+        let entry = cache.entry(key).or_insert_with(|| CacheEntry::with_hash(asset, hash));
+
+        // Safety:
+        // The entry was created with the good type
+        // The cache entry is garantied to live long enough
+        unsafe { entry.get_ref() }
     }
 
-    fn add_dir<A: Asset>(&self, id: String) -> Result<DirReader<A>, io::Error> {
+    fn add_dir<A: Asset>(&self, id: String) -> Result<DirReader<'_, A>, io::Error> {
         let dir = CachedDir::load::<A>(self, &id)?;
         let reader = unsafe { dir.read(self) };
 
@@ -262,9 +884,16 @@ impl AssetCache {
     /// Errors can occur in several cases :
     /// - The asset could not be loaded from the filesystem
     /// - Loaded data could not not be converted properly
-    pub fn load<A: Asset>(&self, id: &str) -> Result<AssetRef<A>, AssetErr<A>> {
+    pub fn load<A: Asset>(&self, id: &str) -> Result<AssetRef<'_, A>, AssetErr<A>> {
+        derived::record_read(&AccessKey::new::<A>(id));
+
         match self.load_cached(id) {
-            Some(asset) => Ok(asset),
+            Some(asset) => {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(id, ty = std::any::type_name::<A>(), "cache hit");
+
+                Ok(asset)
+            },
             None => self.add_asset(id.to_string()),
         }
     }
@@ -273,7 +902,7 @@ impl AssetCache {
     ///
     /// This function does not attempt to load the asset from the filesystem if
     /// it is not found in the cache.
-    pub fn load_cached<A: Asset>(&self, id: &str) -> Option<AssetRef<A>> {
+    pub fn load_cached<A: Asset>(&self, id: &str) -> Option<AssetRef<'_, A>> {
         let key = AccessKey::new::<A>(id);
         let cache = self.assets.read();
         cache.get(&key).map(|asset| unsafe { asset.get_ref() })
@@ -287,13 +916,68 @@ impl AssetCache {
     ///
     /// [`load`]: fn.load.html
     #[inline]
-    pub fn load_expect<A: Asset>(&self, id: &str) -> AssetRef<A>
+    pub fn load_expect<A: Asset>(&self, id: &str) -> AssetRef<'_, A>
     where
         AssetErr<A>: fmt::Debug,
     {
         self.load(id).expect("Could not load essential asset")
     }
 
+    /// Loads several assets and merges them in order.
+    ///
+    /// Each asset is loaded with [`load`] and merged into the previous ones
+    /// with [`Merge::merge`], so later assets override earlier ones. This is
+    /// useful for entity prototypes and their variants.
+    ///
+    /// The result is not stored in the cache. Call this function in
+    /// [`define`] to get a virtual asset updated when one of the merged
+    /// assets is reloaded.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if one of the assets could not be loaded (see
+    /// [`load`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ids` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # cfg_if::cfg_if! { if #[cfg(feature = "json")] {
+    /// use assets_manager::{AssetCache, JsonValue, source::MemorySource};
+    ///
+    /// let source = MemorySource::new();
+    /// source.insert("base.enemy", "json", r#"{"hp": 10, "stats": {"speed": 1, "armor": 2}}"#);
+    /// source.insert("enemies.goblin", "json", r#"{"stats": {"speed": 3}}"#);
+    /// let cache = AssetCache::with_source(source);
+    ///
+    /// let goblin = cache.load_merged::<JsonValue>(&["base.enemy", "enemies.goblin"])?;
+    /// assert_eq!(goblin["hp"], 10);
+    /// assert_eq!(goblin["stats"]["speed"], 3);
+    /// assert_eq!(goblin["stats"]["armor"], 2);
+    /// # }}
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`load`]: #method.load
+    /// [`Merge::merge`]: trait.Merge.html#tymethod.merge
+    /// [`define`]: #method.define
+    pub fn load_merged<A>(&self, ids: &[&str]) -> Result<A, AssetErr<A>>
+    where
+        A: Asset + Merge + Clone,
+    {
+        let (first, rest) = ids.split_first().expect("no asset to merge");
+
+        let mut merged = self.load::<A>(first)?.cloned();
+        for id in rest {
+            merged.merge(self.load::<A>(id)?.cloned());
+        }
+
+        Ok(merged)
+    }
+
     /// Reloads an asset from the filesystem.
     ///
     /// It does not matter whether the asset has been loaded yet.
@@ -310,71 +994,1575 @@ impl AssetCache {
     /// If an error occurs, the asset is left unmodified.
     ///
     /// [`load`]: fn.load.html
-    pub fn force_reload<A: Asset>(&self, id: &str) -> Result<AssetRef<A>, AssetErr<A>> {
-        let cache = self.assets.read();
-        if let Some(cached) = cache.get(&AccessKey::new::<A>(id)) {
-            let path = self.path_of(id, A::EXT);
-            let asset = self.load_from_fs(&path)?;
-            return unsafe { Ok(cached.write(asset)) };
-        }
-        drop(cache);
+    pub fn force_reload<A: Asset>(&self, id: &str) -> Result<AssetRef<'_, A>, AssetErr<A>> {
+        let reloaded = self.reload_entry(id, || {
+            let (asset, hash, _) = self.load_new::<A>(id)?;
+            Ok((asset, hash))
+        });
 
-        self.add_asset(id.to_string())
+        match reloaded {
+            Some(result) => result,
+            None => self.add_asset(id.to_string()),
+        }
     }
 
-    fn load_from_fs<A: Asset>(&self, path: &Path) -> Result<A, AssetErr<A>> {
-        let content = fs::read(&path).map(Into::into);
-        A::Loader::load(content)
+    /// Loads again an asset that is in the cache with `load`, and replaces
+    /// its value, notifying its subscribers and updating virtual assets.
+    ///
+    /// Returns `None` if the asset is not in the cache.
+    fn reload_entry<A, E>(&self, id: &str, load: impl FnOnce() -> Result<(A, Option<u64>), E>) -> Option<Result<AssetRef<'_, A>, E>>
+    where
+        A: Send + Sync + 'static,
+    {
+        let key = AccessKey::new::<A>(id);
+        let cache = self.assets.read();
+        let cached = cache.get(&key)?;
+
+        let result = load().map(|(asset, hash)| unsafe {
+            let asset = cached.write(asset, hash);
+            self.subscribers.lock().notify(&key, cached, &cache);
+            asset
+        });
+        drop(cache);
+
+        if result.is_ok() {
+            self.update_virtuals();
+        }
+        Some(result)
     }
 
-    /// Load all assets of a given type in a directory.
+    /// Loads an asset that needs a context to be loaded.
     ///
-    /// The directory's id is constructed the same way as assets. To specify
-    /// the cache's root, give the empty string (`""`) as id.
+    /// If the asset is not found in the cache, it is loaded from the source
+    /// with [`ContextAsset::load_with`], which is given `context`.
     ///
-    /// The returned structure can be iterated on to get the loaded assets.
+    /// Such assets are not reloaded by hot-reloading, because the context is
+    /// not available then. Use [`reload_with`] instead.
     ///
-    /// # Error
+    /// # Errors
     ///
-    /// An error is returned if the given id does not match a valid readable
-    /// directory.
-    pub fn load_dir<A: Asset>(&self, id: &str) -> io::Result<DirReader<A>> {
-        let dirs = self.dirs.read();
-        if let Some(dir) = dirs.get(&AccessKey::new::<A>(id)) {
-            return unsafe { Ok(dir.read(self)) };
-        }
-        drop(dirs);
-
-        self.add_dir(id.to_string())
-    }
+    /// Error cases are the same as [`load`].
+    ///
+    /// [`ContextAsset::load_with`]: trait.ContextAsset.html#tymethod.load_with
+    /// [`reload_with`]: #method.reload_with
+    /// [`load`]: #method.load
+    pub fn load_with<A, C>(&self, id: &str, context: &C) -> Result<AssetRef<'_, A>, A::Err>
+    where
+        A: ContextAsset<C>,
+        C: ?Sized,
+    {
+        let key = AccessKey::new::<A>(id);
+        derived::record_read(&key);
+
+        if let Some(entry) = self.assets.read().get(&key) {
+            return Ok(unsafe { entry.get_ref() });
+        }
+
+        let asset = self.load_with_context::<A, C>(id, context)?;
+        self.stats.lock().add_type::<A>();
+
+        let mut assets = self.assets.write();
+        let entry = assets.entry(Key::new::<A>(id.into())).or_insert_with(|| CacheEntry::new(asset));
+
+        // Safety: the entry was created with the good type
+        Ok(unsafe { entry.get_ref() })
+    }
+
+    /// Reloads an asset that needs a context to be loaded.
+    ///
+    /// This is the same as [`force_reload`], but for assets loaded with
+    /// [`load_with`]. The same warnings about deadlocks apply.
+    ///
+    /// # Errors
+    ///
+    /// Error cases are the same as [`load`]. If an error occurs, the asset is
+    /// left unmodified.
+    ///
+    /// [`force_reload`]: #method.force_reload
+    /// [`load_with`]: #method.load_with
+    /// [`load`]: #method.load
+    pub fn reload_with<A, C>(&self, id: &str, context: &C) -> Result<AssetRef<'_, A>, A::Err>
+    where
+        A: ContextAsset<C>,
+        C: ?Sized,
+    {
+        let reloaded = self.reload_entry(id, || {
+            let asset = self.load_with_context::<A, C>(id, context)?;
+            Ok((asset, None))
+        });
+
+        match reloaded {
+            Some(result) => result,
+            None => self.load_with(id, context),
+        }
+    }
+
+    /// Loads an asset made of several files with the same id.
+    ///
+    /// If the asset is not found in the cache, it is loaded with
+    /// [`CompoundAsset::load`], which reads its main file and its siblings
+    /// through a [`LoadContext`].
+    ///
+    /// All files read while loading the asset are watched by hot-reloading,
+    /// and a change of any of them loads the asset again. Likewise,
+    /// [`reload_changed`] loads it again when any file with its id changes.
+    ///
+    /// # Errors
+    ///
+    /// Errors returned by [`CompoundAsset::load`] are returned as is.
+    ///
+    /// [`CompoundAsset::load`]: trait.CompoundAsset.html#tymethod.load
+    /// [`LoadContext`]: struct.LoadContext.html
+    /// [`reload_changed`]: #method.reload_changed
+    pub fn load_compound<A: CompoundAsset>(&self, id: &str) -> Result<AssetRef<'_, A>, A::Err> {
+        if let Some(entry) = self.assets.read().get(&AccessKey::new::<A>(id)) {
+            return Ok(unsafe { entry.get_ref() });
+        }
+
+        let asset = self.load_compound_from_source::<A>(id)?;
+        self.stats.lock().add_type::<A>();
+        if !self.types.read().has_kind::<A>() {
+            self.types.write().add_compound_kind::<A>();
+        }
+
+        let mut assets = self.assets.write();
+        let entry = assets.entry(Key::new::<A>(id.into())).or_insert_with(|| CacheEntry::new(asset));
+
+        // Safety: the entry was created with the good type
+        Ok(unsafe { entry.get_ref() })
+    }
+
+    /// Loads a cached compound asset again, returning `true` on success.
+    pub(crate) fn reload_compound<A: CompoundAsset>(&self, id: &str) -> bool {
+        let key = AccessKey::new::<A>(id);
+        let assets = self.assets.read();
+        let cached = match assets.get(&key) {
+            Some(cached) => cached,
+            None => return false,
+        };
+
+        match self.load_compound_from_source::<A>(id) {
+            Ok(asset) => {
+                unsafe {
+                    cached.write(asset, None);
+                    self.subscribers.lock().notify(&key, cached, &assets);
+                }
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    fn load_compound_from_source<A: CompoundAsset>(&self, id: &str) -> Result<A, A::Err> {
+        let read = |ext: &str, buf: &mut Vec<u8>| self.source.read(id, ext, buf);
+        let context = LoadContext::new(id, A::EXT, &read);
+        let result = A::load(&context);
+
+        if result.is_err() {
+            self.stats.lock().load_error();
+        }
+
+        let (dependencies, warnings) = context.finish();
+        self.report_warnings(id, warnings);
+
+        #[cfg(feature = "hot-reloading")]
+        self.watch_dependencies(id, &dependencies, WatchedPaths::add_compound::<A>);
+        #[cfg(not(feature = "hot-reloading"))]
+        let _ = dependencies;
+
+        result
+    }
+
+    fn load_with_context<A, C>(&self, id: &str, context: &C) -> Result<A, A::Err>
+    where
+        A: ContextAsset<C>,
+        C: ?Sized,
+    {
+        let result = match self.checked_ext_of(id, A::EXT) {
+            Ok(ext) => read_with(
+                |buf| self.source.read(id, &ext, buf),
+                |content| A::load_with(content.map(Into::into), context),
+            ),
+            Err(err) => A::load_with(Err(err), context),
+        };
+
+        if result.is_err() {
+            self.stats.lock().load_error();
+        }
+
+        result
+    }
+
+    /// Gets the extension of the file to load for an asset whose type has
+    /// the extension `ext`.
+    ///
+    /// If `ext` is `ANY_EXTENSION`, a file without extension is used if it
+    /// exists, and otherwise the first file with the given id in the
+    /// directory.
+    fn ext_of(&self, id: &str, ext: &'static str) -> Cow<'static, str> {
+        if ext != ANY_EXTENSION {
+            return Cow::Borrowed(ext);
+        }
+
+        match self.exts_of(id).into_iter().next() {
+            Some(ext) => Cow::Owned(ext),
+            None => Cow::Borrowed(""),
+        }
+    }
+
+    /// Same as `ext_of`, but returns an error if the cache rejects ambiguous
+    /// extensions and several files have the given id.
+    fn checked_ext_of(&self, id: &str, ext: &'static str) -> io::Result<Cow<'static, str>> {
+        if ext != ANY_EXTENSION || !self.strict_extensions {
+            return Ok(self.ext_of(id, ext));
+        }
+
+        let mut exts = self.exts_of(id);
+        if exts.len() > 1 {
+            let files: Vec<_> = exts.iter().map(|ext| match &self.path {
+                Some(_) if !self.source.is_mounted(id) => format!("{:?}", self.path_of(id, ext)),
+                _ => format!("\"{}.{}\"", id, ext),
+            }).collect();
+            let message = format!("several files for asset \"{}\": {}", id, files.join(", "));
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        Ok(exts.pop().map_or(Cow::Borrowed(""), Cow::Owned))
+    }
+
+    /// Lists the extensions of the files with the given id, in alphabetical
+    /// order.
+    ///
+    /// The files of the directory are listed once, and then kept until the
+    /// next changes are applied.
+    fn exts_of(&self, id: &str) -> Vec<String> {
+        let dir = match id.rfind('.') {
+            Some(pos) => &id[..pos],
+            None => "",
+        };
+
+        if let Some(files) = self.listings.read().get(dir) {
+            return files.get(id).cloned().unwrap_or_default();
+        }
+
+        let mut files = FileExts::default();
+        let result = self.source.read_dir(dir, &mut |entry| {
+            if let DirEntry::File(entry_id, ext) = entry {
+                files.entry(entry_id.to_owned()).or_default().push(ext.to_owned());
+            }
+        });
+        for exts in files.values_mut() {
+            exts.sort_unstable();
+        }
+
+        let exts = files.get(id).cloned().unwrap_or_default();
+        if result.is_ok() {
+            self.listings.write().insert(dir.to_owned(), files);
+        }
+        exts
+    }
+
+    /// Loads an asset that is not in the cache yet.
+    ///
+    /// Returns the asset along with the hash of its content and the
+    /// extension of its file.
+    fn load_new<A: Asset>(&self, id: &str) -> Result<(A, Option<u64>, Cow<'static, str>), AssetErr<A>> {
+        match self.checked_ext_of(id, A::EXT) {
+            Ok(ext) => {
+                let (asset, hash) = self.load_from_source::<A>(id, &ext)?;
+                Ok((asset, hash, ext))
+            },
+            Err(err) => {
+                let asset = self.decode(id, "", Err(&err))?;
+                Ok((asset, None, Cow::Borrowed("")))
+            },
+        }
+    }
+
+    /// Loads an asset from the source, along with the hash of its content.
+    fn load_from_source<A: Asset>(&self, id: &str, ext: &str) -> Result<(A, Option<u64>), AssetErr<A>> {
+        read_with(
+            |buf| self.source.read(id, ext, buf),
+            |content| {
+                let hash = content.as_ref().ok().map(|content| content_hash(content));
+                let asset = self.decode(id, ext, content.as_ref().map(|content| *content))?;
+                Ok((asset, hash))
+            },
+        )
+    }
+
+    /// Loads an asset from the source without any effect on the cache.
+    ///
+    /// Unlike `load_from_source`, this does not update statistics, watch
+    /// files or report warnings, so it can be used for validation.
+    fn check_from_source<A: Asset>(&self, id: &str, ext: &str) -> Result<A, AssetErr<A>> {
+        read_with(
+            |buf| self.source.read(id, ext, buf),
+            |content| {
+                let read = |ext: &str, buf: &mut Vec<u8>| self.source.read(id, ext, buf);
+                let context = LoadContext::new(id, ext, &read).with_content(content.as_ref().map(|content| *content));
+                A::Loader::load_with_context(&context)
+            },
+        )
+    }
+
+    /// Converts the content of a file into an asset.
+    fn decode<A: Asset>(&self, id: &str, ext: &str, content: Result<&[u8], &io::Error>) -> Result<A, AssetErr<A>> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("load_asset", id, ty = std::any::type_name::<A>());
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        #[cfg(feature = "rich-errors")]
+        let enriched = content.err().and_then(|err| suggest::enrich_error(&self.source, id, ext, err));
+        #[cfg(feature = "rich-errors")]
+        let content = enriched.as_ref().map_or(content, Err);
+
+        let read = |ext: &str, buf: &mut Vec<u8>| self.source.read(id, ext, buf);
+        let context = LoadContext::new(id, ext, &read).with_content(content);
+        let result = A::Loader::load_with_context(&context);
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(_) => tracing::trace!("asset loaded"),
+            Err(err) => tracing::warn!(error = %err, "failed to load asset"),
+        }
+
+        if result.is_err() {
+            if self.panic_on_missing && matches!(content, Err(err) if err.kind() == io::ErrorKind::NotFound) {
+                panic!("{}", suggest::missing_message(&self.source, id, ext, std::any::type_name::<A>()));
+            }
+            self.stats.lock().load_error();
+        }
+
+        let (dependencies, warnings) = context.finish();
+        self.report_warnings(id, warnings);
+
+        // Dependencies are reloaded from the main file, so its extension
+        // must be known.
+        #[cfg(feature = "hot-reloading")]
+        if result.is_ok() && A::EXT != ANY_EXTENSION {
+            self.watch_dependencies(id, &dependencies, WatchedPaths::add_dependent::<A>);
+        }
+        #[cfg(not(feature = "hot-reloading"))]
+        let _ = dependencies;
+
+        result
+    }
+
+    /// Reports the warnings of a loader.
+    fn report_warnings(&self, id: &str, warnings: Vec<String>) {
+        if warnings.is_empty() {
+            return;
+        }
+
+        #[cfg(not(any(feature = "hot-reloading", feature = "tracing")))]
+        let _ = id;
+
+        for warning in &warnings {
+            #[cfg(feature = "hot-reloading")]
+            log::warn!("{:?}: {}", id, warning);
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(id, warning = %warning, "loader warning");
+
+            #[cfg(not(any(feature = "hot-reloading", feature = "tracing")))]
+            let _ = warning;
+        }
+
+        self.stats.lock().load_warnings(warnings.len());
+    }
+
+    /// Watches files read by a loader, which are given by their extension.
+    #[cfg(feature = "hot-reloading")]
+    fn watch_dependencies(&self, id: &str, exts: &[String], add: fn(&mut WatchedPaths, PathBuf, String)) {
+        if self.path.is_some() && !self.source.is_mounted(id) {
+            let mut watched = self.watched.lock();
+            for ext in exts {
+                add(&mut watched, self.path_of(id, ext), id.to_owned());
+            }
+        }
+    }
+
+    /// Loads a section of an asset.
+    ///
+    /// The id must be of the form `file#section`: the file is loaded as an
+    /// asset of type `S::Parent` (see [`load`]), and the section is extracted
+    /// from it with [`Section::extract`]. Sections are cached, and updated
+    /// each time their parent is reloaded if their value changed.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the id is not of the form `file#section`, if
+    /// the parent asset could not be loaded or if it has no such section.
+    ///
+    /// [`load`]: #method.load
+    /// [`Section::extract`]: trait.Section.html#tymethod.extract
+    pub fn load_section<S: Section>(&self, id: &str) -> Result<AssetRef<'_, S>, SectionErr<S>> {
+        let key = AccessKey::new::<S>(id);
+        derived::record_read(&key);
+
+        if let Some(entry) = self.assets.read().get(&key) {
+            return Ok(unsafe { entry.get_ref() });
+        }
+
+        // The parent is not recorded as read, so virtual assets only depend
+        // on the section.
+        let (file, name) = section::split_id(id).ok_or_else(|| SectionError::InvalidId(id.to_owned()))?;
+        let parent = match self.load_cached::<S::Parent>(file) {
+            Some(parent) => parent,
+            None => self.add_asset(file.to_owned()).map_err(SectionError::Load)?,
+        };
+        let section = S::extract(&parent.read(), name).ok_or_else(|| SectionError::NotFound(id.to_owned()))?;
+
+        let mut assets = self.assets.write();
+        let mut inserted = false;
+
+        // Another thread may have loaded the same section in the meantime.
+        let entry = assets.entry(Key::new::<S>(id.into())).or_insert_with(|| {
+            inserted = true;
+            CacheEntry::new(section)
+        });
+
+        if inserted {
+            self.subscribers.lock().add_section::<S>(file, id);
+        }
+
+        // Safety: the entry was created with the good type
+        Ok(unsafe { entry.get_ref() })
+    }
+
+    /// Loads all sections of an asset.
+    ///
+    /// The asset with the given id is loaded as an asset of type `S::Parent`,
+    /// and each section listed by [`Section::section_names`] is loaded with
+    /// [`load_section`]. The returned list contains the id of each section
+    /// (of the form `file#section`) with a reference to it.
+    ///
+    /// Sections are listed each time this function is called, so sections
+    /// added by a reload of the parent are included.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the parent asset could not be loaded.
+    ///
+    /// [`Section::section_names`]: trait.Section.html#method.section_names
+    /// [`load_section`]: #method.load_section
+    pub fn load_sections<S: Section>(&self, id: &str) -> Result<Vec<(String, AssetRef<'_, S>)>, SectionErr<S>> {
+        let parent = self.load::<S::Parent>(id).map_err(SectionError::Load)?;
+        let names = S::section_names(&parent.read());
+
+        let mut sections = Vec::with_capacity(names.len());
+        for name in names {
+            let section_id = format!("{}#{}", id, name);
+            match self.load_section::<S>(&section_id) {
+                Ok(section) => sections.push((section_id, section)),
+                // The parent may have been reloaded in the meantime
+                Err(SectionError::NotFound(_)) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(sections)
+    }
+
+    /// Registers an asset type under the given name.
+    ///
+    /// Assets of this type can then be loaded with [`load_dyn`], which is
+    /// useful for editors or scripting layers that do not know the types of
+    /// the assets they load at compile time.
+    ///
+    /// If another type was already registered with the same name, it is
+    /// replaced.
+    ///
+    /// [`load_dyn`]: #method.load_dyn
+    pub fn register_type<A: Asset>(&self, name: &str) {
+        self.types.write().register::<A>(name);
+    }
+
+    /// Registers `A` as a type of asset that can be loaded as a `Box<T>`.
+    ///
+    /// `T` is typically a trait object, and `into` converts the asset to it
+    /// (e.g. `|goblin| Box::new(goblin)`). Trait objects can then be loaded
+    /// with [`load_as`], which allows data-driven polymorphism without
+    /// listing all possible types in an enum.
+    ///
+    /// Several types can be registered for the same `T`, provided that they
+    /// have different extensions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use assets_manager::{Asset, AssetCache, loader};
+    ///
+    /// trait Value: Send + Sync {
+    ///     fn value(&self) -> i32;
+    /// }
+    ///
+    /// struct X(i32);
+    ///
+    /// impl From<i32> for X {
+    ///     fn from(x: i32) -> X { X(x) }
+    /// }
+    ///
+    /// impl Asset for X {
+    ///     const EXT: &'static str = "x";
+    ///     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+    /// }
+    ///
+    /// impl Value for X {
+    ///     fn value(&self) -> i32 { self.0 }
+    /// }
+    ///
+    /// let cache = AssetCache::new("assets")?;
+    /// cache.register_dyn::<dyn Value, X>(|x| Box::new(x));
+    ///
+    /// let value = cache.load_as::<dyn Value>("test.cache")?;
+    /// assert_eq!(value.read().value(), 42);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`load_as`]: #method.load_as
+    pub fn register_dyn<T, A>(&self, into: fn(A) -> Box<T>)
+    where
+        T: ?Sized + Send + Sync + 'static,
+        A: Asset,
+    {
+        let ctor: DynCtor<T> = Arc::new(move |cache: &AssetCache, id: &str| {
+            let asset = cache.load_new::<A>(id);
+            asset.map(|(asset, _, _)| into(asset)).map_err(|err| err.to_string())
+        });
+        self.types.write().register_dyn::<T, A>(ctor);
+    }
+
+    /// Loads an asset as a trait object, using a type registered with
+    /// [`register_dyn`].
+    ///
+    /// The type used is the first one registered for `T` that has a file
+    /// with the given id. The loaded value is cached as a `Box<T>`, and is
+    /// reloaded by [`reload_changed`] and [`set_root`], but not by
+    /// [`hot_reload`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if no type was registered for `T`, if no
+    /// registered type has a file with the given id, or if the asset could
+    /// not be loaded.
+    ///
+    /// [`register_dyn`]: #method.register_dyn
+    /// [`reload_changed`]: #method.reload_changed
+    /// [`set_root`]: #method.set_root
+    /// [`hot_reload`]: #method.hot_reload
+    pub fn load_as<T>(&self, id: &str) -> Result<AssetRef<'_, Box<T>>, LoadDynError>
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        let key = AccessKey::new::<Box<T>>(id);
+        if let Some(asset) = self.assets.read().get(&key) {
+            return Ok(unsafe { asset.get_ref() });
+        }
+
+        let value = self.load_boxed::<T>(id)?;
+        self.stats.lock().add_type::<Box<T>>();
+
+        let key = Key::new::<Box<T>>(id.into());
+        let mut cache = self.assets.write();
+        let entry = cache.entry(key).or_insert_with(|| CacheEntry::new(value));
+
+        unsafe { Ok(entry.get_ref()) }
+    }
+
+    /// Reloads a cached trait object, returning `true` on success.
+    pub(crate) fn reload_as<T>(&self, id: &str) -> bool
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        let key = AccessKey::new::<Box<T>>(id);
+        if !self.assets.read().contains_key(&key) {
+            return false;
+        }
+
+        let value = match self.load_boxed::<T>(id) {
+            Ok(value) => value,
+            Err(_) => return false,
+        };
+
+        let cache = self.assets.read();
+        match cache.get(&key) {
+            Some(cached) => unsafe {
+                cached.write(value, None);
+                self.subscribers.lock().notify(&key, cached, &cache);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Creates a `Box<T>` from the first registered type that has a file with
+    /// the given id.
+    fn load_boxed<T>(&self, id: &str) -> Result<Box<T>, LoadDynError>
+    where
+        T: ?Sized + Send + Sync + 'static,
+    {
+        // The lock must not be held while loading, as the constructor may
+        // use the cache.
+        let ctors = self.types.read().dyn_ctors::<T>();
+        if ctors.is_empty() {
+            return Err(LoadDynError::UnknownType(std::any::type_name::<T>().to_owned()));
+        }
+
+        let exts = self.exts_of(id);
+        let ctor = ctors.iter().find(|(ext, _)| {
+            if *ext == ANY_EXTENSION {
+                !exts.is_empty()
+            } else {
+                exts.iter().any(|e| e == ext)
+            }
+        });
+
+        match ctor {
+            Some((_, ctor)) => ctor(self, id).map_err(LoadDynError::Load),
+            None => Err(LoadDynError::Load(format!(
+                "no file for {:?} has the extension of a type registered for {}",
+                id, std::any::type_name::<T>(),
+            ))),
+        }
+    }
+
+    /// Starts recording loads in a trace file at `path`.
+    ///
+    /// Each asset loaded from the source from now on is appended to the
+    /// trace, in order. The trace can be given to [`replay_warmup`] on next
+    /// startup to load the same assets upfront, for example behind a loading
+    /// screen, instead of when they are first needed.
+    ///
+    /// Only assets whose type was registered with [`register_type`] are
+    /// recorded, with the name of their type. Assets that were already in the
+    /// cache are not recorded again. If the file already exists, it is
+    /// overwritten.
+    ///
+    /// [`replay_warmup`]: #method.replay_warmup
+    /// [`register_type`]: #method.register_type
+    pub fn record_usage<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let trace = UsageTrace::create(path.as_ref())?;
+        *self.usage.lock() = Some(trace);
+        Ok(())
+    }
+
+    /// Stops recording loads started with [`record_usage`].
+    ///
+    /// [`record_usage`]: #method.record_usage
+    pub fn stop_recording_usage(&self) {
+        *self.usage.lock() = None;
+    }
+
+    /// Loads the assets of a trace recorded with [`record_usage`], in the
+    /// order in which they were first loaded.
+    ///
+    /// Types must be registered with [`register_type`] before calling this
+    /// function. Assets that cannot be loaded, for example because their file
+    /// was removed since the trace was recorded, are skipped.
+    ///
+    /// Returns the number of assets of the trace that were loaded.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the trace cannot be read.
+    ///
+    /// [`record_usage`]: #method.record_usage
+    /// [`register_type`]: #method.register_type
+    pub fn replay_warmup<P: AsRef<Path>>(&self, trace: P) -> io::Result<usize> {
+        let entries = warmup::read_trace(trace.as_ref())?;
+
+        let mut loaded = 0;
+        for (type_name, id) in entries {
+            match self.load_dyn(&type_name, &id) {
+                Ok(_) => loaded += 1,
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(id = &*id, ty = &*type_name, error = %_err, "skipped asset of warmup trace");
+                },
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Appends a newly loaded asset to the usage trace, if any.
+    fn record_usage_of<A: Asset>(&self, id: &str) {
+        let mut usage = self.usage.lock();
+        if let Some(trace) = &mut *usage {
+            let types = self.types.read();
+            if let Some(type_name) = types.name_of(TypeId::of::<A>()) {
+                if let Err(_err) = trace.record(type_name, id) {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %_err, "failed to write usage trace, recording stopped");
+
+                    *usage = None;
+                }
+            }
+        }
+    }
+
+    /// Returns the name of a type of loaded assets with the given extension.
+    pub(crate) fn type_name_for_ext(&self, ext: &str) -> Option<&'static str> {
+        let type_id = self.types.read().kind_with_ext(ext)?;
+        Some(self.stats.lock().type_name(type_id))
+    }
+
+    /// Remembers how to reload assets of type `A`.
+    fn register_kind<A: Asset>(&self) {
+        if !self.types.read().has_kind::<A>() {
+            self.types.write().add_kind::<A>();
+        }
+    }
+
+    /// Reloads assets whose file was changed in the cache's source.
+    ///
+    /// Changes are reported by the source with [`Source::take_changes`]. Only
+    /// assets that are already in the cache are reloaded.
+    ///
+    /// This function does nothing for caches that read from the filesystem:
+    /// changes of files are applied by [`hot_reload`].
+    ///
+    /// Like [`force_reload`], this function requires write locks on changed
+    /// assets, so you **must not** hold an [`AssetGuard`] from this cache when
+    /// you call it.
+    ///
+    /// [`Source::take_changes`]: source/trait.Source.html#method.take_changes
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`force_reload`]: #method.force_reload
+    /// [`AssetGuard`]: struct.AssetGuard.html
+    pub fn reload_changed(&self) {
+        self.listings.write().clear();
+        let mut reloads = Vec::new();
+
+        {
+            let types = self.types.read();
+            let assets = self.assets.read();
+            let mut seen = HashSet::<_, RandomState>::default();
+            let mut candidates = Vec::new();
+
+            self.source.take_changes(&mut |id, ext| {
+                // A file may have changed several times
+                if seen.insert((id.to_owned(), ext.to_owned())) {
+                    types.reloaders(ext, |type_id, reload| candidates.push((type_id, reload)));
+
+                    let skip = self.collision_policy == CollisionPolicy::SkipReload && {
+                        let loaded = candidates.iter().filter(|(type_id, _)| {
+                            assets.contains_key(&AccessKey::new_with(id, *type_id))
+                        });
+                        loaded.count() > 1
+                    };
+
+                    for (_, reload) in candidates.drain(..) {
+                        if !skip {
+                            reloads.push((reload, id.to_owned()));
+                        }
+                    }
+                }
+            });
+        }
+
+        for (reload, id) in reloads {
+            reload(self, &id);
+        }
+
+        self.update_virtuals();
+    }
+
+    /// Defines a virtual asset, computed from other assets.
+    ///
+    /// `compute` is called to get the value of the asset, which is stored in
+    /// the cache with the given id. The assets it loads with [`load`] or
+    /// [`load_virtual`] are recorded as its inputs: when one of them is
+    /// reloaded, the virtual asset is computed again and its own subscribers
+    /// are notified. This happens at the end of [`hot_reload`],
+    /// [`reload_changed`] and [`force_reload`], or at the next call to
+    /// [`load_virtual`].
+    ///
+    /// `compute` should be a pure function of the assets it loads. If a
+    /// virtual asset with the same id and type was already defined, it is
+    /// replaced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use assets_manager::{Asset, AssetCache, loader};
+    ///
+    /// struct X(i32);
+    ///
+    /// impl From<i32> for X {
+    ///     fn from(x: i32) -> X { X(x) }
+    /// }
+    ///
+    /// impl Asset for X {
+    ///     const EXT: &'static str = "x";
+    ///     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+    /// }
+    ///
+    /// let cache = AssetCache::new("assets")?;
+    ///
+    /// let double = cache.define("test.double", |cache| {
+    ///     cache.load::<X>("test.cache").map_or(0, |x| 2 * x.read().0)
+    /// });
+    /// assert_eq!(*double.read(), 84);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`load`]: #method.load
+    /// [`load_virtual`]: #method.load_virtual
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`reload_changed`]: #method.reload_changed
+    /// [`force_reload`]: #method.force_reload
+    pub fn define<A, F>(&self, id: &str, compute: F) -> AssetRef<'_, A>
+    where
+        A: Send + Sync + 'static,
+        F: Fn(&AssetCache) -> A + Send + Sync + 'static,
+    {
+        let (asset, dirty) = self.compute_virtual(id, &compute);
+
+        let owned_id: Box<str> = id.into();
+        let recompute = Box::new(move |cache: &AssetCache| cache.compute_virtual(&owned_id, &compute).1);
+        self.derived.lock().insert(Key::new::<A>(id.into()), recompute, dirty);
+
+        asset
+    }
+
+    /// Loads a virtual asset defined with [`define`].
+    ///
+    /// If one of its inputs was reloaded, it is computed again first.
+    ///
+    /// Returns `None` if no virtual asset with the given id and type is in
+    /// the cache.
+    ///
+    /// [`define`]: #method.define
+    pub fn load_virtual<A: Send + Sync + 'static>(&self, id: &str) -> Option<AssetRef<'_, A>> {
+        let key = AccessKey::new::<A>(id);
+        derived::record_read(&key);
+
+        if self.derived.lock().is_dirty(&key) {
+            self.update_virtuals();
+        }
+
+        let cache = self.assets.read();
+        cache.get(&key).map(|asset| unsafe { asset.get_ref() })
+    }
+
+    /// Defines a virtual asset from a JSON document, whose `$include`
+    /// directives are resolved through the cache.
+    ///
+    /// An object with an `"$include": "other.id"` entry is replaced by the
+    /// content of document `other.id`, whose includes are resolved too. The
+    /// other entries of the object, if any, override the ones of the included
+    /// document. The result is then deserialized as an `A`.
+    ///
+    /// All documents are loaded as [`JsonValue`]s and recorded as inputs of
+    /// the virtual asset, so it is updated when one of them is reloaded (see
+    /// [`define`]). Errors, including include cycles, are stored in the
+    /// asset, so that they can be fixed while hot-reloading.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use assets_manager::{AssetCache, source::MemorySource};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Enemy {
+    ///     name: String,
+    ///     hp: u32,
+    /// }
+    ///
+    /// let source = MemorySource::new();
+    /// source.insert("base", "json", r#"{"name": "unknown", "hp": 10}"#);
+    /// source.insert("goblin", "json", r#"{"$include": "base", "name": "goblin"}"#);
+    /// let cache = AssetCache::with_source(source);
+    ///
+    /// let goblin = cache.compose_json::<Enemy>("goblin");
+    /// match &*goblin.read() {
+    ///     Ok(goblin) => {
+    ///         assert_eq!(goblin.name, "goblin");
+    ///         assert_eq!(goblin.hp, 10);
+    ///     },
+    ///     Err(err) => panic!("{}", err),
+    /// };
+    /// ```
+    ///
+    /// [`JsonValue`]: struct.JsonValue.html
+    /// [`define`]: #method.define
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn compose_json<A>(&self, id: &str) -> AssetRef<'_, Result<A, ComposeError>>
+    where
+        A: for<'de> serde::Deserialize<'de> + Send + Sync + 'static,
+    {
+        let owned_id = id.to_owned();
+        self.define(id, move |cache| {
+            let value = compose::compose::<serde_json::Value>(cache, &owned_id)?;
+            serde_json::from_value(value).map_err(|err| ComposeError::new(&owned_id, err.to_string()))
+        })
+    }
+
+    /// Defines a virtual asset from a RON document, whose `$include`
+    /// directives are resolved through the cache.
+    ///
+    /// This is the same as [`compose_json`], but documents are loaded as
+    /// [`RonValue`]s. Includes are written as maps, such as
+    /// `{"$include": "other.id", "name": "goblin"}`.
+    ///
+    /// [`compose_json`]: #method.compose_json
+    /// [`RonValue`]: struct.RonValue.html
+    #[cfg(feature = "ron")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ron")))]
+    pub fn compose_ron<A>(&self, id: &str) -> AssetRef<'_, Result<A, ComposeError>>
+    where
+        A: for<'de> serde::Deserialize<'de> + Send + Sync + 'static,
+    {
+        let owned_id = id.to_owned();
+        self.define(id, move |cache| {
+            let value = compose::compose::<serde_ron::Value>(cache, &owned_id)?;
+            value.into_rust().map_err(|err| ComposeError::new(&owned_id, err.to_string()))
+        })
+    }
+
+    /// Computes a virtual asset and stores it in the cache.
+    ///
+    /// Returns the flag that is set when one of its inputs is reloaded.
+    fn compute_virtual<A, F>(&self, id: &str, compute: &F) -> (AssetRef<'_, A>, Arc<AtomicBool>)
+    where
+        A: Send + Sync + 'static,
+        F: Fn(&AssetCache) -> A,
+    {
+        let (value, inputs) = derived::tracked(|| compute(self));
+        self.stats.lock().add_type::<A>();
+
+        let dirty = Arc::new(AtomicBool::new(false));
+        let key = AccessKey::new::<A>(id);
+
+        let value = {
+            let cache = self.assets.read();
+            match cache.get(&key) {
+                Some(entry) => unsafe {
+                    let asset = entry.write(value, None);
+                    let mut subscribers = self.subscribers.lock();
+                    subscribers.notify(&key, entry, &cache);
+                    for input in inputs {
+                        subscribers.add_invalidation(input, Arc::downgrade(&dirty));
+                    }
+                    return (asset, dirty);
+                },
+                None => value,
+            }
+        };
+
+        let mut cache = self.assets.write();
+        let entry = cache.entry(Key::new::<A>(id.into())).or_insert_with(|| CacheEntry::new(value));
+
+        let mut subscribers = self.subscribers.lock();
+        for input in inputs {
+            subscribers.add_invalidation(input, Arc::downgrade(&dirty));
+        }
+
+        unsafe { (entry.get_ref(), dirty) }
+    }
+
+    /// Computes again the virtual assets whose inputs were reloaded.
+    fn update_virtuals(&self) {
+        derived::update(self, &self.derived);
+    }
+
+    /// Loads an asset whose type was registered with [`register_type`].
+    ///
+    /// The returned reference can be converted into a typed one with
+    /// [`UntypedRef::downcast`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if no type was registered with the given name, or
+    /// if the asset could not be loaded (see [`load`]).
+    ///
+    /// [`register_type`]: #method.register_type
+    /// [`UntypedRef::downcast`]: struct.UntypedRef.html#method.downcast
+    /// [`load`]: #method.load
+    pub fn load_dyn(&self, type_name: &str, id: &str) -> Result<UntypedRef<'_>, LoadDynError> {
+        let info = self.types.read().get(type_name);
+        match info {
+            Some(info) => (info.load)(self, id),
+            None => Err(LoadDynError::UnknownType(type_name.to_owned())),
+        }
+    }
+
+    /// Loads all files of a directory whose extension is the one of a type
+    /// registered with [`register_type`].
+    ///
+    /// Each file is loaded with the registered type of its extension, so
+    /// directories that contain assets of different types can be loaded in
+    /// one call. Files whose extension has no registered type are skipped. If
+    /// several types share an extension, the one with the smallest name is
+    /// used.
+    ///
+    /// The returned list is sorted by id.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory. Errors of individual assets are returned in the list.
+    ///
+    /// [`register_type`]: #method.register_type
+    pub fn load_dir_any(&self, id: &str) -> io::Result<Vec<(String, Result<UntypedRef<'_>, LoadDynError>)>> {
+        let mut files = Vec::new();
+        {
+            let types = self.types.read();
+            self.source.read_dir(id, &mut |entry| {
+                if let DirEntry::File(id, ext) = entry {
+                    if let Some(info) = types.get_with_ext(ext) {
+                        files.push((id.to_owned(), info));
+                    }
+                }
+            })?;
+        }
+
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(files.into_iter().map(|(id, info)| {
+            let asset = (info.load)(self, &id);
+            (id, asset)
+        }).collect())
+    }
+
+    /// Subscribes to the reloads of an asset.
+    ///
+    /// Each time the asset is reloaded, either by hot-reloading or with
+    /// [`force_reload`], a snapshot of its new value is sent to the returned
+    /// `Receiver`. This allows other threads (e.g. audio or rendering) to
+    /// simply poll it to get fresh data.
+    ///
+    /// The current value is not sent: you can get it with [`load`].
+    ///
+    /// The channel is closed if the asset is removed from the cache by
+    /// hot-reloading.
+    ///
+    /// [`force_reload`]: #method.force_reload
+    /// [`load`]: #method.load
+    pub fn updates<A: Asset + Clone>(&self, id: &str) -> Receiver<Arc<A>> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().add_updates(id, sender);
+        receiver
+    }
+
+    /// Lists the ids of the files of the source that match a pattern.
+    ///
+    /// Unlike [`load_dir`], this lists the content of the source without
+    /// loading anything, so tools can enumerate available content.
+    ///
+    /// The pattern is matched against whole ids:
+    /// - `*` matches any sequence of characters in a part of an id (without
+    ///   `.`), e.g. `"levels.*"` matches `"levels.forest"`.
+    /// - `**` matches any sequence of characters, including `.`, e.g.
+    ///   `"textures.**"` matches `"textures.ui.button"`.
+    /// - A pattern without `*` matches ids that start with it, e.g.
+    ///   `"shaders."` matches all ids in the `shaders` directory and its
+    ///   subdirectories.
+    ///
+    /// Ids are sorted and deduplicated. Directories that cannot be read are
+    /// skipped.
+    ///
+    /// [`load_dir`]: #method.load_dir
+    pub fn find_ids(&self, pattern: &str) -> Vec<String> {
+        let prefix_only = !pattern.contains('*');
+
+        // Start from the deepest directory that contains all matches
+        let literal = &pattern[..pattern.find('*').unwrap_or(pattern.len())];
+        let start = match literal.rfind('.') {
+            Some(pos) => &literal[..pos],
+            None => "",
+        };
+
+        let mut ids = Vec::new();
+        self.walk_dir(start, &mut |id| {
+            let matches = if prefix_only {
+                id.starts_with(pattern)
+            } else {
+                glob_match(pattern.as_bytes(), id.as_bytes())
+            };
+            if matches {
+                ids.push(id.to_owned());
+            }
+        });
+
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Calls `f` with the id of each file in a directory and its
+    /// subdirectories.
+    fn walk_dir(&self, id: &str, f: &mut dyn FnMut(&str)) {
+        let mut dirs = Vec::new();
+        let _ = self.source.read_dir(id, &mut |entry| match entry {
+            DirEntry::File(id, _) => f(id),
+            DirEntry::Directory(id) => dirs.push(id.to_owned()),
+        });
+
+        for dir in dirs {
+            self.walk_dir(&dir, f);
+        }
+    }
+
+    /// Calls `callback` each time an asset whose id starts with `prefix` is
+    /// reloaded, whatever its type.
+    ///
+    /// The callback is given the id of the reloaded asset. This is useful to
+    /// react to changes of a group of assets without listing them up front,
+    /// for example to rebuild a pipeline cache when any shader changes.
+    ///
+    /// Ids are compared character by character, so `"shaders"` would also
+    /// match `"shaders_old.blur"`; use `"shaders."` to only be notified of the
+    /// content of the `shaders` directory. The empty prefix matches all
+    /// assets.
+    ///
+    /// The callback is called while the cache is locked, so it **must not**
+    /// use this cache.
+    pub fn subscribe_prefix<F>(&self, prefix: &str, callback: F)
+    where
+        F: FnMut(&str) + Send + 'static,
+    {
+        self.subscribers.lock().add_prefix(prefix, Box::new(callback));
+    }
+
+    /// Load all assets of a given type in a directory.
+    ///
+    /// The directory's id is constructed the same way as assets. To specify
+    /// the cache's root, give the empty string (`""`) as id.
+    ///
+    /// The returned structure can be iterated on to get the loaded assets.
+    ///
+    /// # Error
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory.
+    pub fn load_dir<A: Asset>(&self, id: &str) -> io::Result<DirReader<'_, A>> {
+        let dirs = self.dirs.read();
+        if let Some(dir) = dirs.get(&AccessKey::new::<A>(id)) {
+            return unsafe { Ok(dir.read(self)) };
+        }
+        drop(dirs);
+
+        self.add_dir(id.to_string())
+    }
+
+    /// Loads the assets of several directories in a [`Registry`], keyed by
+    /// name.
+    ///
+    /// Directories are loaded as with [`load_dir`], in the given order, and
+    /// act as layers: for example a base game directory followed by mod
+    /// directories. The validation hooks of [`RegistryEntry`] are checked on
+    /// the whole registry.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a directory cannot be read, if an asset cannot
+    /// be loaded, or if a validation hook fails.
+    ///
+    /// [`Registry`]: struct.Registry.html
+    /// [`load_dir`]: #method.load_dir
+    /// [`RegistryEntry`]: trait.RegistryEntry.html
+    pub fn load_registry<A: RegistryEntry>(&self, layers: &[&str]) -> Result<Registry<'_, A>, RegistryError> {
+        Registry::load(self, layers)
+    }
+
+    /// Lists the assets of a given type in a directory, without loading them.
+    ///
+    /// Assets are loaded when they are accessed through the returned
+    /// [`LazyDir`]. See [`load_dir`] for how ids are constructed.
+    ///
+    /// # Error
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory.
+    ///
+    /// [`LazyDir`]: struct.LazyDir.html
+    /// [`load_dir`]: #method.load_dir
+    #[inline]
+    pub fn load_dir_lazy<A: Asset>(&self, id: &str) -> io::Result<LazyDir<'_, A>> {
+        LazyDir::new(self, id)
+    }
+
+    /// Load all assets of a given type in each subdirectory of a directory.
+    ///
+    /// This is the same as calling [`load_dir`] on each immediate subdirectory
+    /// of the directory with the given id, so content organized as
+    /// `levels/<level>/*.ron` can be traversed with typed handles. The id of
+    /// each subdirectory can be obtained with [`DirReader::id`].
+    ///
+    /// Subdirectories are listed each time this function is called, but the
+    /// returned `DirReader`s come from the cache. Subdirectories whose name
+    /// cannot be used in an id (non UTF-8 or containing a `.`) or that cannot
+    /// be read are skipped.
+    ///
+    /// # Error
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory.
+    ///
+    /// [`load_dir`]: #method.load_dir
+    /// [`DirReader::id`]: struct.DirReader.html#method.id
+    pub fn load_dirs<A: Asset>(&self, id: &str) -> io::Result<Vec<DirReader<'_, A>>> {
+        let mut ids = Vec::new();
+        self.source.read_dir(id, &mut |entry| {
+            if let DirEntry::Directory(id) = entry {
+                ids.push(id.to_owned());
+            }
+        })?;
+
+        Ok(ids.iter().filter_map(|id| self.load_dir(id).ok()).collect())
+    }
+
+    /// Checks that all assets of a given type in a directory and its
+    /// subdirectories can be loaded.
+    ///
+    /// Each file with the extension of `A` is loaded, but the loaded assets
+    /// are not added to the cache, and failures are not counted in its
+    /// statistics. The ids of files that failed to load are returned with
+    /// their error.
+    ///
+    /// This is intended to be used in tests or in build scripts (see the
+    /// [`build`] module) to catch malformed files early.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory. Errors on subdirectories are ignored.
+    ///
+    /// [`build`]: build/index.html
+    pub fn validate<A: Asset>(&self, id: &str) -> io::Result<Vec<(String, AssetErr<A>)>> {
+        let mut errors = Vec::new();
+        self.validate_dir::<A>(id, &mut errors)?;
+        errors.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(errors)
+    }
+
+    fn validate_dir<A: Asset>(&self, id: &str, errors: &mut Vec<(String, AssetErr<A>)>) -> io::Result<()> {
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+
+        self.source.read_dir(id, &mut |entry| match entry {
+            DirEntry::File(id, ext) => {
+                if A::EXT == ANY_EXTENSION || ext == A::EXT {
+                    files.push((id.to_owned(), ext.to_owned()));
+                }
+            },
+            DirEntry::Directory(id) => dirs.push(id.to_owned()),
+        })?;
+
+        for (id, ext) in files {
+            if let Err(err) = self.check_from_source::<A>(&id, &ext) {
+                errors.push((id, err));
+            }
+        }
+
+        for dir in dirs {
+            let _ = self.validate_dir::<A>(&dir, errors);
+        }
+
+        Ok(())
+    }
 
     /// Remove an asset from the cache.
     ///
-    /// The removed asset matches both the id and the type parameter.
+    /// The removed asset matches both the id and the type parameter. Its
+    /// subscribers are dropped.
     #[inline]
     pub fn remove<A: Asset>(&mut self, id: &str) {
-        let key = AccessKey::new::<A>(id);
-        let cache = self.assets.get_mut();
-        cache.remove(&key);
+        self.drop_detached();
+
+        self.remove_entries(&[Key::new::<A>(id.into())]);
     }
 
     /// Take ownership on an asset.
     ///
     /// The corresponding asset is removed from the cache.
     pub fn take<A: Asset>(&mut self, id: &str) -> Option<A> {
-        let key = AccessKey::new::<A>(id);
-        let cache = self.assets.get_mut();
-        cache.remove(&key).map(|entry| unsafe { entry.into_inner() })
+        self.drop_detached();
+
+        let key = Key::new::<A>(id.into());
+        let entry = self.assets.get_mut().remove(&key);
+        self.forget_entries(&[key]);
+        entry.map(|entry| unsafe { entry.into_inner() })
+    }
+
+    /// Creates a new scope to track the assets loaded through it.
+    ///
+    /// See [`AssetScope`] for more details.
+    ///
+    /// [`AssetScope`]: struct.AssetScope.html
+    #[inline]
+    pub fn scope(&self) -> AssetScope<'_> {
+        AssetScope::new(self)
+    }
+
+    /// Removes from the cache the assets loaded through scopes that were all
+    /// dropped.
+    ///
+    /// Subscribers of removed assets are dropped.
+    ///
+    /// Returns the number of removed assets.
+    pub fn remove_unused(&mut self) -> usize {
+        self.drop_detached();
+
+        let unused = self.scopes.get_mut().take_unused();
+        self.remove_entries(&unused)
     }
 
     /// Clears the cache.
+    ///
+    /// All subscribers are dropped, which closes the channels returned by
+    /// [`updates`] and removes the callbacks added with [`subscribe_prefix`].
+    ///
+    /// [`updates`]: #method.updates
+    /// [`subscribe_prefix`]: #method.subscribe_prefix
     #[inline]
     pub fn clear(&mut self) {
         self.assets.get_mut().clear();
         self.dirs.get_mut().clear();
+        self.listings.get_mut().clear();
+        self.subscribers.get_mut().clear();
+        self.scopes.get_mut().clear();
+        self.derived.get_mut().clear();
+
+        #[cfg(feature = "hot-reloading")]
+        {
+            self.watched.get_mut().clear();
+            self.drop_detached();
+        }
+    }
+
+    /// Takes a snapshot of the current values of all assets of type `A`.
+    ///
+    /// The values are cloned, so later reloads do not affect the snapshot.
+    /// They can be put back in the cache with [`restore`].
+    ///
+    /// [`restore`]: #method.restore
+    pub fn snapshot<A: Asset + Clone>(&self) -> Snapshot<A> {
+        let type_id = TypeId::of::<A>();
+        let assets = self.assets.read();
+
+        let values = assets.iter()
+            .filter(|(key, _)| key.type_id() == type_id)
+            .map(|(key, entry)| {
+                let value = unsafe { entry.get_ref::<A>().cloned() };
+                (key.id().into(), value)
+            })
+            .collect();
+
+        Snapshot::new(values)
+    }
+
+    /// Restores the values of assets from a snapshot.
+    ///
+    /// Each asset in the snapshot that is still in the cache gets back its
+    /// value from the snapshot, and its subscribers are notified. Other assets
+    /// are left unchanged.
+    ///
+    /// Like [`force_reload`], this function requires write locks on the
+    /// assets, so you **must not** hold an [`AssetGuard`] from this cache when
+    /// you call it.
+    ///
+    /// [`force_reload`]: #method.force_reload
+    /// [`AssetGuard`]: struct.AssetGuard.html
+    pub fn restore<A: Asset + Clone>(&self, snapshot: &Snapshot<A>) {
+        let assets = self.assets.read();
+        let mut subscribers = self.subscribers.lock();
+
+        for (id, value) in snapshot.iter() {
+            let key = AccessKey::new::<A>(id);
+            if let Some(entry) = assets.get(&key) {
+                unsafe {
+                    entry.write(value.clone(), None);
+                    subscribers.notify(&key, entry, &assets);
+                }
+            }
+        }
+    }
+
+    /// Returns a summary of the content of the cache.
+    ///
+    /// The returned report contains the number of assets of each type, an
+    /// estimation of the memory they use (see [`Asset::heap_size`] to make it
+    /// more accurate), the number of errors that happened
+    /// and the last reloaded assets. It implements `Display`, so it can be
+    /// printed or drawn in a debug overlay.
+    ///
+    /// [`Asset::heap_size`]: trait.Asset.html#method.heap_size
+    pub fn debug_report(&self) -> DebugReport {
+        let collisions = self.collisions();
+        let assets = self.assets.read();
+        let dirs = self.dirs.read().len();
+        self.stats.lock().report(self.path(), assets.iter(), dirs, collisions)
+    }
+
+    /// Returns the files that are loaded as several types of assets with the
+    /// same extension, sorted by id.
+    ///
+    /// When such a file changes, what happens depends on the cache's
+    /// [`CollisionPolicy`].
+    ///
+    /// [`CollisionPolicy`]: enum.CollisionPolicy.html
+    pub fn collisions(&self) -> Vec<Collision> {
+        let assets = self.assets.read();
+        let types = self.types.read();
+        let stats = self.stats.lock();
+
+        collision::find(
+            assets.keys(),
+            |type_id| types.kind(type_id).map(|(ext, _)| ext),
+            |type_id| stats.type_name(type_id),
+        )
+    }
+
+    /// Sets what happens when a file loaded as several types of assets
+    /// changes.
+    ///
+    /// See [`CollisionPolicy`] for the available options. The default is to
+    /// reload all of them. This applies both to [`hot_reload`] and to
+    /// [`reload_changed`].
+    ///
+    /// [`CollisionPolicy`]: enum.CollisionPolicy.html
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`reload_changed`]: #method.reload_changed
+    pub fn set_collision_policy(&mut self, policy: CollisionPolicy) {
+        self.collision_policy = policy;
+
+        #[cfg(feature = "hot-reloading")]
+        self.watched.get_mut().set_collision_policy(policy);
+    }
+
+    /// Puts assets whose id starts with `prefix` in a budget group.
+    ///
+    /// Budget groups are used to account the memory used by categories of
+    /// assets (e.g. "UI", "World" or "Audio"). If several rules match an
+    /// asset, the first one added is used.
+    ///
+    /// For example, `tag_prefix("ui.", "UI")` puts everything under the `ui`
+    /// directory in the "UI" group, whereas a rule for `"ui"` would also catch
+    /// an asset named `"uikit"`.
+    pub fn tag_prefix(&self, prefix: &str, group: &str) {
+        self.budgets.write().tag_prefix(prefix, group);
+    }
+
+    /// Puts all assets of type `A` in a budget group.
+    ///
+    /// See [`tag_prefix`] for more details.
+    ///
+    /// [`tag_prefix`]: #method.tag_prefix
+    pub fn tag_type<A: Asset>(&self, group: &str) {
+        self.budgets.write().tag_type(TypeId::of::<A>(), group);
+    }
+
+    /// Sets the memory budget of a group, in bytes.
+    ///
+    /// Memory usage is estimated the same way as in [`debug_report`]: types
+    /// that own data should implement [`Asset::heap_size`] for budgets to be
+    /// meaningful. Groups over their budget
+    /// are shown in [`budget_report`], and can be shrunk with
+    /// [`evict_over_budget`].
+    ///
+    /// [`debug_report`]: #method.debug_report
+    /// [`Asset::heap_size`]: trait.Asset.html#method.heap_size
+    /// [`budget_report`]: #method.budget_report
+    /// [`evict_over_budget`]: #method.evict_over_budget
+    pub fn set_budget(&self, group: &str, bytes: usize) {
+        self.budgets.write().set_limit(group, bytes);
+    }
+
+    /// Returns statistics about each budget group, sorted by name.
+    pub fn budget_report(&self) -> Vec<GroupReport> {
+        let assets = self.assets.read();
+        let stats = self.stats.lock();
+        self.budgets.read().report(assets.iter().map(|(key, entry)| (key, stats.size_of_entry(key, entry))))
+    }
+
+    /// Removes assets from groups that are over their budget until they fit in
+    /// it.
+    ///
+    /// Assets used by a live [`AssetScope`] are never removed. Bigger assets
+    /// are removed first. Subscribers of removed assets are dropped.
+    ///
+    /// Returns the number of removed assets.
+    ///
+    /// [`AssetScope`]: struct.AssetScope.html
+    pub fn evict_over_budget(&mut self) -> usize {
+        self.drop_detached();
+
+        let budgets = self.budgets.get_mut();
+        let stats = self.stats.get_mut();
+        let scopes = self.scopes.get_mut();
+        let assets = self.assets.get_mut();
+
+        let mut groups = HashMap::<&str, (usize, Vec<(usize, &Key)>), RandomState>::default();
+        for (key, entry) in assets.iter() {
+            if let Some(group) = budgets.group_of(key) {
+                let size = stats.size_of_entry(key, entry);
+                let (total, candidates) = groups.entry(group).or_default();
+                *total += size;
+                if !scopes.is_used(key) {
+                    candidates.push((size, key));
+                }
+            }
+        }
+
+        let mut evicted = Vec::new();
+        for (group, (mut total, mut candidates)) in groups {
+            let budget = match budgets.limit(group) {
+                Some(budget) => budget,
+                None => continue,
+            };
+
+            candidates.sort_by(|(a_size, a), (b_size, b)| b_size.cmp(a_size).then_with(|| a.id().cmp(b.id())));
+            for (size, key) in candidates {
+                if total <= budget {
+                    break;
+                }
+                total -= size;
+                evicted.push(Key::new_with(key.id().into(), key.type_id()));
+            }
+        }
+
+        self.remove_entries(&evicted)
+    }
+
+    /// Lists the assets on which `AssetGuard`s are currently held.
+    ///
+    /// This is typically called at shutdown or when leaving a level, to find
+    /// systems that keep reading assets they should have released. Guards
+    /// held by the calling thread are reported too.
+    ///
+    /// Assets are sorted by id.
+    #[cfg(feature = "leak-detection")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "leak-detection")))]
+    pub fn report_leaks(&self) -> Vec<LeakReport> {
+        let assets = self.assets.read();
+        let stats = self.stats.lock();
+
+        let mut leaks: Vec<_> = assets.iter().filter_map(|(key, entry)| {
+            match entry.guard_count() {
+                0 => None,
+                n => Some(stats.leak(key, n)),
+            }
+        }).collect();
+
+        leaks.sort_by(|a, b| a.id.cmp(&b.id));
+        leaks
+    }
 
+    /// Drops entries removed by hot-reloading.
+    ///
+    /// This is safe because a mutable reference guaranties that no reference
+    /// to them can exist anymore.
+    #[inline]
+    fn drop_detached(&mut self) {
         #[cfg(feature = "hot-reloading")]
-        self.watched.get_mut().clear();
+        self.detached.get_mut().clear();
     }
 
     /// Reloads changed assets.
@@ -385,9 +2573,19 @@ impl AssetCache {
     ///
     /// This function is typically called within a loop.
     ///
+    /// Changed files are read and loaded by a background thread, but the new
+    /// values are only published in the cache during calls to this function
+    /// (and to [`force_reload`] and [`reload_changed`]). Calling it once per
+    /// frame, for example between update and render, thus guarantees that no
+    /// asset changes value in the middle of a frame.
+    ///
     /// If an error occurs while reloading an asset, a warning will be logged
     /// and the asset will be left unchanged.
     ///
+    /// Hot-reloading activity is reported with the `log` crate: reloaded
+    /// assets at `info` level, failures at `warn` and `error` levels, and
+    /// ignored changes at `debug` and `trace` levels.
+    ///
     /// This function will block the current thread until all changed assets are
     /// reloaded, but it does not perform any I/O. However, it will need to lock
     /// some assets for writing, so you **must not** have any [`AssetGuard`] from
@@ -396,23 +2594,235 @@ impl AssetCache {
     ///
     /// [`AssetGuard`]: struct.AssetGuard.html
     /// [`AssetRef`]: struct.AssetRef.html
+    /// [`force_reload`]: #method.force_reload
+    /// [`reload_changed`]: #method.reload_changed
+    ///
+    /// If the native filesystem watcher cannot be created (e.g. on some network
+    /// mounts), files are polled periodically instead. You can check which one
+    /// is used with [`hot_reload_status`].
+    ///
+    /// [`hot_reload_status`]: #method.hot_reload_status
+    ///
+    /// If the hot-reloading thread or the watcher stop unexpectedly, they are
+    /// restarted by a next call to this function, after a delay that grows
+    /// if restarts keep failing. Meanwhile, [`hot_reload_status`] returns
+    /// [`HotReloadStatus::Restarting`].
+    ///
+    /// [`HotReloadStatus::Restarting`]: enum.HotReloadStatus.html#variant.Restarting
     ///
     /// # Errors
     ///
-    /// This function will return an error it it failed to start hot-reloading.
+    /// This function will return an error it it failed to start hot-reloading,
+    /// even with polling.
     #[cfg(feature = "hot-reloading")]
     #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
     pub fn hot_reload(&self) -> Result<(), notify::Error> {
+        if self.path.is_none() {
+            if !self.paused.load(Ordering::Acquire) {
+                self.reload_changed();
+            }
+            return Ok(());
+        }
+
         let mut reloader = self.reloader.lock();
         match &*reloader {
-            Some(reloader) => reloader.reload(self),
-            None => {
-                *reloader = Some(HotReloader::start(self)?);
+            Some(_) => self.run_reloader(&mut reloader),
+            None => self.start_reloader(&mut reloader, |cache| HotReloader::start(cache, cache.poll_interval, cache.watch_mode))?,
+        }
+        Ok(())
+    }
+
+    /// Applies the changes reported by a running reloader.
+    #[cfg(feature = "hot-reloading")]
+    fn run_reloader(&self, reloader: &mut Option<HotReloader>) {
+        if self.paused.load(Ordering::Acquire) {
+            return;
+        }
+
+        if let Some(running) = reloader {
+            self.listings.write().clear();
+            if running.reload(self) {
+                self.update_virtuals();
+            } else {
+                log::error!("The hot-reloading thread stopped unexpectedly");
+                *reloader = None;
+                self.supervisor.lock().failed();
             }
         }
+    }
+
+    /// Starts a reloader, unless it stopped too recently to be restarted.
+    #[cfg(feature = "hot-reloading")]
+    fn start_reloader(
+        &self,
+        reloader: &mut Option<HotReloader>,
+        start: impl FnOnce(&Self) -> Result<HotReloader, notify::Error>,
+    ) -> Result<(), notify::Error> {
+        let mut supervisor = self.supervisor.lock();
+        if !supervisor.may_start() {
+            return Ok(());
+        }
+
+        match start(self) {
+            Ok(started) => {
+                *reloader = Some(started);
+                supervisor.started();
+                self.watched.lock().rewatch();
+                Ok(())
+            },
+            Err(err) => {
+                if supervisor.is_restarting() {
+                    supervisor.failed();
+                }
+                Err(err)
+            },
+        }
+    }
+
+    /// Reloads changed assets, using a watcher shared with other caches.
+    ///
+    /// This works like [`hot_reload`], but instead of starting its own
+    /// watcher, the cache registers itself in the given [`HotReloadHub`]. If
+    /// the cache was using another watcher, it stops using it.
+    ///
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`HotReloadHub`]: struct.HotReloadHub.html
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn hot_reload_with(&self, hub: &HotReloadHub) -> Result<(), notify::Error> {
+        if self.path.is_none() {
+            return self.hot_reload();
+        }
+
+        let mut reloader = self.reloader.lock();
+        match &*reloader {
+            Some(running) if running.uses_hub(hub) => self.run_reloader(&mut reloader),
+            _ => {
+                // Unregister from the previous watcher first
+                reloader.take();
+                self.start_reloader(&mut reloader, |cache| HotReloader::start_shared(cache, hub))?;
+            },
+        }
         Ok(())
     }
 
+    /// Pauses hot-reloading.
+    ///
+    /// While hot-reloading is paused, [`hot_reload`] does not change any asset.
+    /// Changes made on the filesystem meanwhile are still recorded, and are
+    /// applied at the first call to [`hot_reload`] after [`resume_hot_reload`]
+    /// is called.
+    ///
+    /// This is useful to avoid reload hitches at moments where they would be
+    /// noticed, such as cutscenes or benchmarks.
+    ///
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`resume_hot_reload`]: #method.resume_hot_reload
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn pause_hot_reload(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    /// Resumes hot-reloading after a call to [`pause_hot_reload`].
+    ///
+    /// Changes recorded while hot-reloading was paused will be applied by the
+    /// next call to [`hot_reload`].
+    ///
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`pause_hot_reload`]: #method.pause_hot_reload
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn resume_hot_reload(&self) {
+        self.paused.store(false, Ordering::Release);
+    }
+
+    /// Returns `true` if hot-reloading is currently paused.
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn is_hot_reload_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Ignores changes of assets whose id starts with `prefix`.
+    ///
+    /// This is useful when the application itself writes files in the asset
+    /// directory (e.g. screenshots or exported data), so these writes don't
+    /// trigger reloads.
+    ///
+    /// Only changes reported by the watcher are ignored: these assets can
+    /// still be loaded and reloaded explicitly. To ignore the `generated`
+    /// directory, pass `"generated."`, as `"generated"` also matches files
+    /// next to it such as `"generated_list"`.
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn ignore_reloads_under(&self, prefix: &str) {
+        self.watched.lock().ignore(prefix);
+    }
+
+    /// Sets what happens to an asset when its file is removed.
+    ///
+    /// See [`RemovalPolicy`] for the available options. The default is to keep
+    /// the last value of the asset.
+    ///
+    /// [`RemovalPolicy`]: enum.RemovalPolicy.html
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn set_removal_policy(&mut self, policy: RemovalPolicy) {
+        self.removal_policy = policy;
+    }
+
+    /// Returns the current state of hot-reloading.
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn hot_reload_status(&self) -> HotReloadStatus {
+        match &*self.reloader.lock() {
+            Some(reloader) => reloader.status(),
+            None if self.supervisor.lock().is_restarting() => HotReloadStatus::Restarting,
+            None => HotReloadStatus::Stopped,
+        }
+    }
+
+    /// Returns the number of times hot-reloading was restarted after its
+    /// thread or its watcher stopped unexpectedly.
+    ///
+    /// See [`hot_reload`] for more details.
+    ///
+    /// [`hot_reload`]: #method.hot_reload
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn hot_reload_restarts(&self) -> usize {
+        self.supervisor.lock().restarts()
+    }
+
+    /// Sets the interval between two scans of the filesystem when hot-reloading
+    /// has to fall back to polling.
+    ///
+    /// The default is 2 seconds. The new value is used the next time
+    /// hot-reloading is started.
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Sets which directories are watched by [`hot_reload`].
+    ///
+    /// See [`WatchMode`] for the available options. The default is to watch
+    /// the whole directory of the cache. The new value is used the next time
+    /// hot-reloading is started. It has no effect with [`hot_reload_with`],
+    /// as a [`HotReloadHub`] always watches whole directories.
+    ///
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`hot_reload_with`]: #method.hot_reload_with
+    /// [`WatchMode`]: enum.WatchMode.html
+    /// [`HotReloadHub`]: struct.HotReloadHub.html
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn set_watch_mode(&mut self, mode: WatchMode) {
+        self.watch_mode = mode;
+    }
+
     /// Stops the hot-reloading.
     ///
     /// If [`hot_reload`] has not been called on this `AssetCache`, this method
@@ -425,13 +2835,14 @@ impl AssetCache {
     pub fn stop_hot_reloading(&self) {
         let mut reloader = self.reloader.lock();
         reloader.take();
+        self.supervisor.lock().reset();
     }
 }
 
 impl fmt::Debug for AssetCache {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AssetCache")
-            .field("path", &self.path)
+            .field("path", &self.path())
             .field("assets", &self.assets.read())
             .finish()
     }
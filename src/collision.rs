@@ -0,0 +1,96 @@
+//! Files loaded as several types of assets
+
+use crate::{ANY_EXTENSION, cache::Key, RandomState};
+
+use std::{any::TypeId, collections::HashMap, fmt};
+
+
+/// What to do when a file that was loaded as several types of assets
+/// changes.
+///
+/// Assets are keyed by their id and their type, so a file can be loaded as
+/// several types with the same extension (e.g. a `.ron` file loaded both as
+/// a typed struct and as a generic value). Such collisions can be listed with
+/// [`AssetCache::collisions`].
+///
+/// It can be set with [`AssetCache::set_collision_policy`].
+///
+/// [`AssetCache::collisions`]: struct.AssetCache.html#method.collisions
+/// [`AssetCache::set_collision_policy`]: struct.AssetCache.html#method.set_collision_policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// The change is routed to all types that loaded the file, each one
+    /// being reloaded with its own loader.
+    ///
+    /// This is the default.
+    ReloadAll,
+
+    /// The file is not reloaded, and all its assets keep their last value.
+    ///
+    /// This avoids having values of different types that come from different
+    /// versions of a file, for example if one of them fails to reload.
+    SkipReload,
+}
+
+impl Default for CollisionPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::ReloadAll
+    }
+}
+
+
+/// A file loaded as several types of assets, given by
+/// [`AssetCache::collisions`].
+///
+/// [`AssetCache::collisions`]: struct.AssetCache.html#method.collisions
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Collision {
+    /// The id of the assets.
+    pub id: String,
+
+    /// The extension of the file.
+    pub ext: &'static str,
+
+    /// The names of the types of the assets, in alphabetical order.
+    pub type_names: Vec<&'static str>,
+}
+
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} is loaded as {}", self.id, self.ext, self.type_names.join(", "))
+    }
+}
+
+/// Finds the ids loaded as several types with the same extension.
+///
+/// `ext_of` gives the extension of a type of asset, and `name_of` its name.
+/// Types that accept any extension are not taken into account, as their
+/// assets may come from different files.
+pub(crate) fn find<'a>(
+    keys: impl Iterator<Item = &'a Key>,
+    ext_of: impl Fn(TypeId) -> Option<&'static str>,
+    name_of: impl Fn(TypeId) -> &'static str,
+) -> Vec<Collision> {
+    let mut files = HashMap::<_, Vec<_>, RandomState>::default();
+
+    for key in keys {
+        match ext_of(key.type_id()) {
+            Some(ext) if ext != ANY_EXTENSION => files.entry((key.id(), ext)).or_default().push(key.type_id()),
+            _ => (),
+        }
+    }
+
+    let mut collisions: Vec<_> = files.into_iter()
+        .filter(|(_, types)| types.len() > 1)
+        .map(|((id, ext), types)| {
+            let mut type_names: Vec<_> = types.into_iter().map(&name_of).collect();
+            type_names.sort_unstable();
+            Collision { id: id.to_owned(), ext, type_names }
+        })
+        .collect();
+
+    collisions.sort_by(|a, b| (&a.id, a.ext).cmp(&(&b.id, b.ext)));
+    collisions
+}
@@ -0,0 +1,170 @@
+//! Composition of documents with `$include` directives
+
+use crate::{Asset, AssetCache};
+
+use std::{error::Error, fmt, ops::Deref};
+
+
+/// The key of the include directive.
+const INCLUDE: &str = "$include";
+
+/// A document tree in which includes can be resolved.
+pub(crate) trait Tree: Clone + Sized {
+    /// The asset type used to load documents.
+    type Asset: Asset + Deref<Target = Self>;
+
+    /// If `self` is a map with an include directive, removes it and returns
+    /// the included id.
+    fn take_include(&mut self) -> Option<Option<String>>;
+
+    /// Returns the children of `self`.
+    fn children_mut(&mut self) -> Vec<&mut Self>;
+
+    /// Inserts all entries of `over` in `base`, if both are maps.
+    ///
+    /// Returns `None` if `over` is a non-empty map, but `base` is not a map.
+    fn override_with(base: Self, over: Self) -> Option<Self>;
+}
+
+/// Loads the document `id` and resolves its includes, recursively.
+///
+/// As all documents are loaded through the cache, this records them as reads
+/// of the current virtual asset.
+pub(crate) fn compose<T: Tree>(cache: &AssetCache, id: &str) -> Result<T, ComposeError> {
+    compose_inner(cache, id, &mut Vec::new())
+}
+
+fn compose_inner<T: Tree>(cache: &AssetCache, id: &str, stack: &mut Vec<String>) -> Result<T, ComposeError> {
+    if let Some(pos) = stack.iter().position(|s| s == id) {
+        let mut cycle = stack[pos..].join(" -> ");
+        cycle.push_str(" -> ");
+        cycle.push_str(id);
+        return Err(ComposeError::new(id, format!("include cycle: {}", cycle)));
+    }
+
+    let value = match cache.load::<T::Asset>(id) {
+        Ok(asset) => T::clone(&asset.read()),
+        Err(err) => return Err(ComposeError::new(id, err.to_string())),
+    };
+
+    stack.push(id.to_owned());
+    let result = resolve(cache, id, value, stack);
+    stack.pop();
+    result
+}
+
+fn resolve<T: Tree>(cache: &AssetCache, id: &str, mut value: T, stack: &mut Vec<String>) -> Result<T, ComposeError> {
+    for child in value.children_mut() {
+        let resolved = resolve(cache, id, child.clone(), stack)?;
+        *child = resolved;
+    }
+
+    match value.take_include() {
+        None => Ok(value),
+        Some(Some(included)) => {
+            let base = compose_inner(cache, &included, stack)?;
+            T::override_with(base, value).ok_or_else(|| {
+                ComposeError::new(id, format!("cannot add fields to {:?}, which is not a map", included))
+            })
+        },
+        Some(None) => Err(ComposeError::new(id, format!("the value of {:?} must be a string", INCLUDE))),
+    }
+}
+
+#[cfg(feature = "json")]
+impl Tree for serde_json::Value {
+    type Asset = crate::JsonValue;
+
+    fn take_include(&mut self) -> Option<Option<String>> {
+        match self {
+            Self::Object(map) => map.remove(INCLUDE).map(|id| id.as_str().map(String::from)),
+            _ => None,
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Self> {
+        match self {
+            Self::Object(map) => map.values_mut().collect(),
+            Self::Array(seq) => seq.iter_mut().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn override_with(base: Self, over: Self) -> Option<Self> {
+        match (base, over) {
+            (base, Self::Object(over)) if over.is_empty() => Some(base),
+            (Self::Object(mut base), Self::Object(over)) => {
+                base.extend(over);
+                Some(Self::Object(base))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "ron")]
+impl Tree for serde_ron::Value {
+    type Asset = crate::RonValue;
+
+    fn take_include(&mut self) -> Option<Option<String>> {
+        match self {
+            Self::Map(map) => map.remove(&Self::String(INCLUDE.into())).map(|id| match id {
+                Self::String(id) => Some(id),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Self> {
+        match self {
+            Self::Map(map) => map.values_mut().collect(),
+            Self::Seq(seq) => seq.iter_mut().collect(),
+            Self::Option(Some(value)) => vec![value],
+            _ => Vec::new(),
+        }
+    }
+
+    fn override_with(base: Self, over: Self) -> Option<Self> {
+        match (base, over) {
+            (base, Self::Map(over)) if over.is_empty() => Some(base),
+            (Self::Map(mut base), Self::Map(over)) => {
+                base.extend(over);
+                Some(Self::Map(base))
+            },
+            _ => None,
+        }
+    }
+}
+
+
+/// An error which occurs when composing a document.
+///
+/// See [`AssetCache::compose_json`] for more details.
+///
+/// [`AssetCache::compose_json`]: struct.AssetCache.html#method.compose_json
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComposeError {
+    id: String,
+    reason: String,
+}
+
+impl ComposeError {
+    pub(crate) fn new(id: &str, reason: String) -> Self {
+        ComposeError { id: id.to_owned(), reason }
+    }
+
+    /// Returns the id of the document where the error occured.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to compose {:?}: {}", self.id, self.reason)
+    }
+}
+
+impl Error for ComposeError {}
@@ -0,0 +1,67 @@
+//! Assets made of several files with the same name
+
+use crate::loader::LoadContext;
+
+use std::fmt;
+
+
+/// An asset made of several files with the same id and different extensions.
+///
+/// Some formats split an asset in several files, such as a `model.obj`
+/// mesh and its `model.mtl` materials. Such assets are loaded with
+/// [`AssetCache::load_compound`], which gives a [`LoadContext`] to the
+/// loading function, so it can read the main file and its siblings.
+///
+/// All files read by the loading function are watched: when one of them
+/// changes, the asset is loaded again.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{AssetCache, CompoundAsset, LoadContext, source::MemorySource};
+///
+/// struct Model {
+///     mesh: String,
+///     materials: Option<String>,
+/// }
+///
+/// impl CompoundAsset for Model {
+///     const EXT: &'static str = "obj";
+///     type Err = Box<dyn std::error::Error>;
+///
+///     fn load(context: &LoadContext) -> Result<Self, Self::Err> {
+///         let mesh = String::from_utf8(context.content()?.into_owned())?;
+///         let materials = match context.sibling("mtl") {
+///             Ok(content) => Some(String::from_utf8(content.into_owned())?),
+///             Err(_) => None,
+///         };
+///         Ok(Model { mesh, materials })
+///     }
+/// }
+///
+/// let source = MemorySource::new();
+/// source.insert("cube", "obj", "v 0 0 0");
+/// source.insert("cube", "mtl", "newmtl red");
+/// let cache = AssetCache::with_source(source);
+///
+/// let cube = cache.load_compound::<Model>("cube")?;
+/// assert_eq!(cube.read().materials.as_deref(), Some("newmtl red"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache::load_compound`]: struct.AssetCache.html#method.load_compound
+/// [`LoadContext`]: struct.LoadContext.html
+pub trait CompoundAsset: Sized + Send + Sync + 'static {
+    /// The extension of the main file of the asset.
+    ///
+    /// See [`Asset::EXT`].
+    ///
+    /// [`Asset::EXT`]: trait.Asset.html#associatedconstant.EXT
+    const EXT: &'static str;
+
+    /// The error which can be returned from loading.
+    type Err: fmt::Display;
+
+    /// Loads the asset from the files given by `context`.
+    fn load(context: &LoadContext) -> Result<Self, Self::Err>;
+}
@@ -0,0 +1,266 @@
+//! Assets that need an external context to be loaded
+
+use crate::{Asset, loader::Loader};
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::Infallible,
+    error::Error,
+    fmt,
+    io,
+    ops::{Deref, DerefMut},
+    str::{self, Utf8Error},
+};
+
+
+/// An asset that needs an external context to be loaded.
+///
+/// Some assets cannot be built from raw bytes alone: for example, a texture
+/// may need a GPU device, or a procedural asset a random seed. Such assets
+/// are loaded with [`AssetCache::load_with`], which gives a context of type
+/// `C` to the loading function, without the need of global statics.
+///
+/// Because the context is not available when files change, these assets are
+/// not reloaded by hot-reloading. They can be reloaded explicitly with
+/// [`AssetCache::reload_with`].
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{AssetCache, ContextAsset};
+/// use std::{borrow::Cow, io, str};
+///
+/// struct Scale(f32);
+///
+/// struct Distance(f32);
+///
+/// impl ContextAsset<Scale> for Distance {
+///     const EXT: &'static str = "x";
+///     type Err = Box<dyn std::error::Error>;
+///
+///     fn load_with(content: io::Result<Cow<[u8]>>, scale: &Scale) -> Result<Self, Self::Err> {
+///         let content = content?;
+///         let value: f32 = str::from_utf8(&content)?.trim().parse()?;
+///         Ok(Distance(value * scale.0))
+///     }
+/// }
+///
+/// let cache = AssetCache::new("assets")?;
+/// let distance = cache.load_with::<Distance, _>("test.cache", &Scale(0.5))?;
+/// assert_eq!(distance.read().0, 21.0);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache::load_with`]: struct.AssetCache.html#method.load_with
+/// [`AssetCache::reload_with`]: struct.AssetCache.html#method.reload_with
+pub trait ContextAsset<C: ?Sized>: Sized + Send + Sync + 'static {
+    /// The extension used by the asset files from the given asset type.
+    ///
+    /// See [`Asset::EXT`].
+    ///
+    /// [`Asset::EXT`]: trait.Asset.html#associatedconstant.EXT
+    const EXT: &'static str;
+
+    /// The error which can be returned from loading.
+    type Err: fmt::Display;
+
+    /// Converts raw bytes into the asset, using the given context.
+    fn load_with(content: io::Result<Cow<[u8]>>, context: &C) -> Result<Self, Self::Err>;
+}
+
+
+/// A map of variables used to expand placeholders in [`Interpolated`]
+/// assets.
+///
+/// [`Interpolated`]: struct.Interpolated.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variables {
+    vars: HashMap<String, String>,
+}
+
+impl Variables {
+    /// Creates an empty map of variables.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of a variable, returning its previous value if any.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.vars.insert(name.into(), value.into())
+    }
+
+    /// Returns the value of a variable.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+
+    /// Replaces `${NAME}` placeholders in `text` with the values of the
+    /// variables.
+    ///
+    /// `$${` is replaced by a literal `${`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a placeholder is not closed or names an
+    /// undefined variable.
+    pub fn expand(&self, text: &str) -> Result<String, InterpolationError<Infallible>> {
+        self.try_expand(text)
+    }
+
+    fn try_expand<E>(&self, text: &str) -> Result<String, InterpolationError<E>> {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                result.push_str(&rest[..start - 1]);
+                result.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after.find('}').ok_or(InterpolationError::Unclosed)?;
+            let name = &after[..end];
+            let value = self.get(name).ok_or_else(|| InterpolationError::Undefined(name.to_owned()))?;
+            result.push_str(value);
+            rest = &after[end + 1..];
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+}
+
+impl<K, V> std::iter::FromIterator<(K, V)> for Variables
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let vars = iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        Variables { vars }
+    }
+}
+
+/// An asset whose file is preprocessed to expand variables before loading.
+///
+/// Placeholders like `${NAME}` are replaced with values from a [`Variables`]
+/// map given as the context of [`AssetCache::load_with`]. Then the result is
+/// loaded as an `A`, with its usual loader. This allows a single template to
+/// serve several deployments.
+///
+/// The file must be valid UTF-8.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, Interpolated, Variables, loader, source::MemorySource};
+///
+/// struct Url(String);
+///
+/// impl From<String> for Url {
+///     fn from(s: String) -> Url {
+///         Url(s)
+///     }
+/// }
+///
+/// impl Asset for Url {
+///     const EXT: &'static str = "txt";
+///     type Loader = loader::LoadFrom<String, loader::StringLoader>;
+/// }
+///
+/// let source = MemorySource::new();
+/// source.insert("server", "txt", "https://${HOST}/api");
+/// let cache = AssetCache::with_source(source);
+///
+/// let vars: Variables = vec![("HOST", "example.com")].into_iter().collect();
+/// let url = cache.load_with::<Interpolated<Url>, _>("server", &vars)?;
+/// assert_eq!((url.read().0).0, "https://example.com/api");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`Variables`]: struct.Variables.html
+/// [`AssetCache::load_with`]: struct.AssetCache.html#method.load_with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Interpolated<A>(pub A);
+
+impl<A> Deref for Interpolated<A> {
+    type Target = A;
+
+    #[inline]
+    fn deref(&self) -> &A {
+        &self.0
+    }
+}
+
+impl<A> DerefMut for Interpolated<A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut A {
+        &mut self.0
+    }
+}
+
+impl<A: Asset> ContextAsset<Variables> for Interpolated<A> {
+    const EXT: &'static str = A::EXT;
+    type Err = InterpolationError<crate::AssetErr<A>>;
+
+    fn load_with(content: io::Result<Cow<[u8]>>, vars: &Variables) -> Result<Self, Self::Err> {
+        let content = content.map_err(InterpolationError::Io)?;
+        let text = str::from_utf8(&content).map_err(InterpolationError::Utf8)?;
+        let expanded = vars.try_expand(text)?;
+
+        let asset = A::Loader::load(Ok(expanded.into_bytes().into())).map_err(InterpolationError::Load)?;
+        Ok(Interpolated(asset))
+    }
+}
+
+/// An error which occurs when loading an [`Interpolated`] asset.
+///
+/// [`Interpolated`]: struct.Interpolated.html
+#[derive(Debug)]
+pub enum InterpolationError<E> {
+    /// An I/O error occured when loading the file.
+    Io(io::Error),
+
+    /// The loaded file was not valid UTF-8.
+    Utf8(Utf8Error),
+
+    /// A placeholder was opened but not closed.
+    Unclosed,
+
+    /// A placeholder named an undefined variable.
+    Undefined(String),
+
+    /// An error occured when loading the expanded file.
+    Load(E),
+}
+
+impl<E: fmt::Display> fmt::Display for InterpolationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::Utf8(err) => err.fmt(f),
+            Self::Unclosed => f.write_str("unclosed variable placeholder"),
+            Self::Undefined(name) => write!(f, "undefined variable {:?}", name),
+            Self::Load(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E> Error for InterpolationError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Utf8(err) => Some(err),
+            Self::Load(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Virtual assets computed from other assets
+
+use crate::{
+    AssetCache,
+    cache::{AccessKey, Key},
+    lock::Mutex,
+};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{Arc, atomic::{AtomicBool, Ordering}},
+};
+
+use crate::RandomState;
+
+
+thread_local! {
+    /// The keys read by the virtual assets being computed on this thread.
+    static READS: RefCell<Vec<Vec<Key>>> = RefCell::new(Vec::new());
+}
+
+/// Records that the virtual asset being computed, if any, reads `key`.
+pub(crate) fn record_read(key: &AccessKey) {
+    READS.with(|reads| {
+        if let Some(current) = reads.borrow_mut().last_mut() {
+            current.push(Key::new_with(key.id().into(), key.type_id()));
+        }
+    });
+}
+
+/// Calls `f`, returning the keys of the assets it loaded.
+pub(crate) fn tracked<R>(f: impl FnOnce() -> R) -> (R, Vec<Key>) {
+    struct Pop;
+
+    impl Drop for Pop {
+        fn drop(&mut self) {
+            READS.with(|reads| reads.borrow_mut().pop());
+        }
+    }
+
+    READS.with(|reads| reads.borrow_mut().push(Vec::new()));
+    let pop = Pop;
+    let result = f();
+
+    let keys = READS.with(|reads| reads.borrow_mut().last_mut().map(std::mem::take));
+    drop(pop);
+
+    (result, keys.unwrap_or_default())
+}
+
+
+/// Recomputes a virtual asset and stores it in the cache.
+///
+/// Returns the flag that will be set when an input changes.
+type Recompute = Box<dyn Fn(&AssetCache) -> Arc<AtomicBool> + Send + Sync>;
+
+struct Definition {
+    recompute: Recompute,
+    dirty: Mutex<Arc<AtomicBool>>,
+}
+
+/// The virtual assets defined in a cache.
+pub(crate) struct Definitions {
+    defs: HashMap<Key, Arc<Definition>, RandomState>,
+}
+
+impl Definitions {
+    pub fn new() -> Self {
+        Self {
+            defs: HashMap::with_hasher(RandomState::new()),
+        }
+    }
+
+    pub fn insert(&mut self, key: Key, recompute: Recompute, dirty: Arc<AtomicBool>) {
+        let def = Definition {
+            recompute,
+            dirty: Mutex::new(dirty),
+        };
+        self.defs.insert(key, Arc::new(def));
+    }
+
+    /// Returns `true` if the virtual asset with the given key has to be
+    /// recomputed.
+    pub fn is_dirty(&self, key: &AccessKey) -> bool {
+        self.defs.get(key).map_or(false, |def| def.dirty.lock().load(Ordering::Acquire))
+    }
+
+    pub fn contains(&self, key: &AccessKey) -> bool {
+        self.defs.contains_key(key)
+    }
+
+    /// Marks all virtual assets to be recomputed.
+    pub fn invalidate_all(&mut self) {
+        for def in self.defs.values() {
+            def.dirty.lock().store(true, Ordering::Release);
+        }
+    }
+
+    pub fn remove(&mut self, key: &AccessKey) {
+        self.defs.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.defs.clear();
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&Key) -> bool) {
+        self.defs.retain(|key, _| f(key));
+    }
+
+    fn take_dirty(&self) -> Vec<Arc<Definition>> {
+        self.defs.values()
+            .filter(|def| def.dirty.lock().swap(false, Ordering::AcqRel))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Recomputes all virtual assets whose inputs changed.
+pub(crate) fn update(cache: &AssetCache, defs: &Mutex<Definitions>) {
+    // Recomputing a virtual asset may invalidate other ones, but a cycle
+    // should not make us loop forever.
+    let max_rounds = defs.lock().defs.len() + 1;
+
+    for _ in 0..max_rounds {
+        let dirty = defs.lock().take_dirty();
+        if dirty.is_empty() {
+            break;
+        }
+
+        for def in dirty {
+            let flag = (def.recompute)(cache);
+            *def.dirty.lock() = flag;
+        }
+    }
+}
@@ -1,16 +1,18 @@
 use crate::{
+    ANY_EXTENSION,
     Asset,
     AssetCache,
     AssetErr,
     AssetRef,
     lock::{RwLock, RwLockReadGuard},
+    source::DirEntry,
 };
 
 use std::{
     io,
     fmt,
-    fs,
     marker::PhantomData,
+    slice,
 };
 
 
@@ -70,45 +72,122 @@ impl<'a> Iterator for StringIter<'a> {
     }
 }
 
+/// A file of a directory that was not loaded because it does not have the
+/// extension of the requested type.
+///
+/// Skipped files can be listed with [`DirReader::skipped`], for example to
+/// catch typos in extensions.
+///
+/// [`DirReader::skipped`]: struct.DirReader.html#method.skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    id: String,
+    ext: String,
+    other_type: Option<&'static str>,
+}
+
+impl SkippedFile {
+    /// The id of the file.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The extension of the file.
+    #[inline]
+    pub fn ext(&self) -> &str {
+        &self.ext
+    }
+
+    /// The name of another type of asset loaded in the cache with the
+    /// extension of this file, if any.
+    ///
+    /// If there is none, the file may have a mistyped extension.
+    #[inline]
+    pub fn other_type(&self) -> Option<&'static str> {
+        self.other_type
+    }
+}
+
+impl fmt::Display for SkippedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (.{})", self.id, self.ext)?;
+        match self.other_type {
+            Some(ty) => write!(f, ": extension of {}", ty),
+            None => f.write_str(": unknown extension"),
+        }
+    }
+}
+
+/// Loads all given assets in the cache.
+#[cfg(not(feature = "rayon"))]
+fn load_all<A: Asset>(cache: &AssetCache, ids: &[String]) {
+    for id in ids {
+        let _ = cache.load::<A>(id);
+    }
+}
+
+/// Loads all given assets in the cache, in parallel.
+#[cfg(feature = "rayon")]
+fn load_all<A: Asset>(cache: &AssetCache, ids: &[String]) {
+    use rayon::prelude::*;
+
+    ids.par_iter().for_each(|id| {
+        let _ = cache.load::<A>(id);
+    });
+}
+
+/// Lists the files of a directory with the extension of `A`. Other files are
+/// added to `others` with their extension.
+fn list_dir<A: Asset>(cache: &AssetCache, id: &str, others: &mut Vec<(String, String)>) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+
+    cache.source().read_dir(id, &mut |entry| {
+        if let DirEntry::File(id, ext) = entry {
+            if ext == A::EXT || A::EXT == ANY_EXTENSION {
+                files.push(id.to_owned());
+            } else {
+                others.push((id.to_owned(), ext.to_owned()));
+            }
+        }
+    })?;
+
+    // With any extension, several files may have the same id
+    if A::EXT == ANY_EXTENSION {
+        files.sort();
+        files.dedup();
+    }
+
+    Ok(files)
+}
+
 pub(crate) struct CachedDir {
+    id: Box<str>,
     assets: Box<StringList>,
+    skipped: Box<[SkippedFile]>,
 }
 
 impl CachedDir {
     pub fn load<A: Asset>(cache: &AssetCache, id: &str) -> Result<Self, io::Error> {
-        let path = cache.path_of(id, "");
-        let entries = fs::read_dir(path)?;
-
-        let mut loaded = Vec::new();
-
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-
-                if path.extension().unwrap_or_else(|| "".as_ref()) != A::EXT {
-                    continue;
-                }
-
-                let name = match path.file_stem().and_then(|n| n.to_str()) {
-                    Some(name) => name,
-                    None => continue,
-                };
-
-                if path.is_file() {
-                    let mut this_id = id.to_owned();
-                    if !this_id.is_empty() {
-                        this_id.push('.');
-                    }
-                    this_id.push_str(name);
-
-                    let _ = cache.load::<A>(&this_id);
-                    loaded.push(this_id);
-                }
-            }
-        }
+        let mut skipped = Vec::new();
+        let loaded = list_dir::<A>(cache, id, &mut skipped)?;
+
+        let mut skipped: Vec<_> = skipped.into_iter().map(|(id, ext)| {
+            let other_type = cache.type_name_for_ext(&ext);
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(id = &*id, ext = &*ext, other_type, "skipped file in directory");
+
+            SkippedFile { id, ext, other_type }
+        }).collect();
+        skipped.sort_by(|a, b| (&a.id, &a.ext).cmp(&(&b.id, &b.ext)));
+
+        load_all::<A>(cache, &loaded);
 
         Ok(Self {
+            id: id.into(),
             assets: Box::new(loaded.into()),
+            skipped: skipped.into(),
         })
     }
 
@@ -116,7 +195,9 @@ impl CachedDir {
     pub unsafe fn read<'a, A>(&self, cache: &'a AssetCache) -> DirReader<'a, A> {
         DirReader {
             cache,
+            id: &*(&*self.id as *const str),
             assets: &*(&*self.assets as *const StringList),
+            skipped: &*(&*self.skipped as *const [SkippedFile]),
             _marker: PhantomData,
         }
     }
@@ -145,23 +226,40 @@ impl CachedDir {
 /// [hot-reloading]: struct.AssetCache.html#method.hot_reload
 pub struct DirReader<'a, A> {
     cache: &'a AssetCache,
+    id: &'a str,
     assets: &'a StringList,
+    skipped: &'a [SkippedFile],
     _marker: PhantomData<&'a A>,
 }
 
 impl<A> Clone for DirReader<'_, A> {
     #[inline]
     fn clone(&self) -> Self {
-        Self {
-            cache: self.cache,
-            assets: self.assets,
-            _marker: PhantomData,
-        }
+        *self
     }
 }
 
 impl<A> Copy for DirReader<'_, A> {}
 
+impl<'a, A> DirReader<'a, A> {
+    /// Returns the id of the directory.
+    #[inline]
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// Returns the files of the directory that were not loaded because they
+    /// do not have the extension of `A`, sorted by id.
+    ///
+    /// This is useful to catch files with a mistyped extension, which would
+    /// otherwise be silently ignored. With the `tracing` feature, skipped
+    /// files are also reported as `debug` events.
+    #[inline]
+    pub fn skipped(&self) -> &'a [SkippedFile] {
+        self.skipped
+    }
+}
+
 impl<'a, A: Asset> DirReader<'a, A> {
     /// An iterator over successfully loaded assets in a directory.
     ///
@@ -193,6 +291,28 @@ impl<'a, A: Asset> DirReader<'a, A> {
             _marker: PhantomData,
         }
     }
+
+    /// An iterator over the ids of all assets in a directory.
+    ///
+    /// This iterator does not access the cache, so it does not matter whether
+    /// an asset was successfully loaded.
+    #[inline]
+    pub fn ids(&self) -> Ids<'a> {
+        Ids {
+            iter: self.assets.into_iter(),
+        }
+    }
+
+    /// Checks if the directory contains an asset with the given id.
+    ///
+    /// `id` is the full id of the asset, not only its name in the directory.
+    /// As [`ids`], this function does not access the cache.
+    ///
+    /// [`ids`]: #method.ids
+    #[inline]
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids().any(|this_id| this_id == id)
+    }
 }
 
 impl<'a, A> IntoIterator for &DirReader<'a, A>
@@ -208,6 +328,128 @@ where
     }
 }
 
+/// A directory whose assets are only loaded when they are accessed.
+///
+/// It can be obtained by calling [`AssetCache::load_dir_lazy`].
+///
+/// Unlike [`DirReader`], creating a `LazyDir` only lists the files of the
+/// directory: each asset is loaded the first time it is accessed, which is
+/// useful to enumerate big directories (e.g. for a menu) without parsing all
+/// their files. Loaded assets are stored in the cache as usual.
+///
+/// The list of files is the one at creation, and is not cached.
+///
+/// [`AssetCache::load_dir_lazy`]: struct.AssetCache.html#method.load_dir_lazy
+/// [`DirReader`]: struct.DirReader.html
+pub struct LazyDir<'a, A> {
+    cache: &'a AssetCache,
+    id: Box<str>,
+    ids: Vec<String>,
+    _marker: PhantomData<&'a A>,
+}
+
+impl<'a, A: Asset> LazyDir<'a, A> {
+    pub(crate) fn new(cache: &'a AssetCache, id: &str) -> io::Result<Self> {
+        let mut ids = list_dir::<A>(cache, id, &mut Vec::new())?;
+        ids.sort();
+
+        Ok(Self {
+            cache,
+            id: id.into(),
+            ids,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the id of the directory.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the ids of the assets in the directory, sorted.
+    ///
+    /// This does not load anything.
+    #[inline]
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// Returns the number of assets in the directory.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if the directory contains no asset.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Loads the asset at the given position in [`ids`].
+    ///
+    /// [`ids`]: #method.ids
+    pub fn get(&self, index: usize) -> Option<Result<AssetRef<'a, A>, AssetErr<A>>> {
+        let id = self.ids.get(index)?;
+        Some(self.cache.load(id))
+    }
+
+    /// An iterator over the assets in the directory, which loads each of them
+    /// when it is reached.
+    #[inline]
+    pub fn iter(&self) -> LazyDirIter<'_, 'a, A> {
+        LazyDirIter {
+            cache: self.cache,
+            iter: self.ids.iter(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A> fmt::Debug for LazyDir<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyDir")
+            .field("id", &self.id)
+            .field("ids", &self.ids)
+            .finish()
+    }
+}
+
+/// An iterator over the assets of a [`LazyDir`], which loads each of them
+/// when it is reached.
+///
+/// It can be obtained by calling [`LazyDir::iter`].
+///
+/// [`LazyDir`]: struct.LazyDir.html
+/// [`LazyDir::iter`]: struct.LazyDir.html#method.iter
+pub struct LazyDirIter<'b, 'a, A> {
+    cache: &'a AssetCache,
+    iter: slice::Iter<'b, String>,
+    _marker: PhantomData<&'a A>,
+}
+
+impl<'b, 'a, A: Asset> Iterator for LazyDirIter<'b, 'a, A> {
+    type Item = (&'b str, Result<AssetRef<'a, A>, AssetErr<A>>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.iter.next()?;
+        Some((id, self.cache.load(id)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<A> fmt::Debug for LazyDirIter<'_, '_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyDirIter").finish()
+    }
+}
+
 /// An iterator over successfully loaded assets in a directory.
 ///
 /// This iterator yields each asset that was successfully loaded.
@@ -266,6 +508,30 @@ where
     }
 }
 
+/// An iterator over the ids of the assets in a directory.
+///
+/// It can be obtained by calling [`DirReader::ids`].
+///
+/// [`DirReader::ids`]: struct.DirReader.html#method.ids
+pub struct Ids<'a> {
+    iter: StringIter<'a>,
+}
+
+impl<'a> Iterator for Ids<'a> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+impl fmt::Debug for Ids<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ids").finish()
+    }
+}
+
 impl<A> fmt::Debug for DirReader<'_, A>
 where
     A: fmt::Debug + Asset,
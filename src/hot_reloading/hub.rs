@@ -0,0 +1,253 @@
+//! A filesystem watcher shared by several caches
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, channel, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use notify::Watcher as _;
+
+use super::{
+    AnyWatcher,
+    DEFAULT_POLL_INTERVAL,
+    FileCache,
+    HotReloadStatus,
+    JoinOnDrop,
+    SharedPtr,
+    handle_event,
+};
+use crate::{
+    AssetCache,
+    lock::Mutex,
+};
+
+use crate::RandomState;
+
+
+enum Command {
+    Add(usize),
+    Remove(usize),
+    Update(usize, SharedPtr<AssetCache>, Sender<()>),
+}
+
+struct Watched {
+    watcher: AnyWatcher,
+
+    /// The number of caches using each watched root.
+    roots: HashMap<PathBuf, usize, RandomState>,
+}
+
+#[allow(unused)]
+struct Hub {
+    // The Watcher has to be dropped before the JoinHandle, so the spawned
+    // thread can be notified that it should end before we join on it
+    watched: Mutex<Watched>,
+    sender: Mutex<Sender<Command>>,
+    next_slot: AtomicUsize,
+    handle: JoinOnDrop,
+}
+
+impl Hub {
+    fn send(&self, command: Command) {
+        let _ = self.sender.lock().send(command);
+    }
+}
+
+
+/// A filesystem watcher shared by several [`AssetCache`]s.
+///
+/// By default, each cache that uses hot-reloading starts its own watcher and
+/// its own thread. When an application uses several caches (e.g. one for UI
+/// assets and one for level assets), they can share a single hub instead with
+/// [`AssetCache::hot_reload_with`], which reduces the number of threads and
+/// OS watch handles.
+///
+/// A `HotReloadHub` can be cloned cheaply: clones refer to the same watcher.
+/// The watcher is stopped when the hub and all caches using it are dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use assets_manager::{AssetCache, HotReloadHub};
+///
+/// let hub = HotReloadHub::new()?;
+/// let ui = AssetCache::new("assets/ui")?;
+/// let levels = AssetCache::new("assets/levels")?;
+///
+/// loop {
+///     ui.hot_reload_with(&hub)?;
+///     levels.hot_reload_with(&hub)?;
+///
+///     // Use the assets
+/// #   break;
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache`]: struct.AssetCache.html
+/// [`AssetCache::hot_reload_with`]: struct.AssetCache.html#method.hot_reload_with
+#[derive(Clone)]
+pub struct HotReloadHub {
+    inner: Arc<Hub>,
+}
+
+impl HotReloadHub {
+    /// Creates a new hub and starts its watcher.
+    ///
+    /// If the native watcher of the platform is not available, files are
+    /// polled instead.
+    pub fn new() -> Result<Self, notify::Error> {
+        let (notify_tx, notify_rx) = channel();
+        let (command_tx, command_rx) = channel();
+
+        let watcher = match notify::watcher(notify_tx.clone(), Duration::from_millis(50)) {
+            Ok(watcher) => AnyWatcher::Native(watcher),
+            Err(err) => {
+                log::warn!("Cannot start native watcher ({}), falling back to polling", err);
+                AnyWatcher::Poll(notify::PollWatcher::new(notify_tx, DEFAULT_POLL_INTERVAL)?)
+            },
+        };
+
+        let handle = thread::spawn(move || {
+            const TIMEOUT: Duration = Duration::from_millis(20);
+            let mut caches = HashMap::with_hasher(RandomState::new());
+
+            loop {
+                match command_rx.recv_timeout(TIMEOUT) {
+                    Err(mpsc::RecvTimeoutError::Timeout) => (),
+                    Ok(Command::Add(slot)) => {
+                        caches.insert(slot, FileCache::new());
+                    },
+                    Ok(Command::Remove(slot)) => {
+                        caches.remove(&slot);
+                    },
+                    Ok(Command::Update(slot, SharedPtr(ptr), answer)) => {
+                        if let Some(cache) = caches.get_mut(&slot) {
+                            // Safety: The received pointer is guarantied to be
+                            // valid until we reply back
+                            let asset_cache = unsafe { ptr.as_ref() };
+                            cache.update(asset_cache);
+                            cache.get_watched(&mut asset_cache.watched.lock());
+                        }
+                        let _ = answer.send(());
+                    },
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+
+                while let Ok(event) = notify_rx.try_recv() {
+                    handle_event(event, caches.values_mut());
+                }
+            }
+        }).into();
+
+        let hub = Hub {
+            watched: Mutex::new(Watched {
+                watcher,
+                roots: HashMap::with_hasher(RandomState::new()),
+            }),
+            sender: Mutex::new(command_tx),
+            next_slot: AtomicUsize::new(0),
+            handle,
+        };
+
+        Ok(Self { inner: Arc::new(hub) })
+    }
+
+    /// Returns the state of the hub's watcher.
+    ///
+    /// This is never [`HotReloadStatus::Stopped`].
+    ///
+    /// [`HotReloadStatus::Stopped`]: enum.HotReloadStatus.html#variant.Stopped
+    pub fn status(&self) -> HotReloadStatus {
+        self.inner.watched.lock().watcher.status()
+    }
+
+    /// Starts watching `root` for a new cache.
+    pub(crate) fn register(&self, root: &Path) -> Result<HubClient, notify::Error> {
+        {
+            let mut watched = self.inner.watched.lock();
+            let Watched { watcher, roots } = &mut *watched;
+
+            match roots.get_mut(root) {
+                Some(count) => *count += 1,
+                None => {
+                    watcher.watch(root)?;
+                    roots.insert(root.to_owned(), 1);
+                },
+            }
+        }
+
+        let slot = self.inner.next_slot.fetch_add(1, Ordering::Relaxed);
+        self.inner.send(Command::Add(slot));
+
+        Ok(HubClient {
+            hub: self.inner.clone(),
+            slot,
+            root: root.to_owned(),
+        })
+    }
+}
+
+impl fmt::Debug for HotReloadHub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HotReloadHub")
+            .field("roots", &self.inner.watched.lock().roots)
+            .finish()
+    }
+}
+
+
+/// A cache registered in a hub.
+pub struct HubClient {
+    hub: Arc<Hub>,
+    slot: usize,
+    root: PathBuf,
+}
+
+impl HubClient {
+    #[inline]
+    pub fn is_from(&self, hub: &HotReloadHub) -> bool {
+        Arc::ptr_eq(&self.hub, &hub.inner)
+    }
+
+    #[inline]
+    pub fn status(&self) -> HotReloadStatus {
+        self.hub.watched.lock().watcher.status()
+    }
+
+    pub fn reload(&self, cache: &AssetCache) -> bool {
+        let (answer_tx, answer_rx) = channel();
+        self.hub.send(Command::Update(self.slot, SharedPtr(cache.into()), answer_tx));
+
+        // If the hub's thread panicked, the answer sender is dropped without
+        // being used, so this does not block.
+        answer_rx.recv().is_ok()
+    }
+}
+
+impl Drop for HubClient {
+    fn drop(&mut self) {
+        self.hub.send(Command::Remove(self.slot));
+
+        let mut watched = self.hub.watched.lock();
+        let Watched { watcher, roots } = &mut *watched;
+
+        if let Some(count) = roots.get_mut(&self.root) {
+            *count -= 1;
+            if *count == 0 {
+                roots.remove(&self.root);
+                if let Err(err) = watcher.unwatch(&self.root) {
+                    log::warn!("Cannot stop watching {:?}: {}", self.root, err);
+                }
+            }
+        }
+    }
+}
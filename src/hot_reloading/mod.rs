@@ -1,18 +1,23 @@
+mod hub;
 mod paths;
 
+pub use hub::HotReloadHub;
+use hub::HubClient;
 pub(crate) use paths::WatchedPaths;
 use paths::FileCache;
 
 use std::{
+    collections::HashSet,
     fmt,
     mem::ManuallyDrop,
+    path::{Path, PathBuf},
     ptr::NonNull,
     sync::mpsc::{self, channel, Receiver, Sender},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use notify::{DebouncedEvent, RecursiveMode, Watcher as _};
 
 use crate::{
     AssetCache,
@@ -40,27 +45,426 @@ impl From<thread::JoinHandle<()>> for JoinOnDrop {
 }
 
 
+/// The state of hot-reloading for an [`AssetCache`].
+///
+/// It can be obtained with [`AssetCache::hot_reload_status`].
+///
+/// [`AssetCache`]: struct.AssetCache.html
+/// [`AssetCache::hot_reload_status`]: struct.AssetCache.html#method.hot_reload_status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotReloadStatus {
+    /// Hot-reloading has not been started or was stopped.
+    Stopped,
+
+    /// Changes are reported by the native watcher of the platform.
+    Running,
+
+    /// The native watcher could not be created, so files are polled
+    /// periodically instead.
+    ///
+    /// Changes may take up to the polling interval to be noticed.
+    Polling,
+
+    /// The hot-reloading thread or the filesystem watcher stopped
+    /// unexpectedly, and will be restarted by a next call to `hot_reload`.
+    ///
+    /// Restarts are delayed more and more if they keep failing, up to 30
+    /// seconds. Changes made on the filesystem in the meantime may be missed.
+    Restarting,
+}
+
+
+/// What to do with an asset when its file is removed while hot-reloading.
+///
+/// It can be set with [`AssetCache::set_removal_policy`].
+///
+/// [`AssetCache::set_removal_policy`]: struct.AssetCache.html#method.set_removal_policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalPolicy {
+    /// The asset keeps its last value.
+    ///
+    /// This is the default.
+    KeepLastValue,
+
+    /// The asset is removed from the cache.
+    ///
+    /// Existing [`AssetRef`]s stay valid and keep the last value, but the
+    /// asset will not be returned by [`load_cached`] anymore, and [`load`]
+    /// will try to load it again. Channels returned by [`updates`] for this
+    /// asset are closed.
+    ///
+    /// [`AssetRef`]: struct.AssetRef.html
+    /// [`load_cached`]: struct.AssetCache.html#method.load_cached
+    /// [`load`]: struct.AssetCache.html#method.load
+    /// [`updates`]: struct.AssetCache.html#method.updates
+    RemoveEntry,
+
+    /// The asset is reloaded as if the file could not be read, so its loader
+    /// can provide a fallback value (e.g. with [`LoadOrDefault`]).
+    ///
+    /// If the loader returns an error, the asset keeps its last value.
+    ///
+    /// [`LoadOrDefault`]: loader/struct.LoadOrDefault.html
+    UseFallback,
+}
+
+impl Default for RemovalPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::KeepLastValue
+    }
+}
+
+
+/// Which directories are watched to detect changes while hot-reloading.
+///
+/// It can be set with [`AssetCache::set_watch_mode`].
+///
+/// [`AssetCache::set_watch_mode`]: struct.AssetCache.html#method.set_watch_mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// The whole directory of the cache is watched, including directories
+    /// that do not contain loaded assets.
+    ///
+    /// This is the default.
+    Recursive,
+
+    /// Only the directories that contain loaded assets are watched, each one
+    /// non-recursively.
+    ///
+    /// On Linux, this uses far fewer inotify watches than `Recursive` for
+    /// large trees with few loaded assets, which avoids exhausting the system
+    /// limit. However, directories are not watched before an asset is loaded
+    /// from them, so [`load_dir`] does not see files added to new
+    /// directories.
+    ///
+    /// [`load_dir`]: struct.AssetCache.html#method.load_dir
+    LoadedDirs,
+}
+
+impl Default for WatchMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Recursive
+    }
+}
+
+
+/// The default interval between two scans of the filesystem when the native
+/// watcher is not available.
+pub(crate) const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The delay before the first restart of a reloader that stopped.
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The maximum delay before restarting a reloader that stopped.
+///
+/// A reloader that runs for this long is considered healthy again.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Keeps track of the failures of a cache's reloader, to restart it with an
+/// exponential backoff.
+pub(crate) struct Supervisor {
+    /// The number of consecutive failures.
+    failures: u32,
+    /// When the reloader stopped, if it was not restarted since.
+    stopped_at: Option<Instant>,
+    /// When the current reloader was started.
+    started_at: Option<Instant>,
+    restarts: usize,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self {
+            failures: 0,
+            stopped_at: None,
+            started_at: None,
+            restarts: 0,
+        }
+    }
+
+    fn backoff(&self) -> Duration {
+        let factor = 1 << self.failures.saturating_sub(1).min(16);
+        (MIN_BACKOFF * factor).min(MAX_BACKOFF)
+    }
+
+    /// Records that the reloader stopped unexpectedly, or could not be
+    /// restarted.
+    pub fn failed(&mut self) {
+        let now = Instant::now();
+        if self.started_at.take().map_or(false, |t| now.duration_since(t) >= MAX_BACKOFF) {
+            self.failures = 0;
+        }
+        self.failures += 1;
+        self.stopped_at = Some(now);
+
+        log::warn!("Restarting hot-reloading in {:?}", self.backoff());
+    }
+
+    /// Returns `true` if a reloader can be started now.
+    pub fn may_start(&self) -> bool {
+        self.stopped_at.map_or(true, |t| t.elapsed() >= self.backoff())
+    }
+
+    /// Records that a reloader was started.
+    pub fn started(&mut self) {
+        if self.stopped_at.take().is_some() {
+            self.restarts += 1;
+            log::info!("Hot-reloading restarted");
+        }
+        self.started_at = Some(Instant::now());
+    }
+
+    /// Returns `true` if the reloader stopped and has not been restarted yet.
+    pub fn is_restarting(&self) -> bool {
+        self.stopped_at.is_some()
+    }
+
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    /// Forgets past failures, when hot-reloading is stopped on purpose.
+    pub fn reset(&mut self) {
+        self.failures = 0;
+        self.stopped_at = None;
+        self.started_at = None;
+    }
+}
+
+#[allow(unused)]
+enum AnyWatcher {
+    Native(notify::RecommendedWatcher),
+    Poll(notify::PollWatcher),
+}
+
+/// Creates a native watcher, watching `path` recursively if any.
+fn native_watcher(tx: Sender<DebouncedEvent>, path: Option<&Path>) -> Result<AnyWatcher, notify::Error> {
+    let mut watcher = AnyWatcher::Native(notify::watcher(tx, Duration::from_millis(50))?);
+    if let Some(path) = path {
+        watcher.watch(path)?;
+    }
+    Ok(watcher)
+}
+
+/// Creates a polling watcher, watching `path` recursively if any.
+fn poll_watcher(tx: Sender<DebouncedEvent>, path: Option<&Path>, interval: Duration) -> Result<AnyWatcher, notify::Error> {
+    let mut watcher = AnyWatcher::Poll(notify::PollWatcher::new(tx, interval)?);
+    if let Some(path) = path {
+        watcher.watch(path)?;
+    }
+    Ok(watcher)
+}
+
+impl AnyWatcher {
+    fn status(&self) -> HotReloadStatus {
+        match self {
+            AnyWatcher::Native(_) => HotReloadStatus::Running,
+            AnyWatcher::Poll(_) => HotReloadStatus::Polling,
+        }
+    }
+
+    fn watch(&mut self, path: &Path) -> Result<(), notify::Error> {
+        match self {
+            AnyWatcher::Native(watcher) => watcher.watch(path, RecursiveMode::Recursive),
+            AnyWatcher::Poll(watcher) => watcher.watch(path, RecursiveMode::Recursive),
+        }
+    }
+
+    fn watch_dir(&mut self, path: &Path) -> Result<(), notify::Error> {
+        match self {
+            AnyWatcher::Native(watcher) => watcher.watch(path, RecursiveMode::NonRecursive),
+            AnyWatcher::Poll(watcher) => watcher.watch(path, RecursiveMode::NonRecursive),
+        }
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<(), notify::Error> {
+        match self {
+            AnyWatcher::Native(watcher) => watcher.unwatch(path),
+            AnyWatcher::Poll(watcher) => watcher.unwatch(path),
+        }
+    }
+}
+
+/// Converts a path given by the watcher to the form used for watched paths.
+///
+/// On Windows, the root of a cache is canonicalized to a verbatim path (with
+/// a `\\?\` prefix), which supports long paths and network shares. Paths of
+/// events are converted to the same form, so they match.
+#[cfg(windows)]
+fn normalize_path(path: PathBuf) -> PathBuf {
+    verbatim_path(&path).unwrap_or(path)
+}
+
+/// Returns the verbatim form of an absolute path that is not already
+/// verbatim.
+#[cfg(windows)]
+fn verbatim_path(path: &Path) -> Option<PathBuf> {
+    use std::{ffi::OsString, path::{Component, Prefix}};
+
+    let mut components = path.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => prefix,
+        _ => return None,
+    };
+
+    let mut normalized = match prefix.kind() {
+        Prefix::Disk(letter) => {
+            let mut s = OsString::from(r"\\?\");
+            s.push(format!("{}:\\", letter as char));
+            PathBuf::from(s)
+        },
+        Prefix::UNC(server, share) => {
+            let mut s = OsString::from(r"\\?\UNC\");
+            s.push(server);
+            s.push(r"\");
+            s.push(share);
+            s.push(r"\");
+            PathBuf::from(s)
+        },
+        _ => return None,
+    };
+
+    for component in components {
+        match component {
+            Component::RootDir | Component::CurDir => (),
+            Component::ParentDir => { normalized.pop(); },
+            other => normalized.push(other),
+        }
+    }
+    Some(normalized)
+}
+
+#[cfg(not(windows))]
+#[inline]
+fn normalize_path(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// Applies a filesystem event to file caches.
+fn handle_event<'a>(event: DebouncedEvent, caches: impl Iterator<Item = &'a mut FileCache>) {
+    match event {
+        DebouncedEvent::Write(path)
+        | DebouncedEvent::Chmod(path)
+        | DebouncedEvent::Create(path) => {
+            let path = normalize_path(path);
+            for cache in caches {
+                cache.load(path.clone());
+            }
+        },
+        DebouncedEvent::Rename(src, dst) => {
+            let (src, dst) = (normalize_path(src), normalize_path(dst));
+            for cache in caches {
+                cache.remove(src.clone());
+                cache.load(dst.clone());
+            }
+        },
+        DebouncedEvent::Remove(path) => {
+            let path = normalize_path(path);
+            for cache in caches {
+                cache.remove(path.clone());
+            }
+        },
+        DebouncedEvent::Error(err, path) => {
+            log::error!("Error while watching {:?}: {}", path, err);
+        },
+        DebouncedEvent::Rescan => {
+            log::warn!("Some filesystem events may have been missed");
+        },
+        DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => (),
+    }
+}
+
+
+/// The hot-reloading state of a cache, either with its own watcher or with
+/// one shared with other caches.
+pub enum HotReloader {
+    Local(LocalReloader),
+    Shared(HubClient),
+}
+
+impl HotReloader {
+    #[inline]
+    pub fn start(cache: &AssetCache, poll_interval: Duration, mode: WatchMode) -> Result<Self, notify::Error> {
+        LocalReloader::start(cache, poll_interval, mode).map(HotReloader::Local)
+    }
+
+    #[inline]
+    pub fn start_shared(cache: &AssetCache, hub: &HotReloadHub) -> Result<Self, notify::Error> {
+        hub.register(cache.path()).map(HotReloader::Shared)
+    }
+
+    /// Returns `true` if the reloader is a client of the given hub.
+    #[inline]
+    pub fn uses_hub(&self, hub: &HotReloadHub) -> bool {
+        match self {
+            HotReloader::Local(_) => false,
+            HotReloader::Shared(client) => client.is_from(hub),
+        }
+    }
+
+    #[inline]
+    pub fn status(&self) -> HotReloadStatus {
+        match self {
+            HotReloader::Local(reloader) => reloader.status(),
+            HotReloader::Shared(client) => client.status(),
+        }
+    }
+
+    /// Applies changes to the cache.
+    ///
+    /// Returns `false` if the reloader's thread stopped unexpectedly.
+    #[inline]
+    pub fn reload(&mut self, cache: &AssetCache) -> bool {
+        match self {
+            HotReloader::Local(reloader) => reloader.reload(cache),
+            HotReloader::Shared(client) => client.reload(cache),
+        }
+    }
+}
+
+impl fmt::Debug for HotReloader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("HotReloader { .. }")
+    }
+}
+
+
 #[allow(unused)]
-pub struct HotReloader {
+pub struct LocalReloader {
     sender: Sender<SharedPtr<AssetCache>>,
     receiver: Receiver<()>,
 
     // The Watcher has to be dropped before the JoinHandle, so the spawned
     // thread can be notified that it should end before we join on it
-    watcher: notify::RecommendedWatcher,
+    watcher: AnyWatcher,
     handle: JoinOnDrop,
+
+    /// The directories watched with `WatchMode::LoadedDirs`.
+    dirs: Option<HashSet<PathBuf>>,
 }
 
 
-impl HotReloader {
-    pub fn start(cache: &AssetCache) -> Result<Self, notify::Error> {
+impl LocalReloader {
+    pub fn start(cache: &AssetCache, poll_interval: Duration, mode: WatchMode) -> Result<Self, notify::Error> {
         let (notify_tx, notify_rx) = channel();
 
         let (ptr_tx, ptr_rx) = channel();
         let (answer_tx, answer_rx) = channel();
 
-        let mut watcher = notify::watcher(notify_tx, Duration::from_millis(50))?;
-        watcher.watch(cache.path(), RecursiveMode::Recursive)?;
+        let (root, dirs) = match mode {
+            WatchMode::Recursive => (Some(cache.path()), None),
+            WatchMode::LoadedDirs => (None, Some(HashSet::new())),
+        };
+
+        let watcher = match native_watcher(notify_tx.clone(), root) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::warn!("Cannot start native watcher ({}), falling back to polling", err);
+                poll_watcher(notify_tx, root, poll_interval)?
+            },
+        };
 
         let handle = thread::spawn(move || {
             const TIMEOUT: Duration = Duration::from_millis(20);
@@ -82,37 +486,77 @@ impl HotReloader {
                     Err(mpsc::RecvTimeoutError::Disconnected) => break,
                 }
 
-                while let Ok(event) = notify_rx.try_recv() {
-                    match event {
-                        DebouncedEvent::Write(path)
-                        | DebouncedEvent::Chmod(path)
-                        | DebouncedEvent::Create(path)
-                        | DebouncedEvent::Rename(_, path) => {
-                            cache.load(path);
+                loop {
+                    match notify_rx.try_recv() {
+                        Ok(event) => handle_event(event, std::iter::once(&mut cache)),
+                        Err(mpsc::TryRecvError::Empty) => break,
+                        // The watcher died, so stop to be restarted
+                        Err(mpsc::TryRecvError::Disconnected) => {
+                            log::error!("The filesystem watcher stopped unexpectedly");
+                            return;
                         },
-                        _ => (),
                     }
                 }
             }
         }).into();
 
-        Ok(HotReloader {
+        let mut reloader = LocalReloader {
             watcher,
             handle,
 
             sender: ptr_tx,
             receiver: answer_rx,
-        })
+
+            dirs,
+        };
+        reloader.watch_dirs(cache.watched.lock().dirs());
+
+        Ok(reloader)
     }
 
-    pub fn reload(&self, cache: &AssetCache) {
-        self.sender.send(SharedPtr(cache.into())).unwrap();
-        self.receiver.recv().unwrap();
+    /// Watches the given directories if they are not already, with
+    /// `WatchMode::LoadedDirs`.
+    fn watch_dirs<'a>(&mut self, new_dirs: impl Iterator<Item = &'a Path>) {
+        if let Some(dirs) = &mut self.dirs {
+            for dir in new_dirs {
+                if !dirs.contains(dir) {
+                    match self.watcher.watch_dir(dir) {
+                        Ok(()) => {
+                            dirs.insert(dir.to_owned());
+                        },
+                        Err(err) => log::warn!("Cannot watch {:?}: {}", dir, err),
+                    }
+                }
+            }
+        }
     }
-}
 
-impl fmt::Debug for HotReloader {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.pad("HotReloader { .. }")
+    pub fn status(&self) -> HotReloadStatus {
+        self.watcher.status()
+    }
+
+    pub fn reload(&mut self, cache: &AssetCache) -> bool {
+        if let Some(dirs) = &mut self.dirs {
+            let watched = cache.watched.lock();
+
+            // Stop watching directories that do not contain watched paths
+            // anymore, but keep the others to avoid missing events.
+            if watched.is_cleared() {
+                let kept: HashSet<&Path> = watched.dirs().collect();
+                let watcher = &mut self.watcher;
+                dirs.retain(|dir| {
+                    let keep = kept.contains(&**dir);
+                    if !keep {
+                        let _ = watcher.unwatch(dir);
+                    }
+                    keep
+                });
+            }
+            self.watch_dirs(watched.added_dirs());
+        }
+
+        // If the thread stopped, the answer sender is dropped without being
+        // used, so this does not block.
+        self.sender.send(SharedPtr(cache.into())).is_ok() && self.receiver.recv().is_ok()
     }
 }
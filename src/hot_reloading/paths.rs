@@ -1,18 +1,26 @@
 use std::{
     any::{Any, TypeId},
-    borrow::Cow,
-    collections::HashMap,
+    borrow::{Borrow, Cow},
+    collections::{HashMap, HashSet},
+    fmt,
     fs,
-    io,
+    io::{self, Read},
+    mem,
+    panic::{self, AssertUnwindSafe},
     path::{Path, PathBuf},
 };
 
+use super::RemovalPolicy;
 use crate::{
     Asset,
     AssetCache,
-    cache::Key,
-    loader::Loader,
+    CollisionPolicy,
+    CompoundAsset,
+    LoadContext,
+    cache::{AccessKey, Key, content_hash, read_file},
+    loader::{Loader, panic_message},
     lock::CacheEntry,
+    source::{FileSystem, SymlinkPolicy},
 };
 
 use crate::RandomState;
@@ -41,18 +49,17 @@ impl<T> Types<T> {
             self.0.push((type_id, t));
         }
     }
-}
 
-impl Types<LoadFn> {
     #[inline]
-    fn insert_with<A: Asset>(&mut self) {
-        self.insert(TypeId::of::<A>(), load::<A>);
+    fn retain(&mut self, mut f: impl FnMut(TypeId) -> bool) {
+        self.0.retain(|(type_id, _)| f(*type_id));
     }
 }
 
-fn borrowed(content: &io::Result<Vec<u8>>) -> io::Result<Cow<[u8]>> {
+
+fn borrowed<'a>(content: &io::Result<&'a [u8]>) -> io::Result<Cow<'a, [u8]>> {
     match content {
-        Ok(bytes) => Ok(bytes.into()),
+        Ok(bytes) => Ok(Cow::Borrowed(bytes)),
         Err(err) => match err.raw_os_error() {
             Some(e) => Err(io::Error::from_raw_os_error(e)),
             None => Err(err.kind().into()),
@@ -62,23 +69,115 @@ fn borrowed(content: &io::Result<Vec<u8>>) -> io::Result<Cow<[u8]>> {
 
 
 trait AnyAsset: Any + Send + Sync {
-    unsafe fn reload(self: Box<Self>, entry: &CacheEntry);
+    unsafe fn reload(self: Box<Self>, entry: &CacheEntry, hash: Option<u64>);
+}
+
+impl<A: Send + Sync + 'static> AnyAsset for A {
+    unsafe fn reload(self: Box<Self>, entry: &CacheEntry, hash: Option<u64>) {
+        entry.write::<A>(*self, hash);
+    }
+}
+
+
+/// A reloaded asset, with the hash of the content of the main file it was
+/// loaded from, if any.
+type Reloaded = (Box<dyn AnyAsset>, Option<u64>);
+
+/// Loads an asset from the content of the changed file at `path`.
+///
+/// Other files are read through `filesystem`, which applies the checks of the
+/// cache's source.
+type LoadFn = fn(
+    content: io::Result<Cow<[u8]>>,
+    id: &str,
+    path: &Path,
+    filesystem: Option<&FileSystem>,
+) -> Option<Reloaded>;
+
+fn load<A: Asset>(content: io::Result<Cow<[u8]>>, id: &str, path: &Path, filesystem: Option<&FileSystem>) -> Option<Reloaded> {
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let hash = content.as_ref().ok().map(|content| content_hash(content));
+    let read = |ext: &str, buf: &mut Vec<u8>| read_sibling(filesystem, path, ext, buf);
+    let context = LoadContext::new(id, ext, &read).with_content(content.as_ref().map(|content| &content[..]));
+    load_with(context, id, path, A::Loader::load_with_context).map(|asset| (asset, hash))
+}
+
+/// Loads an asset from its main file when a file it depends on changed at
+/// `path`.
+///
+/// The hash is the one of the main file, not of the changed one.
+fn load_dependent<A: Asset>(_: io::Result<Cow<[u8]>>, id: &str, path: &Path, filesystem: Option<&FileSystem>) -> Option<Reloaded> {
+    let mut buf = Vec::new();
+    let content = read_sibling(filesystem, path, A::EXT, &mut buf).map(|_| &buf[..]);
+    let hash = content.as_ref().ok().map(|content| content_hash(content));
+    let read = |ext: &str, buf: &mut Vec<u8>| read_sibling(filesystem, path, ext, buf);
+    let context = LoadContext::new(id, A::EXT, &read).with_content(content.as_ref().map(|content| *content));
+    load_with(context, id, path, A::Loader::load_with_context).map(|asset| (asset, hash))
+}
+
+/// Loads a compound asset, reading its files next to `path`, which may be the
+/// path of any of them.
+///
+/// Compound assets have no main file, so they have no content hash, as when
+/// they are first loaded.
+fn load_compound<A: CompoundAsset>(_: io::Result<Cow<[u8]>>, id: &str, path: &Path, filesystem: Option<&FileSystem>) -> Option<Reloaded> {
+    let read = |ext: &str, buf: &mut Vec<u8>| read_sibling(filesystem, path, ext, buf);
+    load_with(LoadContext::new(id, A::EXT, &read), id, path, A::load).map(|asset| (asset, None))
 }
 
-impl<A: Asset> AnyAsset for A {
-    unsafe fn reload(self: Box<Self>, entry: &CacheEntry) {
-        entry.write::<A>(*self);
+/// Reads the file next to `path` with the given extension.
+///
+/// The file is rejected if it cannot be read according to the symbolic link
+/// policy and the confinement of `filesystem`, as with initial loads.
+fn read_sibling(filesystem: Option<&FileSystem>, path: &Path, ext: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+    let path = path.with_extension(ext);
+    if let Some(filesystem) = filesystem {
+        filesystem.check_path(&path)?;
     }
+    let mut file = fs::File::open(path)?;
+    file.read_to_end(buf).map(drop)
 }
 
+fn load_with<A, E>(
+    context: LoadContext,
+    id: &str,
+    path: &Path,
+    load: fn(&LoadContext) -> Result<A, E>,
+) -> Option<Box<dyn AnyAsset>>
+where
+    A: Send + Sync + 'static,
+    E: fmt::Display,
+{
+    // A panicking loader must not kill the thread that reloads assets
+    let result = match panic::catch_unwind(AssertUnwindSafe(|| load(&context))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let msg = panic_message(&*payload);
+            log::warn!("Loader panicked when reloading {:?} from {:?}: {}", id, path, msg);
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(id, ty = std::any::type_name::<A>(), panic = %msg, "loader panicked when reloading asset");
+
+            return None;
+        },
+    };
+
+    let (_, warnings) = context.finish();
+    for warning in warnings {
+        log::warn!("{:?}: {}", id, warning);
 
-type LoadFn = fn(content: io::Result<Cow<[u8]>>, id: &str, path: &Path) -> Option<Box<dyn AnyAsset>>;
+        #[cfg(feature = "tracing")]
+        tracing::warn!(id, warning = %warning, "loader warning");
+    }
 
-fn load<A: Asset>(content: io::Result<Cow<[u8]>>, id: &str, path: &Path) -> Option<Box<dyn AnyAsset>> {
-    match A::Loader::load(content) {
+    match result {
         Ok(asset) => Some(Box::new(asset)),
         Err(e) => {
             log::warn!("Error reloading {:?} from {:?}: {}", id, path, e);
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(id, ty = std::any::type_name::<A>(), error = %e, "failed to reload asset");
+
             None
         },
     }
@@ -102,6 +201,11 @@ impl WatchedPath {
 pub struct WatchedPaths {
     paths: HashMap<PathBuf, WatchedPath, RandomState>,
     added: Vec<(PathBuf, TypeId)>,
+    ignored: Vec<Box<str>>,
+    ignored_changed: bool,
+    filesystem: Option<FileSystem>,
+    filesystem_changed: bool,
+    collision_policy: CollisionPolicy,
     cleared: bool,
 }
 
@@ -110,26 +214,72 @@ impl WatchedPaths {
         Self {
             paths: HashMap::with_hasher(RandomState::new()),
             added: Vec::new(),
+            ignored: Vec::new(),
+            ignored_changed: false,
+            filesystem: None,
+            filesystem_changed: false,
+            collision_policy: CollisionPolicy::default(),
             cleared: false,
         }
     }
 
+    pub fn ignore(&mut self, prefix: &str) {
+        if !self.ignored.iter().any(|p| &**p == prefix) {
+            self.ignored.push(prefix.into());
+            self.ignored_changed = true;
+        }
+    }
+
+    /// Sets the source whose checks are applied to changed files.
+    pub fn set_filesystem(&mut self, filesystem: &FileSystem) {
+        let has_checks = filesystem.is_confined() || filesystem.symlink_policy() != SymlinkPolicy::Follow;
+        self.filesystem = if has_checks {
+            Some(filesystem.clone())
+        } else {
+            None
+        };
+        self.filesystem_changed = true;
+    }
+
+    /// Sets what happens when a file loaded as several types changes.
+    pub fn set_collision_policy(&mut self, policy: CollisionPolicy) {
+        self.collision_policy = policy;
+    }
+
     pub fn add<A: Asset>(&mut self, path: PathBuf, id: String) {
+        self.add_with(path, id, TypeId::of::<A>(), load::<A>);
+    }
+
+    /// Watches a file read by the loader of an asset, other than its main
+    /// file.
+    pub fn add_dependent<A: Asset>(&mut self, path: PathBuf, id: String) {
+        self.add_with(path, id, TypeId::of::<A>(), load_dependent::<A>);
+    }
+
+    /// Watches a file read by a compound asset.
+    pub fn add_compound<A: CompoundAsset>(&mut self, path: PathBuf, id: String) {
+        self.add_with(path, id, TypeId::of::<A>(), load_compound::<A>);
+    }
+
+    fn add_with(&mut self, path: PathBuf, id: String, type_id: TypeId, load: LoadFn) {
         match self.paths.get_mut(&path) {
             None => {
                 let mut info = WatchedPath::new(id);
-                info.types.insert_with::<A>();
+                info.types.insert(type_id, load);
 
                 self.paths.insert(path.clone(), info);
             },
             Some(infos) => {
                 debug_assert_eq!(infos.id, id);
 
-                infos.types.insert_with::<A>();
+                if infos.types.get(type_id).is_none() {
+                    log::debug!("{:?} is loaded as several types of assets", path);
+                }
+                infos.types.insert(type_id, load);
             },
         }
 
-        self.added.push((path, TypeId::of::<A>()));
+        self.added.push((path, type_id));
     }
 
     pub fn clear(&mut self) {
@@ -137,12 +287,94 @@ impl WatchedPaths {
         self.added.clear();
         self.cleared = true;
     }
+
+    /// Moves all watched paths from the `old` root to the `new` one.
+    pub fn rebase(&mut self, old: &Path, new: &Path) {
+        let paths = mem::replace(&mut self.paths, HashMap::with_hasher(RandomState::new()));
+
+        for (path, infos) in paths {
+            if let Ok(rel) = path.strip_prefix(old) {
+                self.paths.insert(new.join(rel), infos);
+            }
+        }
+
+        self.rewatch();
+    }
+
+    /// Stops watching the paths of assets whose id is `prefix` or starts with
+    /// `prefix.`.
+    pub fn remove_under(&mut self, prefix: &str) {
+        let len = self.paths.len();
+        self.paths.retain(|_, infos| {
+            let id = &*infos.id;
+            !(id == prefix || (id.starts_with(prefix) && id.as_bytes()[prefix.len()] == b'.'))
+        });
+
+        if self.paths.len() != len {
+            self.rewatch();
+        }
+    }
+
+    /// Stops watching the paths of the given assets.
+    pub fn remove_keys(&mut self, keys: &[Key]) {
+        if keys.is_empty() {
+            return;
+        }
+
+        let keys: HashSet<&AccessKey, RandomState> = keys.iter().map(|key| key.borrow()).collect();
+        let mut changed = false;
+
+        self.paths.retain(|_, infos| {
+            let id = &*infos.id;
+            let len = infos.types.0.len();
+            infos.types.retain(|type_id| !keys.contains(&AccessKey::new_with(id, type_id)));
+            changed |= infos.types.0.len() != len;
+            !infos.types.0.is_empty()
+        });
+
+        if changed {
+            self.rewatch();
+        }
+    }
+
+    /// Returns `true` if paths were cleared since the reloader last got them.
+    pub fn is_cleared(&self) -> bool {
+        self.cleared
+    }
+
+    /// Returns the directories of all watched paths.
+    pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+        self.paths.keys().filter_map(|path| path.parent())
+    }
+
+    /// Returns the directories of the paths added since the reloader last
+    /// got them.
+    pub fn added_dirs(&self) -> impl Iterator<Item = &Path> {
+        self.added.iter().filter_map(|(path, _)| path.parent())
+    }
+
+    /// Marks all paths as newly added, so a new reloader watches them all.
+    pub fn rewatch(&mut self) {
+        self.added.clear();
+        self.cleared = true;
+
+        for (path, infos) in &self.paths {
+            for (type_id, _) in &infos.types.0 {
+                self.added.push((path.clone(), *type_id));
+            }
+        }
+    }
 }
 
 
 pub struct FileCache {
     paths: HashMap<PathBuf, WatchedPath, RandomState>,
-    changed: HashMap<Key, Box<dyn AnyAsset>, RandomState>,
+    changed: HashMap<Key, Reloaded, RandomState>,
+    removed: Vec<PathBuf>,
+    ignored: Vec<Box<str>>,
+    filesystem: Option<FileSystem>,
+    collision_policy: CollisionPolicy,
+    errors: usize,
 }
 
 impl FileCache {
@@ -150,39 +382,165 @@ impl FileCache {
         Self {
             paths: HashMap::with_hasher(RandomState::new()),
             changed: HashMap::with_hasher(RandomState::new()),
+            removed: Vec::new(),
+            ignored: Vec::new(),
+            filesystem: None,
+            collision_policy: CollisionPolicy::default(),
+            errors: 0,
+        }
+    }
+
+    /// Returns the informations about a watched path, if changes to it
+    /// should be handled.
+    fn get_infos<'a>(
+        paths: &'a HashMap<PathBuf, WatchedPath, RandomState>,
+        ignored: &[Box<str>],
+        path: &Path,
+    ) -> Option<&'a WatchedPath> {
+        match paths.get(path) {
+            Some(infos) if ignored.iter().any(|prefix| infos.id.starts_with(&**prefix)) => {
+                log::debug!("Ignoring change of {:?}", infos.id);
+                None
+            },
+            Some(infos) => Some(infos),
+            None => {
+                log::trace!("Ignoring change of unused file {:?}", path);
+                None
+            },
         }
     }
 
     pub fn load(&mut self, path: PathBuf) {
-        let path_infos = match self.paths.get_mut(&path) {
+        let path_infos = match Self::get_infos(&self.paths, &self.ignored, &path) {
             Some(i) => i,
             None => return,
         };
 
-        let content = fs::read(&path);
+        if let Some(filesystem) = &self.filesystem {
+            if let Err(err) = filesystem.check_path(&path) {
+                log::warn!("Not reloading {:?}: {}", path_infos.id, err);
+                return;
+            }
+        }
+
+        // The file was created again since it was removed
+        self.removed.retain(|p| *p != path);
 
-        for (type_id, load) in &mut path_infos.types.0 {
-            if let Some(asset) = load(borrowed(&content), &path_infos.id, &path) {
-                let key = Key::new_with(path_infos.id.clone().into(), *type_id);
-                self.changed.insert(key, asset);
+        if path_infos.types.0.len() > 1 && self.collision_policy == CollisionPolicy::SkipReload {
+            log::warn!("Not reloading {:?}: it is loaded as several types of assets", path_infos.id);
+            return;
+        }
 
+        let changed = &mut self.changed;
+        let errors = &mut self.errors;
+        let filesystem = self.filesystem.as_ref();
+
+        read_file(&path, |content| {
+            for (type_id, load) in &path_infos.types.0 {
+                match load(borrowed(&content), &path_infos.id, &path, filesystem) {
+                    Some(reloaded) => {
+                        let key = Key::new_with(path_infos.id.clone().into(), *type_id);
+                        changed.insert(key, reloaded);
+                    },
+                    None => *errors += 1,
+                }
             }
+        });
+    }
+
+    pub fn remove(&mut self, path: PathBuf) {
+        if Self::get_infos(&self.paths, &self.ignored, &path).is_none() {
+            return;
+        }
+
+        if !self.removed.contains(&path) {
+            self.removed.push(path);
         }
     }
 
-    pub fn update(&mut self, cache: &AssetCache) {
-        let assets = cache.assets.read();
+    /// Applies the removal policy to removed files.
+    ///
+    /// Returns the keys of the entries to remove from the cache.
+    fn handle_removed(&mut self, policy: RemovalPolicy) -> Vec<Key> {
+        let mut to_remove = Vec::new();
 
-        for (key, value) in self.changed.drain() {
-            let mut changed = false;
+        for path in self.removed.drain(..) {
+            let path_infos = match self.paths.get(&path) {
+                Some(i) => i,
+                None => continue,
+            };
 
-            if let Some(entry) = assets.get(&key) {
-                unsafe { value.reload(entry) };
-                changed = true;
+            match policy {
+                RemovalPolicy::KeepLastValue => {
+                    log::info!("{:?} was removed, keeping last value of {:?}", path, path_infos.id);
+                },
+                RemovalPolicy::RemoveEntry => {
+                    for (type_id, _) in &path_infos.types.0 {
+                        to_remove.push(Key::new_with(path_infos.id.clone().into(), *type_id));
+                    }
+                },
+                RemovalPolicy::UseFallback => {
+                    for (type_id, load) in &path_infos.types.0 {
+                        let content = Err(io::ErrorKind::NotFound.into());
+                        if let Some(reloaded) = load(content, &path_infos.id, &path, self.filesystem.as_ref()) {
+                            let key = Key::new_with(path_infos.id.clone().into(), *type_id);
+                            self.changed.insert(key, reloaded);
+                        }
+                    }
+                },
             }
+        }
 
-            if changed {
-                log::info!("Reloading {:?}", key.id());
+        to_remove
+    }
+
+    pub fn update(&mut self, cache: &AssetCache) {
+        let to_remove = self.handle_removed(cache.removal_policy);
+        self.update_changed(cache);
+
+        if !to_remove.is_empty() {
+            {
+                let mut assets = cache.assets.write();
+                let mut detached = cache.detached.lock();
+
+                for key in &to_remove {
+                    if let Some(entry) = assets.remove(key) {
+                        log::info!("Removing {:?}", key.id());
+
+                        // AssetRefs to this entry may still exist, so we
+                        // cannot drop it yet.
+                        detached.push(entry);
+                    }
+                }
+            }
+
+            cache.forget_entries(&to_remove);
+        }
+    }
+
+    fn update_changed(&mut self, cache: &AssetCache) {
+        let assets = cache.assets.read();
+        let mut stats = cache.stats.lock();
+        stats.reload_errors(mem::replace(&mut self.errors, 0));
+
+        for (key, (value, hash)) in self.changed.drain() {
+            match assets.get(&key) {
+                Some(entry) => {
+                    unsafe {
+                        value.reload(entry, hash);
+                        cache.subscribers.lock().notify(key.borrow(), entry, &assets);
+                    }
+
+                    log::info!("Reloading {:?}", key.id());
+
+                    #[cfg(feature = "tracing")]
+                    tracing::info!(id = key.id(), "reloaded asset");
+
+                    stats.reloaded(key);
+                },
+                None => {
+                    log::debug!("Not reloading {:?}: it was removed from the cache", key.id());
+                },
             }
         }
     }
@@ -193,6 +551,18 @@ impl FileCache {
             self.paths.clear();
         }
 
+        if watched.ignored_changed {
+            watched.ignored_changed = false;
+            self.ignored.clone_from(&watched.ignored);
+        }
+
+        if watched.filesystem_changed {
+            watched.filesystem_changed = false;
+            self.filesystem.clone_from(&watched.filesystem);
+        }
+
+        self.collision_policy = watched.collision_policy;
+
         for (path, id) in watched.added.drain(..) {
             let infos = match watched.paths.get(&path) {
                 Some(infos) => infos,
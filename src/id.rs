@@ -0,0 +1,170 @@
+//! Validation of asset ids
+
+use std::{error::Error, fmt, ops::Deref};
+
+
+/// A valid asset id.
+///
+/// An id is a list of non-empty segments separated by dots, such as
+/// `"ui.palette"`. A segment cannot contain path separators (`/` and `\`), a
+/// colon or a NUL character, so an id always designates a file under the root
+/// of a source.
+///
+/// Ids given to [`AssetCache`] are not required to be `AssetId`s, but ids that
+/// come from untrusted data (e.g. mods) should be validated with
+/// [`AssetId::parse`] first. The [`FileSystem`] source rejects invalid ids.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::AssetId;
+///
+/// assert!(AssetId::parse("ui.palette").is_ok());
+/// assert!(AssetId::parse("../../secrets").is_err());
+///
+/// let id = AssetId::normalize("ui/palette")?;
+/// assert_eq!(id, "ui.palette");
+/// # Ok::<(), assets_manager::InvalidId>(())
+/// ```
+///
+/// [`AssetCache`]: struct.AssetCache.html
+/// [`AssetId::parse`]: #method.parse
+/// [`FileSystem`]: source/struct.FileSystem.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AssetId<'a>(&'a str);
+
+impl<'a> AssetId<'a> {
+    /// Checks that `id` is a valid id.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `id` is empty, has an empty segment (including
+    /// leading or trailing dots, and `..`), or contains a forbidden character.
+    pub fn parse(id: &'a str) -> Result<Self, InvalidId> {
+        let invalid = |reason| Err(InvalidId { id: id.to_owned(), reason });
+
+        if id.is_empty() {
+            return invalid(Reason::Empty);
+        }
+
+        for segment in id.split('.') {
+            if segment.is_empty() {
+                return invalid(Reason::EmptySegment);
+            }
+            if let Some(c) = segment.chars().find(|&c| is_forbidden(c)) {
+                return invalid(Reason::Forbidden(c));
+            }
+        }
+
+        Ok(AssetId(id))
+    }
+
+    /// Returns the id as a string.
+    #[inline]
+    pub fn as_str(self) -> &'a str {
+        self.0
+    }
+}
+
+impl AssetId<'_> {
+    /// Converts a path-like id such as `"ui/palette"` to a valid id, by
+    /// replacing path separators with dots.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the result is not a valid id (see
+    /// [`parse`]).
+    ///
+    /// [`parse`]: #method.parse
+    pub fn normalize(id: &str) -> Result<String, InvalidId> {
+        let id = id.replace(|c| c == '/' || c == '\\', ".");
+        AssetId::parse(&id)?;
+        Ok(id)
+    }
+}
+
+#[inline]
+fn is_forbidden(c: char) -> bool {
+    matches!(c, '/' | '\\' | ':' | '\0')
+}
+
+/// Returns `true` if `id` is a valid id, or the id of the root directory.
+pub(crate) fn is_valid_dir(id: &str) -> bool {
+    id.is_empty() || AssetId::parse(id).is_ok()
+}
+
+impl Deref for AssetId<'_> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl AsRef<str> for AssetId<'_> {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl PartialEq<str> for AssetId<'_> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for AssetId<'_> {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for AssetId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reason {
+    Empty,
+    EmptySegment,
+    Forbidden(char),
+}
+
+/// An error returned when an id is not valid.
+///
+/// See [`AssetId::parse`] for more details.
+///
+/// [`AssetId::parse`]: struct.AssetId.html#method.parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidId {
+    id: String,
+    reason: Reason,
+}
+
+impl InvalidId {
+    /// Returns the invalid id.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl fmt::Display for InvalidId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid asset id {:?}: ", self.id)?;
+        match self.reason {
+            Reason::Empty => f.write_str("the id is empty"),
+            Reason::EmptySegment => f.write_str("the id has an empty segment"),
+            Reason::Forbidden(c) => write!(f, "the id contains {:?}", c),
+        }
+    }
+}
+
+impl Error for InvalidId {}
@@ -0,0 +1,160 @@
+//! References to assets that are resolved on first access
+
+use crate::{
+    Asset,
+    AssetCache,
+    AssetErr,
+    AssetRef,
+    lock::EntryData,
+};
+
+use std::{
+    fmt,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+
+/// A reference to an asset by id, resolved through a cache on first access.
+///
+/// This is useful in data structures that reference other assets by id, for
+/// example when they are themselves loaded from files: the referenced assets
+/// are only loaded when they are first needed, and the handle is then kept so
+/// later accesses do not need a lookup in the cache.
+///
+/// A `LazyAsset` can be shared between threads. It must always be resolved
+/// with the same cache.
+///
+/// With the `serde` feature, it can be deserialized from a string.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, LazyAsset, loader};
+///
+/// struct X(i32);
+///
+/// impl From<i32> for X {
+///     fn from(x: i32) -> X { X(x) }
+/// }
+///
+/// impl Asset for X {
+///     const EXT: &'static str = "x";
+///     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+/// }
+///
+/// let cache = AssetCache::new("assets")?;
+/// let lazy = LazyAsset::<X>::new("test.cache");
+/// assert!(!lazy.is_resolved());
+///
+/// let x = lazy.get(&cache)?;
+/// assert_eq!(x.read().0, 42);
+/// assert!(lazy.is_resolved());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct LazyAsset<'a, A> {
+    id: Box<str>,
+    resolved: AtomicPtr<EntryData<A>>,
+    _marker: PhantomData<&'a AssetCache>,
+}
+
+impl<'a, A> LazyAsset<'a, A> {
+    /// Creates a new unresolved `LazyAsset` with the given id.
+    #[inline]
+    pub fn new<S: Into<Box<str>>>(id: S) -> Self {
+        Self {
+            id: id.into(),
+            resolved: AtomicPtr::new(ptr::null_mut()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the id of the referenced asset.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns `true` if the asset was already resolved.
+    #[inline]
+    pub fn is_resolved(&self) -> bool {
+        !self.resolved.load(Ordering::Acquire).is_null()
+    }
+}
+
+impl<'a, A: Asset> LazyAsset<'a, A> {
+    /// Gets a reference to the asset, loading it if needed.
+    ///
+    /// The first successful call stores the reference, so next calls do not
+    /// need to access the cache.
+    ///
+    /// # Errors
+    ///
+    /// Error cases are the same as [`AssetCache::load`]. If an error occurs,
+    /// the next call will try to load the asset again.
+    ///
+    /// [`AssetCache::load`]: struct.AssetCache.html#method.load
+    pub fn get(&self, cache: &'a AssetCache) -> Result<AssetRef<'a, A>, AssetErr<A>> {
+        let ptr = self.resolved.load(Ordering::Acquire);
+
+        if !ptr.is_null() {
+            // Safety: the pointer was obtained from an `AssetRef<'a, A>`
+            let data = unsafe { &*ptr };
+            return Ok(AssetRef { data });
+        }
+
+        let asset = cache.load::<A>(&self.id)?;
+        let ptr = asset.data as *const EntryData<A> as *mut EntryData<A>;
+        self.resolved.store(ptr, Ordering::Release);
+
+        Ok(asset)
+    }
+}
+
+impl<A> Clone for LazyAsset<'_, A> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            resolved: AtomicPtr::new(self.resolved.load(Ordering::Acquire)),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A> From<String> for LazyAsset<'_, A> {
+    #[inline]
+    fn from(id: String) -> Self {
+        Self::new(id)
+    }
+}
+
+impl<A> From<&str> for LazyAsset<'_, A> {
+    #[inline]
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl<A> fmt::Debug for LazyAsset<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyAsset")
+            .field("id", &self.id)
+            .field("resolved", &self.is_resolved())
+            .finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, A> serde::Deserialize<'de> for LazyAsset<'_, A> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<A> serde::Serialize for LazyAsset<'_, A> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.id)
+    }
+}
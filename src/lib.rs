@@ -9,15 +9,25 @@
 //! ## Cargo features
 //!
 //! - `hot-reloading`: Add hot-reloading
+//! - `rayon`: Load directories in parallel
+//! - `tracing`: Emit spans and events about loads and reloads with the
+//!   `tracing` crate
+//! - `leak-detection`: Count the guards held on each asset, to find them with
+//!   `AssetCache::report_leaks`
+//! - `rich-errors`: List the files with a close name in the error of a missing
+//!   asset, to help finding typos in ids
 //!
 //! ### Additionnal loaders
 //! - `bincode`: Bincode deserialization
 //! - `cbor`: CBOR deserialization
-//! - `json`: JSON deserialization
+//! - `csv`: CSV and TSV deserialization, and the `Table` asset
+//! - `ini`: INI deserialization
+//! - `json`: JSON deserialization, and the `JsonValue` asset
 //! - `msgpack`: MessagePack deserialization
-//! - `ron`: RON deserialization
+//! - `ron`: RON deserialization, and the `RonValue` asset
 //! - `toml`: TOML deserialization
-//! - `yaml`: YAML deserialization
+//! - `xml`: XML deserialization
+//! - `yaml`: YAML deserialization, and `Document` assets
 //!
 //! ### Internal features
 //!
@@ -94,19 +104,101 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod background;
+
+pub mod build;
+pub use background::{BackgroundLoader, BackgroundLoaderBuilder, LoadCost, PendingDir, PendingHandle};
+
+mod buffered;
+pub use buffered::Buffered;
+
+mod budget;
+pub use budget::GroupReport;
+
 mod cache;
 pub use cache::AssetCache;
 
+mod collision;
+pub use collision::{Collision, CollisionPolicy};
+
 pub mod loader;
 
+mod lazy;
+pub use lazy::LazyAsset;
+
+mod derived;
+
+mod id;
+pub use id::{AssetId, InvalidId};
+
 mod lock;
 pub use lock::{AssetRef, AssetGuard};
 
+mod merge;
+pub use merge::Merge;
+
+mod mount;
+
+pub mod source;
+
+pub mod testing;
+
+mod registry;
+pub use registry::{Registry, RegistryEntry, RegistryError, RegistryId};
+
+mod report;
+pub use report::{DebugReport, ReloadReport, Resolution, TypeReport};
+#[cfg(feature = "leak-detection")]
+pub use report::LeakReport;
+
+mod scope;
+pub use scope::AssetScope;
+
+mod section;
+pub use section::{Section, SectionError, Sliced};
+
+mod snapshot;
+pub use snapshot::Snapshot;
+
+mod subscribers;
+
+mod suggest;
+
+pub mod units;
+
+mod value;
+pub use value::{Color, Curve, Document, Palette, ParseDataError};
+#[cfg(feature = "json")]
+pub use value::JsonValue;
+#[cfg(feature = "ron")]
+pub use value::RonValue;
+#[cfg(feature = "csv")]
+pub use value::Table;
+
+mod untyped;
+pub use untyped::{LoadDynError, UntypedRef};
+
+mod warmup;
+
+#[cfg(any(feature = "json", feature = "ron"))]
+mod compose;
+#[cfg(any(feature = "json", feature = "ron"))]
+pub use compose::ComposeError;
+
+mod compound;
+pub use compound::CompoundAsset;
+pub use loader::LoadContext;
+
+mod context;
+pub use context::{ContextAsset, Interpolated, InterpolationError, Variables};
+
 mod dirs;
-pub use dirs::{DirReader, ReadAllDir, ReadDir};
+pub use dirs::{DirReader, Ids, LazyDir, LazyDirIter, ReadAllDir, ReadDir, SkippedFile};
 
 #[cfg(feature = "hot-reloading")]
 mod hot_reloading;
+#[cfg(feature = "hot-reloading")]
+pub use hot_reloading::{HotReloadHub, HotReloadStatus, RemovalPolicy, WatchMode};
 
 #[cfg(test)]
 mod tests;
@@ -158,7 +250,12 @@ pub trait Asset: Sized + Send + Sync + 'static {
     ///
     /// It must not contain the `.` caracter.
     ///
-    /// Use `""` for no extension.
+    /// Use `""` for files without extension, and [`ANY_EXTENSION`] to match
+    /// files with any extension. In this case, if several files have the same
+    /// name, the one without extension is used, and otherwise the one whose
+    /// extension comes first in alphabetical order.
+    ///
+    /// [`ANY_EXTENSION`]: constant.ANY_EXTENSION.html
     const EXT: &'static str;
 
     /// Specifies a way to to convert raw bytes into the asset.
@@ -167,6 +264,40 @@ pub trait Asset: Sized + Send + Sync + 'static {
     ///
     /// [`loader`]: loader/index.html
     type Loader: loader::Loader<Self>;
+
+    /// How expensive the asset is to load.
+    ///
+    /// This is used as a hint by [`BackgroundLoader`] to schedule loads. The
+    /// default is `LoadCost::Medium`.
+    ///
+    /// [`BackgroundLoader`]: struct.BackgroundLoader.html
+    const COST: LoadCost = LoadCost::Medium;
+
+    /// An estimation of the memory owned by the asset, such as the content
+    /// of a `Vec` or of a `String`.
+    ///
+    /// It is added to the size of the value itself in memory estimations,
+    /// such as the ones of [`AssetCache::debug_report`] and of memory
+    /// budgets. The default is 0, so only the size of the value is counted.
+    ///
+    /// [`AssetCache::debug_report`]: struct.AssetCache.html#method.debug_report
+    #[inline]
+    fn heap_size(&self) -> usize {
+        0
+    }
 }
 
+/// The value of [`Asset::EXT`] for assets that can be loaded from files with
+/// any extension.
+///
+/// To find the file of such an asset, the cache lists the files of its
+/// directory. This listing is kept until changes are applied by
+/// [`AssetCache::hot_reload`] or [`AssetCache::reload_changed`], so files
+/// added in the meantime are not seen.
+///
+/// [`Asset::EXT`]: trait.Asset.html#associatedconstant.EXT
+/// [`AssetCache::hot_reload`]: struct.AssetCache.html#method.hot_reload
+/// [`AssetCache::reload_changed`]: struct.AssetCache.html#method.reload_changed
+pub const ANY_EXTENSION: &str = "*";
+
 type AssetErr<A> = <<A as Asset>::Loader as loader::Loader<A>>::Err;
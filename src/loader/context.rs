@@ -0,0 +1,122 @@
+use std::{borrow::Cow, cell::RefCell, fmt, io};
+
+
+/// Reads the file with the id of the asset and the given extension.
+type ReadFn<'a> = &'a dyn Fn(&str, &mut Vec<u8>) -> io::Result<()>;
+
+/// Informations given to a loader about the asset it loads.
+///
+/// It is given to [`Loader::load_with_context`] and [`CompoundAsset::load`].
+/// It gives the id of the asset, the extension of its file and its content,
+/// and allows to read files with the same id and other extensions. These
+/// files are recorded as dependencies of the asset: hot-reloading watches
+/// them and loads the asset again when one of them changes.
+///
+/// Loaders can also report problems that do not prevent them from loading
+/// the asset with [`warn`].
+///
+/// [`Loader::load_with_context`]: trait.Loader.html#method.load_with_context
+/// [`CompoundAsset::load`]: ../trait.CompoundAsset.html#tymethod.load
+/// [`warn`]: #method.warn
+pub struct LoadContext<'a> {
+    id: &'a str,
+    ext: &'a str,
+    content: Option<Result<&'a [u8], &'a io::Error>>,
+    read: ReadFn<'a>,
+    dependencies: RefCell<Vec<String>>,
+    warnings: RefCell<Vec<String>>,
+}
+
+impl<'a> LoadContext<'a> {
+    pub(crate) fn new(id: &'a str, ext: &'a str, read: ReadFn<'a>) -> Self {
+        Self {
+            id,
+            ext,
+            content: None,
+            read,
+            dependencies: RefCell::new(Vec::new()),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Gives the content of the main file, if it was already read.
+    pub(crate) fn with_content(mut self, content: Result<&'a [u8], &'a io::Error>) -> Self {
+        self.content = Some(content);
+        self
+    }
+
+    /// The id of the asset being loaded.
+    #[inline]
+    pub fn id(&self) -> &str {
+        self.id
+    }
+
+    /// The extension of the file of the asset.
+    ///
+    /// This is the extension of the file that was actually found, which is
+    /// useful for assets that accept any extension.
+    #[inline]
+    pub fn ext(&self) -> &str {
+        self.ext
+    }
+
+    /// Returns the content of the file of the asset.
+    pub fn content(&self) -> io::Result<Cow<'_, [u8]>> {
+        match self.content {
+            Some(Ok(bytes)) => Ok(Cow::Borrowed(bytes)),
+            Some(Err(err)) => Err(copy_error(err)),
+            None => self.sibling(self.ext),
+        }
+    }
+
+    /// Reads the file with the same id as the asset and the given extension.
+    ///
+    /// The file is recorded as a dependency even if it cannot be read, so the
+    /// asset is loaded again when it is created.
+    pub fn sibling(&self, ext: &str) -> io::Result<Cow<'_, [u8]>> {
+        let mut dependencies = self.dependencies.borrow_mut();
+        if !dependencies.iter().any(|e| e == ext) {
+            dependencies.push(ext.to_owned());
+        }
+        drop(dependencies);
+
+        let mut buf = Vec::new();
+        (self.read)(ext, &mut buf)?;
+        Ok(Cow::Owned(buf))
+    }
+
+    /// Reports a problem that does not prevent the asset from being loaded,
+    /// such as a deprecated field.
+    ///
+    /// Warnings are reported with the id of the asset through `log` and
+    /// `tracing` when they are enabled, and counted in the cache's
+    /// [`DebugReport`].
+    ///
+    /// [`DebugReport`]: ../struct.DebugReport.html
+    pub fn warn(&self, message: impl fmt::Display) {
+        self.warnings.borrow_mut().push(message.to_string());
+    }
+
+    /// Returns the extensions of the files read with `sibling` and the
+    /// warnings reported.
+    pub(crate) fn finish(self) -> (Vec<String>, Vec<String>) {
+        (self.dependencies.into_inner(), self.warnings.into_inner())
+    }
+}
+
+impl fmt::Debug for LoadContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadContext")
+            .field("id", &self.id)
+            .field("ext", &self.ext)
+            .finish()
+    }
+}
+
+/// Creates an error similar to `err`, as `io::Error` is not `Clone`.
+fn copy_error(err: &io::Error) -> io::Error {
+    match err.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(err.kind(), err.to_string()),
+    }
+}
@@ -107,3 +107,40 @@ where
         }
     }
 }
+
+
+/// An error which occurs when loading with [`CatchPanic`].
+///
+/// [`CatchPanic`]: struct.CatchPanic.html
+#[derive(Debug)]
+pub enum CatchPanicError<E> {
+    /// The inner loader returned an error.
+    Load(E),
+
+    /// The inner loader panicked, with the given message.
+    Panic(String),
+}
+
+impl<E> fmt::Display for CatchPanicError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(err) => err.fmt(f),
+            Self::Panic(msg) => write!(f, "loader panicked: {}", msg),
+        }
+    }
+}
+
+impl<E> Error for CatchPanicError<E>
+where
+    E: Error + 'static
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Load(err) => Some(err),
+            Self::Panic(_) => None,
+        }
+    }
+}
@@ -14,17 +14,22 @@
 
 #[allow(unused_imports)]
 use std::{
+    any::Any,
     borrow::Cow,
     convert::Infallible,
     error::Error,
     fmt::Display,
     io,
     marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
     str::{self, FromStr},
 };
 
+mod context;
+pub use context::LoadContext;
+
 mod errors;
-pub use errors::{StringLoaderError, ParseLoaderError};
+pub use errors::{CatchPanicError, StringLoaderError, ParseLoaderError};
 
 #[cfg(test)]
 mod tests;
@@ -101,7 +106,38 @@ mod tests;
 /// # let fruit = Ok(b" banana \n"[..].into());
 /// # assert_eq!(FruitLoader::load(fruit).unwrap(), Fruit::Banana);
 /// ```
-
+///
+/// ## Using the `LoadContext`
+///
+/// Loaders that need more than the content of the file, such as its
+/// extension or files next to it, can override `load_with_context`. The
+/// cache always calls this function, which defaults to calling `load` with
+/// the content of the file.
+///
+/// ```
+/// use assets_manager::loader::{LoadContext, Loader};
+/// use std::{borrow::Cow, error::Error, io, str};
+///
+/// struct Shader {
+///     vertex: String,
+///     fragment: String,
+/// }
+///
+/// struct ShaderLoader;
+/// impl Loader<Shader> for ShaderLoader {
+///     type Err = Box<dyn Error>;
+///
+///     fn load(_: io::Result<Cow<[u8]>>) -> Result<Shader, Self::Err> {
+///         Err("a fragment shader is needed".into())
+///     }
+///
+///     fn load_with_context(context: &LoadContext) -> Result<Shader, Self::Err> {
+///         let vertex = String::from_utf8(context.content()?.into_owned())?;
+///         let fragment = String::from_utf8(context.sibling("frag")?.into_owned())?;
+///         Ok(Shader { vertex, fragment })
+///     }
+/// }
+/// ```
 pub trait Loader<T> {
     /// The associated error which can be returned from loading.
     ///
@@ -110,6 +146,21 @@ pub trait Loader<T> {
 
     /// Loads an asset from its raw bytes representation.
     fn load(content: io::Result<Cow<[u8]>>) -> Result<T, Self::Err>;
+
+    /// Loads an asset with informations about it given by a [`LoadContext`].
+    ///
+    /// Files read with [`LoadContext::sibling`] are watched by hot-reloading
+    /// along with the file of the asset, provided that the asset type has an
+    /// extension.
+    ///
+    /// The default implementation calls `load` with the content of the file.
+    ///
+    /// [`LoadContext`]: struct.LoadContext.html
+    /// [`LoadContext::sibling`]: struct.LoadContext.html#method.sibling
+    #[inline]
+    fn load_with_context(context: &LoadContext) -> Result<T, Self::Err> {
+        Self::load(context.content())
+    }
 }
 
 /// Returns the default value in case of failure.
@@ -148,6 +199,73 @@ where
     fn load(content: io::Result<Cow<[u8]>>) -> Result<T, Self::Err> {
         L::load(content).or_else(|_| Ok(T::default()))
     }
+
+    fn load_with_context(context: &LoadContext) -> Result<T, Self::Err> {
+        L::load_with_context(context).or_else(|_| Ok(T::default()))
+    }
+}
+
+/// Converts panics of another loader into errors.
+///
+/// Loaders that deserialize untrusted or hand-edited files may panic on
+/// unexpected input. With this loader, such a panic is caught and returned
+/// as an error, like any other failed load, instead of unwinding through the
+/// code that loads the asset. The panic message is still printed by the panic
+/// hook.
+///
+/// Panics of loaders are always caught when reloading assets, so this loader
+/// is only needed to handle them on the first load.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, loader::{CatchPanic, LoadFrom, ParseLoader}};
+///
+/// struct Level(u32);
+///
+/// impl From<u32> for Level {
+///     fn from(n: u32) -> Level {
+///         Level(n)
+///     }
+/// }
+///
+/// impl Asset for Level {
+///     const EXT: &'static str = "lvl";
+///     type Loader = CatchPanic<LoadFrom<u32, ParseLoader>>;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CatchPanic<L>(PhantomData<L>);
+impl<T, L> Loader<T> for CatchPanic<L>
+where
+    L: Loader<T>,
+{
+    type Err = CatchPanicError<L::Err>;
+
+    fn load(content: io::Result<Cow<[u8]>>) -> Result<T, Self::Err> {
+        match panic::catch_unwind(AssertUnwindSafe(|| L::load(content))) {
+            Ok(result) => result.map_err(CatchPanicError::Load),
+            Err(payload) => Err(CatchPanicError::Panic(panic_message(&*payload))),
+        }
+    }
+
+    fn load_with_context(context: &LoadContext) -> Result<T, Self::Err> {
+        match panic::catch_unwind(AssertUnwindSafe(|| L::load_with_context(context))) {
+            Ok(result) => result.map_err(CatchPanicError::Load),
+            Err(payload) => Err(CatchPanicError::Panic(panic_message(&*payload))),
+        }
+    }
+}
+
+/// Returns the message of a panic, given its payload.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        (*msg).to_owned()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
 }
 
 /// Load assets from another type.
@@ -186,6 +304,10 @@ where
     fn load(content: io::Result<Cow<[u8]>>) -> Result<T, Self::Err> {
         Ok(L::load(content)?.into())
     }
+
+    fn load_with_context(context: &LoadContext) -> Result<T, Self::Err> {
+        Ok(L::load_with_context(context)?.into())
+    }
 }
 
 /// Loads assets as a `Vec<u8>`.
@@ -248,6 +370,76 @@ where
     }
 }
 
+/// Loads text documents with an optional front-matter header.
+///
+/// The header is delimited by lines made of `---` (for YAML) or `+++` (for
+/// TOML) at the start of the file, and is loaded with the inner loader `L`.
+/// The rest of the file is the body of the [`Document`]. If there is no
+/// header, the whole file is the body.
+///
+/// ```text
+/// ---
+/// speaker: Alice
+/// mood: happy
+/// ---
+/// Hello, traveler!
+/// ```
+///
+/// See trait [`Loader`] for more informations.
+///
+/// [`Document`]: ../struct.Document.html
+/// [`Loader`]: trait.Loader.html
+#[derive(Debug)]
+pub struct FrontMatterLoader<L>(PhantomData<L>);
+impl<M, L> Loader<crate::Document<M>> for FrontMatterLoader<L>
+where
+    L: Loader<M>,
+{
+    type Err = ParseLoaderError<L::Err>;
+
+    fn load(content: io::Result<Cow<[u8]>>) -> Result<crate::Document<M>, Self::Err> {
+        let content = content?;
+        let text = str::from_utf8(&content)?;
+
+        let (meta, body) = match split_front_matter(text) {
+            Some((header, body)) => {
+                let meta = L::load(Ok(header.as_bytes().into())).map_err(ParseLoaderError::Parse)?;
+                (Some(meta), body)
+            },
+            None => (None, text),
+        };
+
+        Ok(crate::Document { meta, body: body.to_owned() })
+    }
+}
+
+/// Splits a text between its front-matter header and its body.
+fn split_front_matter(text: &str) -> Option<(&str, &str)> {
+    let delimiter = if text.starts_with("---") {
+        "---"
+    } else if text.starts_with("+++") {
+        "+++"
+    } else {
+        return None;
+    };
+
+    let first_end = text.find('\n')? + 1;
+    if text[..first_end].trim_end() != delimiter {
+        return None;
+    }
+
+    let mut start = first_end;
+    while start < text.len() {
+        let end = text[start..].find('\n').map_or(text.len(), |i| start + i + 1);
+        if text[start..end].trim_end() == delimiter {
+            return Some((&text[first_end..start], &text[end..]));
+        }
+        start = end;
+    }
+
+    None
+}
+
 macro_rules! serde_loader {
     ($feature:literal, $doc:literal, $name:ident, $fun:path, $error:ty) => {
         #[doc = $doc]
@@ -267,6 +459,8 @@ macro_rules! serde_loader {
         {
             type Err = $error;
 
+            // The `?` converts the error for some formats but not for others
+            #[allow(clippy::needless_question_mark)]
             #[inline]
             fn load(content: io::Result<Cow<[u8]>>) -> Result<T, Self::Err> {
                 Ok($fun(&*content?)?)
@@ -278,8 +472,93 @@ macro_rules! serde_loader {
 
 serde_loader!("bincode", "Loads assets from Bincode encoded files.", BincodeLoader, serde_bincode::deserialize, serde_bincode::Error);
 serde_loader!("cbor", "Loads assets from CBOR encoded files.", CborLoader, serde_cbor::from_slice, serde_cbor::Error);
+serde_loader!("ini", "Loads assets from INI files.\n\nNumbers are parsed from strings, and sections are deserialized as nested structs or maps. A file without sections can be loaded as a `HashMap<String, String>`.", IniLoader, serde_ini::from_read, Box<dyn Error>);
 serde_loader!("json", "Loads assets from JSON files.", JsonLoader, serde_json::from_slice, Box<dyn Error>);
 serde_loader!("msgpack", "Loads assets from MessagePack files.", MessagePackLoader, serde_msgpack::decode::from_read, Box<dyn Error>);
 serde_loader!("ron", "Loads assets from RON files.", RonLoader, serde_ron::de::from_bytes, serde_ron::de::Error);
 serde_loader!("toml", "Loads assets from TOML files.", TomlLoader, serde_toml::de::from_slice, Box<dyn Error>);
+serde_loader!("xml", "Loads assets from XML files.\n\nAttributes and child elements of the root element are deserialized as fields.", XmlLoader, serde_xml::de::from_reader, Box<dyn Error>);
 serde_loader!("yaml", "Loads assets from YAML files.", YamlLoader, serde_yaml::from_slice, Box<dyn Error>);
+
+macro_rules! csv_loader {
+    ($doc:literal, $name:ident, $delimiter:literal) => {
+        #[doc = $doc]
+        ///
+        /// The first line of the file is a header. Each following line is
+        /// deserialized as a `T`, by matching struct fields with column
+        /// names, so this loader produces a `Vec<T>`. As a `Vec` cannot
+        /// implement `Asset`, use [`LoadFrom`] to load a newtype:
+        ///
+        /// ```no_run
+        /// # cfg_if::cfg_if! { if #[cfg(feature = "csv")] {
+        /// use serde::Deserialize;
+        /// use assets_manager::{Asset, loader};
+        ///
+        /// #[derive(Deserialize)]
+        /// struct Item {
+        ///     name: String,
+        ///     price: u32,
+        /// }
+        ///
+        /// struct Shop(Vec<Item>);
+        ///
+        /// impl From<Vec<Item>> for Shop {
+        ///     fn from(items: Vec<Item>) -> Shop {
+        ///         Shop(items)
+        ///     }
+        /// }
+        ///
+        /// impl Asset for Shop {
+        ///     const EXT: &'static str = "csv";
+        ///     type Loader = loader::LoadFrom<Vec<Item>, loader::CsvLoader>;
+        /// }
+        /// # }}
+        /// ```
+        ///
+        /// It can also load a [`Table`], to get cells as strings without
+        /// defining a struct.
+        ///
+        /// See trait [`Loader`] for more informations.
+        ///
+        /// [`LoadFrom`]: struct.LoadFrom.html
+        /// [`Table`]: ../struct.Table.html
+        /// [`Loader`]: trait.Loader.html
+        #[cfg(feature = "csv")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "csv")))]
+        #[derive(Debug)]
+        pub struct $name;
+
+        #[cfg(feature = "csv")]
+        impl<T> Loader<Vec<T>> for $name
+        where
+            T: for<'de> serde::Deserialize<'de>,
+        {
+            type Err = serde_csv::Error;
+
+            fn load(content: io::Result<Cow<[u8]>>) -> Result<Vec<T>, Self::Err> {
+                let content = content?;
+                csv_reader(&content, $delimiter).deserialize().collect()
+            }
+        }
+
+        #[cfg(feature = "csv")]
+        impl Loader<crate::Table> for $name {
+            type Err = serde_csv::Error;
+
+            fn load(content: io::Result<Cow<[u8]>>) -> Result<crate::Table, Self::Err> {
+                let content = content?;
+                crate::Table::from_reader(csv_reader(&content, $delimiter))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+fn csv_reader(content: &[u8], delimiter: u8) -> serde_csv::Reader<&[u8]> {
+    serde_csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(content)
+}
+
+csv_loader!("Loads assets from CSV files.", CsvLoader, b',');
+csv_loader!("Loads assets from TSV (tab-separated values) files.", TsvLoader, b'\t');
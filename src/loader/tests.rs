@@ -1,7 +1,7 @@
 use std::{borrow::Cow, io::Result};
 use super::*;
 
-fn raw(s: &str) -> Result<Cow<[u8]>> {
+fn raw(s: &str) -> Result<Cow<'_, [u8]>> {
     Ok(s.as_bytes().into())
 }
 
@@ -46,6 +46,25 @@ fn from_other() {
     assert_eq!(loaded, X(n));
 }
 
+#[test]
+fn catch_panic() {
+    struct Panicking;
+
+    impl Loader<i32> for Panicking {
+        type Err = Infallible;
+
+        fn load(content: Result<Cow<[u8]>>) -> std::result::Result<i32, Infallible> {
+            let n = ParseLoader::load(content).expect("not a number");
+            Ok(n)
+        }
+    }
+
+    assert_eq!(CatchPanic::<Panicking>::load(raw("5")).unwrap(), 5);
+
+    let err = CatchPanic::<Panicking>::load(raw("five")).unwrap_err();
+    assert!(matches!(&err, CatchPanicError::Panic(msg) if msg.starts_with("not a number")), "{}", err);
+}
+
 cfg_if::cfg_if! { if #[cfg(feature = "serde")] {
     use serde::{Serialize, Deserialize};
     use rand::{
@@ -103,3 +122,105 @@ test_loader!(toml_loader, TomlLoader, serde_toml::ser::to_vec);
 
 #[cfg(feature = "yaml")]
 test_loader!(yaml_loader, YamlLoader, serde_yaml::to_vec);
+
+#[cfg(feature = "csv")]
+#[test]
+fn csv_loader() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Item {
+        name: String,
+        price: u32,
+    }
+
+    let content = raw("name,price\nsword,30\nshield,25\n");
+    let items: Vec<Item> = CsvLoader::load(content).unwrap();
+    assert_eq!(items, [
+        Item { name: "sword".into(), price: 30 },
+        Item { name: "shield".into(), price: 25 },
+    ]);
+
+    let content = raw("name\tprice\nsword\t30\n");
+    let table: crate::Table = TsvLoader::load(content).unwrap();
+    assert_eq!(table.headers(), ["name", "price"]);
+    assert_eq!(table.get(0, "price"), Some("30"));
+    assert_eq!(table.get(1, "price"), None);
+    assert_eq!(table.get(0, "weight"), None);
+
+    let content = raw("name,price\nsword\n");
+    assert!(<CsvLoader as Loader<crate::Table>>::load(content).is_err());
+}
+
+#[cfg(feature = "ini")]
+#[test]
+fn ini_loader() {
+    use std::collections::HashMap;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        window: Window,
+    }
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Window {
+        width: u32,
+        height: u32,
+    }
+
+    let content = raw("; comment\nname=demo\n[window]\nwidth=800\nheight=600\n");
+    let config: Config = IniLoader::load(content).unwrap();
+    assert_eq!(config, Config {
+        name: "demo".into(),
+        window: Window { width: 800, height: 600 },
+    });
+
+    let content = raw("a=1\nb=hello\n");
+    let map: HashMap<String, String> = IniLoader::load(content).unwrap();
+    assert_eq!(map["a"], "1");
+    assert_eq!(map["b"], "hello");
+}
+
+#[cfg(feature = "xml")]
+#[test]
+fn xml_loader() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Sprite {
+        name: String,
+        frame: Vec<u32>,
+    }
+
+    let content = raw(r#"<sprite name="hero"><frame>1</frame><frame>2</frame></sprite>"#);
+    let sprite: Sprite = XmlLoader::load(content).unwrap();
+    assert_eq!(sprite, Sprite { name: "hero".into(), frame: vec![1, 2] });
+}
+
+#[test]
+fn front_matter_loader() {
+    type Loader = FrontMatterLoader<StringLoader>;
+
+    let doc = Loader::load(raw("---\nspeaker: Alice\n---\nHello!\n")).unwrap();
+    assert_eq!(doc.meta.as_deref(), Some("speaker: Alice\n"));
+    assert_eq!(doc.body, "Hello!\n");
+
+    let doc = Loader::load(raw("+++\r\nspeaker = 'Bob'\r\n+++\r\nHi")).unwrap();
+    assert_eq!(doc.meta.as_deref(), Some("speaker = 'Bob'\r\n"));
+    assert_eq!(doc.body, "Hi");
+
+    let doc = Loader::load(raw("---\nno end\n")).unwrap();
+    assert_eq!(doc.meta, None);
+    assert_eq!(doc.body, "---\nno end\n");
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn front_matter_yaml() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Meta {
+        speaker: String,
+    }
+
+    let content = raw("---\nspeaker: Alice\n---\nHello!");
+    let doc: crate::Document<Meta> = FrontMatterLoader::<YamlLoader>::load(content).unwrap();
+    assert_eq!(doc.meta, Some(Meta { speaker: "Alice".into() }));
+    assert_eq!(doc.body, "Hello!");
+}
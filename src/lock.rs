@@ -1,12 +1,18 @@
 //! Definitions of cache entries and locks
 
+use crate::Buffered;
+
 use std::{
     any::Any,
     fmt,
     hash,
     ops::Deref,
+    sync::Arc,
 };
 
+#[cfg(feature = "leak-detection")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 
 #[cfg(feature = "parking_lot")]
 use parking_lot as sync;
@@ -31,42 +37,70 @@ fn wrap<T>(param: sync::LockResult<T>) -> T {
 
 /// `RwLock` from `parking_lot` and `std` have different APIs, so we use this
 /// simple wrapper to easily permit both.
-pub(crate) struct RwLock<T: ?Sized>(sync::RwLock<T>);
+///
+/// With leak detection, it also counts the `AssetGuard`s on it. The counter
+/// is the first field, so it can be read without knowing `T`.
+#[repr(C)]
+pub(crate) struct RwLock<T: ?Sized> {
+    #[cfg(feature = "leak-detection")]
+    guards: AtomicUsize,
+    inner: sync::RwLock<T>,
+}
 
 impl<T> RwLock<T> {
     #[inline]
     pub fn new(inner: T) -> Self {
-        Self(sync::RwLock::new(inner))
+        Self {
+            #[cfg(feature = "leak-detection")]
+            guards: AtomicUsize::new(0),
+            inner: sync::RwLock::new(inner),
+        }
     }
 
     #[inline]
     pub fn into_inner(self) -> T {
-        wrap(self.0.into_inner())
+        wrap(self.inner.into_inner())
     }
 }
 
 impl<T: ?Sized> RwLock<T> {
     #[inline]
-    pub fn read(&self) -> RwLockReadGuard<T> {
-        wrap(self.0.read())
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        wrap(self.inner.read())
+    }
+
+    #[inline]
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        wrap(self.inner.write())
+    }
+
+    /// Locks for reading, unless it is locked for writing.
+    #[cfg(feature = "parking_lot")]
+    #[inline]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.inner.try_read()
     }
 
+    /// Locks for reading, unless it is locked for writing.
+    #[cfg(not(feature = "parking_lot"))]
     #[inline]
-    pub fn write(&self) -> RwLockWriteGuard<T> {
-        wrap(self.0.write())
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        match self.inner.try_read() {
+            Ok(guard) => Some(guard),
+            Err(sync::TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(sync::TryLockError::WouldBlock) => None,
+        }
     }
 
     #[inline]
     pub fn get_mut(&mut self) -> &mut T {
-        wrap(self.0.get_mut())
+        wrap(self.inner.get_mut())
     }
 }
 
 
-#[cfg(feature = "hot-reloading")]
 pub(crate) struct Mutex<T: ?Sized>(sync::Mutex<T>);
 
-#[cfg(feature = "hot-reloading")]
 impl<T> Mutex<T> {
     #[inline]
     pub fn new(inner: T) -> Self {
@@ -74,10 +108,9 @@ impl<T> Mutex<T> {
     }
 }
 
-#[cfg(feature = "hot-reloading")]
 impl<T: ?Sized> Mutex<T> {
     #[inline]
-    pub fn lock(&self) -> sync::MutexGuard<T> {
+    pub fn lock(&self) -> sync::MutexGuard<'_, T> {
         wrap(self.0.lock())
     }
 
@@ -88,14 +121,56 @@ impl<T: ?Sized> Mutex<T> {
 }
 
 
+/// The value of a cache entry: the lock on the asset, and the hash of the
+/// content from which it was loaded.
+///
+/// The lock is the first field, so the guard counter of leak detection can
+/// be read without knowing `T`.
+#[repr(C)]
+pub(crate) struct EntryData<T> {
+    lock: RwLock<T>,
+    content_hash: Mutex<Option<u64>>,
+}
+
+impl<T> EntryData<T> {
+    #[inline]
+    fn new(asset: T, hash: Option<u64>) -> Self {
+        Self {
+            lock: RwLock::new(asset),
+            content_hash: Mutex::new(hash),
+        }
+    }
+
+    #[inline]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.lock.read()
+    }
+
+    #[inline]
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.lock.write()
+    }
+
+    #[inline]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.lock.try_read()
+    }
+
+    #[inline]
+    pub fn content_hash(&self) -> Option<u64> {
+        *self.content_hash.lock()
+    }
+}
+
+
 /// An entry in the cache
 ///
 /// # Safety
 ///
 /// - Methods that are generic over `T` can only be called with the same `T` used
-/// to create them.
+///   to create them.
 /// - When an `AssetRef<'a, T>` is returned, you have to ensure that `self`
-/// outlives it. The `CacheEntry` can be moved but cannot be dropped.
+///   outlives it. The `CacheEntry` can be moved but cannot be dropped.
 ///
 /// [`ContreteCacheEntry`]: struct.ContreteCacheEntry.html
 pub(crate) struct CacheEntry(Box<dyn Any + Send + Sync>);
@@ -106,7 +181,14 @@ impl<'a> CacheEntry {
     /// The returned structure can safely use its methods with type parameter `T`.
     #[inline]
     pub fn new<T: Send + Sync + 'static>(asset: T) -> Self {
-        CacheEntry(Box::new(RwLock::new(asset)))
+        Self::with_hash(asset, None)
+    }
+
+    /// Creates a new `CacheEntry` containing an asset of type `T` loaded from
+    /// content with the given hash.
+    #[inline]
+    pub fn with_hash<T: Send + Sync + 'static>(asset: T, hash: Option<u64>) -> Self {
+        CacheEntry(Box::new(EntryData::new(asset, hash)))
     }
 
     /// Returns a reference to the underlying lock.
@@ -116,29 +198,40 @@ impl<'a> CacheEntry {
     /// See type-level documentation.
     #[inline]
     pub unsafe fn get_ref<T: Send + Sync + 'static>(&self) -> AssetRef<'a, T> {
-        debug_assert!(self.0.is::<RwLock<T>>());
+        debug_assert!(self.0.is::<EntryData<T>>());
 
         let data = {
-            let ptr = &*self.0 as *const dyn Any as *const RwLock<T>;
+            let ptr = &*self.0 as *const dyn Any as *const EntryData<T>;
             &*ptr
         };
 
         AssetRef { data }
     }
 
-    /// Write a value and a get reference to the underlying lock
+    /// Write a value and the hash of its content, and a get reference to the
+    /// underlying lock
     ///
     /// # Safety
     ///
     /// See type-level documentation.
-    pub unsafe fn write<T: Send + Sync + 'static>(&self, asset: T) -> AssetRef<'a, T> {
+    pub unsafe fn write<T: Send + Sync + 'static>(&self, asset: T, hash: Option<u64>) -> AssetRef<'a, T> {
         let lock = self.get_ref();
         let mut cached_guard = lock.data.write();
         *cached_guard = asset;
+        *lock.data.content_hash.lock() = hash;
         drop(cached_guard);
         lock
     }
 
+    /// Returns the number of `AssetGuard`s currently held on the entry.
+    #[cfg(feature = "leak-detection")]
+    pub fn guard_count(&self) -> usize {
+        // Safety: the entry always contains an `EntryData<T>`, whose first
+        // field is a `RwLock<T>`, whose first field is the counter.
+        let counter = unsafe { &*(&*self.0 as *const dyn Any as *const AtomicUsize) };
+        counter.load(Ordering::Acquire)
+    }
+
     /// Consumes the `CacheEntry` and returns its inner value.
     ///
     /// # Safety
@@ -146,9 +239,9 @@ impl<'a> CacheEntry {
     /// See type-level documentation.
     #[inline]
     pub unsafe fn into_inner<T: Send + Sync + 'static>(self) -> T {
-        debug_assert!(self.0.is::<RwLock<T>>());
+        debug_assert!(self.0.is::<EntryData<T>>());
 
-        Box::from_raw(Box::into_raw(self.0) as *mut RwLock<T>).into_inner()
+        Box::from_raw(Box::into_raw(self.0) as *mut EntryData<T>).lock.into_inner()
     }
 }
 
@@ -173,7 +266,7 @@ impl fmt::Debug for CacheEntry {
 /// `lazy_static` crate). You can also use crates allow threads with non-static
 /// data (such as `crossbeam-utils::scope`).
 pub struct AssetRef<'a, A> {
-    data: &'a RwLock<A>,
+    pub(crate) data: &'a EntryData<A>,
 }
 
 impl<'a, A> AssetRef<'a, A> {
@@ -182,8 +275,13 @@ impl<'a, A> AssetRef<'a, A> {
     /// Returns a RAII guard which will release the lock once dropped.
     #[inline]
     pub fn read(&self) -> AssetGuard<'a, A> {
+        #[cfg(feature = "leak-detection")]
+        self.data.lock.guards.fetch_add(1, Ordering::AcqRel);
+
         AssetGuard {
             guard: self.data.read(),
+            #[cfg(feature = "leak-detection")]
+            counter: &self.data.lock.guards,
         }
     }
 
@@ -192,9 +290,28 @@ impl<'a, A> AssetRef<'a, A> {
     pub fn ptr_eq(&self, other: &Self) -> bool {
         std::ptr::eq(self.data, other.data)
     }
+
+    /// Returns a hash of the content of the file from which the asset was
+    /// loaded, updated each time the asset is reloaded.
+    ///
+    /// The value is the 64-bit FNV-1a hash of the raw bytes of the file, so
+    /// it is stable across platforms and versions of this crate, and can be
+    /// computed by other tools. It can be used for example to check that two
+    /// peers of a multiplayer game have the same data files.
+    ///
+    /// Returns `None` if the asset was not loaded from a single file, for
+    /// example if it was defined with [`AssetCache::define`], loaded with a
+    /// context, if it is a compound asset or if its loader accepted a missing
+    /// file.
+    ///
+    /// [`AssetCache::define`]: struct.AssetCache.html#method.define
+    #[inline]
+    pub fn content_hash(&self) -> Option<u64> {
+        self.data.content_hash()
+    }
 }
 
-impl<A> AssetRef<'_, A>
+impl<'a, A> AssetRef<'a, A>
 where
     A: Clone
 {
@@ -203,13 +320,32 @@ where
     pub fn cloned(self) -> A {
         self.data.read().clone()
     }
+
+    /// Returns a snapshot of the current value of the asset.
+    ///
+    /// The value is cloned into an `Arc`, so it can be kept and shared during
+    /// a long computation without holding a read lock on the asset, which
+    /// would block its reloading. Later reloads do not affect the snapshot.
+    #[inline]
+    pub fn snapshot(&self) -> Arc<A> {
+        Arc::new(self.data.read().clone())
+    }
+
+    /// Returns a double-buffered reference to the asset, which keeps the
+    /// value the asset had at the previous frame.
+    ///
+    /// See [`Buffered`] for more details.
+    ///
+    /// [`Buffered`]: struct.Buffered.html
+    #[inline]
+    pub fn buffered(self) -> Buffered<'a, A> {
+        Buffered::new(self)
+    }
 }
 
 impl<A> Clone for AssetRef<'_, A> {
     fn clone(&self) -> Self {
-        Self {
-            data: self.data,
-        }
+        *self
     }
 }
 
@@ -240,6 +376,16 @@ where
 /// [`AssetRef::read`]: struct.AssetRef.html#method.read
 pub struct AssetGuard<'a, A> {
     guard: RwLockReadGuard<'a, A>,
+    #[cfg(feature = "leak-detection")]
+    counter: &'a AtomicUsize,
+}
+
+#[cfg(feature = "leak-detection")]
+impl<A> Drop for AssetGuard<'_, A> {
+    #[inline]
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 impl<A> Deref for AssetGuard<'_, A> {
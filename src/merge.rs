@@ -0,0 +1,114 @@
+//! Deep merging of documents
+
+use std::{collections::HashMap, hash::{BuildHasher, Hash}};
+
+
+/// Values that can be merged with another value of the same type.
+///
+/// This is used by [`AssetCache::load_merged`] to combine several documents,
+/// for example an entity prototype and its variants. Later values override
+/// earlier ones.
+///
+/// Maps are merged recursively: entries of `other` are merged into the
+/// corresponding entries of `self`, and new entries are added. Other values
+/// are replaced.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::Merge;
+///
+/// struct Stats {
+///     hp: Option<u32>,
+///     speed: Option<f32>,
+/// }
+///
+/// impl Merge for Stats {
+///     fn merge(&mut self, other: Self) {
+///         self.hp = other.hp.or(self.hp);
+///         self.speed = other.speed.or(self.speed);
+///     }
+/// }
+/// ```
+///
+/// [`AssetCache::load_merged`]: struct.AssetCache.html#method.load_merged
+pub trait Merge {
+    /// Merges `other` into `self`, with values of `other` taking precedence.
+    fn merge(&mut self, other: Self);
+}
+
+impl<K, V, S> Merge for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Merge,
+    S: BuildHasher,
+{
+    fn merge(&mut self, other: Self) {
+        for (key, value) in other {
+            match self.get_mut(&key) {
+                Some(old) => old.merge(value),
+                None => {
+                    self.insert(key, value);
+                },
+            }
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+impl Merge for serde_json::Value {
+    fn merge(&mut self, other: Self) {
+        match (self, other) {
+            (Self::Object(this), Self::Object(other)) => {
+                for (key, value) in other {
+                    match this.get_mut(&key) {
+                        Some(old) => old.merge(value),
+                        None => {
+                            this.insert(key, value);
+                        },
+                    }
+                }
+            },
+            (this, other) => *this = other,
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+impl Merge for crate::JsonValue {
+    #[inline]
+    fn merge(&mut self, other: Self) {
+        self.0.merge(other.0)
+    }
+}
+
+#[cfg(feature = "ron")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ron")))]
+impl Merge for serde_ron::Value {
+    fn merge(&mut self, other: Self) {
+        match (self, other) {
+            (Self::Map(this), Self::Map(other)) => {
+                for (key, value) in other {
+                    match this.get_mut(&key) {
+                        Some(old) => old.merge(value),
+                        None => {
+                            this.insert(key, value);
+                        },
+                    }
+                }
+            },
+            (this, other) => *this = other,
+        }
+    }
+}
+
+#[cfg(feature = "ron")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ron")))]
+impl Merge for crate::RonValue {
+    #[inline]
+    fn merge(&mut self, other: Self) {
+        self.0.merge(other.0)
+    }
+}
@@ -0,0 +1,159 @@
+//! Sources mounted under id prefixes
+
+use crate::source::{DirEntry, Metadata, Source};
+
+use std::{collections::HashSet, io};
+
+use crate::RandomState;
+
+
+/// Returns the id relative to `prefix` if `id` is under it.
+fn relative<'a>(prefix: &str, id: &'a str) -> Option<&'a str> {
+    if id == prefix {
+        Some("")
+    } else if id.starts_with(prefix) && id.as_bytes()[prefix.len()] == b'.' {
+        Some(&id[prefix.len() + 1..])
+    } else {
+        None
+    }
+}
+
+/// Returns the full id of `id` in the source mounted at `prefix`.
+fn absolute(prefix: &str, id: &str) -> String {
+    if id.is_empty() {
+        prefix.to_owned()
+    } else {
+        format!("{}.{}", prefix, id)
+    }
+}
+
+/// Returns the id of the parent directory of `id`.
+fn parent(id: &str) -> &str {
+    match id.rfind('.') {
+        Some(pos) => &id[..pos],
+        None => "",
+    }
+}
+
+
+/// The source of a cache, with other sources mounted under id prefixes.
+///
+/// When several mounted prefixes match an id, the last mounted one is used.
+pub(crate) struct MountedSource {
+    base: Box<dyn Source>,
+    mounts: Vec<(Box<str>, Box<dyn Source>)>,
+}
+
+impl MountedSource {
+    pub fn new(base: Box<dyn Source>) -> Self {
+        Self {
+            base,
+            mounts: Vec::new(),
+        }
+    }
+
+    /// Replaces the source used for ids that are not mounted.
+    pub fn replace_base(&mut self, base: Box<dyn Source>) -> Box<dyn Source> {
+        std::mem::replace(&mut self.base, base)
+    }
+
+    pub fn mount(&mut self, prefix: &str, source: Box<dyn Source>) {
+        self.mounts.push((prefix.into(), source));
+    }
+
+    /// Removes the last source mounted at `prefix`.
+    pub fn unmount(&mut self, prefix: &str) -> Option<Box<dyn Source>> {
+        let pos = self.mounts.iter().rposition(|(p, _)| &**p == prefix)?;
+        Some(self.mounts.remove(pos).1)
+    }
+
+    /// Returns `true` if `id` is read from a mounted source.
+    pub fn is_mounted(&self, id: &str) -> bool {
+        self.mounts.iter().any(|(prefix, _)| relative(prefix, id).is_some())
+    }
+
+    /// Returns the prefix of the source that contains `id`, if it is mounted.
+    pub fn mount_of(&self, id: &str) -> Option<&str> {
+        self.find(id).map(|(prefix, _, _)| prefix)
+    }
+
+    /// Returns the source that contains `id`, its prefix and the id relative
+    /// to it.
+    fn find<'a>(&self, id: &'a str) -> Option<(&str, &dyn Source, &'a str)> {
+        self.mounts.iter().rev().find_map(|(prefix, source)| {
+            let rel = relative(prefix, id)?;
+            Some((&**prefix, &**source, rel))
+        })
+    }
+}
+
+impl Source for MountedSource {
+    fn read(&self, id: &str, ext: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.find(id) {
+            Some((_, source, id)) => source.read(id, ext, buf),
+            None => self.base.read(id, ext, buf),
+        }
+    }
+
+    fn read_range(&self, id: &str, ext: &str, offset: u64, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.find(id) {
+            Some((_, source, id)) => source.read_range(id, ext, offset, len, buf),
+            None => self.base.read_range(id, ext, offset, len, buf),
+        }
+    }
+
+    fn metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        match self.find(id) {
+            Some((prefix, source, id)) => {
+                let mut metadata = source.metadata(id, ext)?;
+                metadata.mount = Some(prefix.to_owned());
+                Ok(metadata)
+            },
+            None => self.base.metadata(id, ext),
+        }
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        if let Some((prefix, source, rel)) = self.find(id) {
+            return source.read_dir(rel, &mut |entry| match entry {
+                DirEntry::File(id, ext) => f(DirEntry::File(&absolute(prefix, id), ext)),
+                DirEntry::Directory(id) => f(DirEntry::Directory(&absolute(prefix, id))),
+            });
+        }
+
+        // Mount points appear as directories of their parent
+        let mut mount_points: HashSet<&str, RandomState> = self.mounts.iter()
+            .map(|(prefix, _)| &**prefix)
+            .filter(|prefix| parent(prefix) == id)
+            .collect();
+
+        let result = self.base.read_dir(id, &mut |entry| {
+            if let DirEntry::Directory(dir) = entry {
+                mount_points.remove(dir);
+            }
+            f(entry)
+        });
+
+        // The parent directory may only exist because of mounts
+        if result.is_err() && mount_points.is_empty() {
+            return result;
+        }
+
+        for dir in mount_points {
+            f(DirEntry::Directory(dir));
+        }
+        Ok(())
+    }
+
+    fn take_changes(&self, f: &mut dyn FnMut(&str, &str)) {
+        self.base.take_changes(&mut |id, ext| {
+            if !self.is_mounted(id) {
+                f(id, ext);
+            }
+        });
+
+        for (prefix, source) in &self.mounts {
+            source.take_changes(&mut |id, ext| f(&absolute(prefix, id), ext));
+        }
+    }
+}
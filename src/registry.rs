@@ -0,0 +1,272 @@
+//! Registries of assets keyed by name
+
+use crate::{Asset, AssetCache, AssetRef, RandomState};
+
+use std::{collections::HashMap, convert::TryFrom, error::Error, fmt, io, ops::Index};
+
+
+/// An asset type that can be loaded in a [`Registry`].
+///
+/// The associated items of this trait are validation hooks, checked when the
+/// registry is loaded with [`AssetCache::load_registry`]. Their defaults
+/// accept any set of entries, except duplicates.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, RegistryEntry, loader};
+///
+/// struct Biome(String);
+///
+/// impl From<String> for Biome {
+///     fn from(s: String) -> Biome {
+///         Biome(s)
+///     }
+/// }
+///
+/// impl Asset for Biome {
+///     const EXT: &'static str = "txt";
+///     type Loader = loader::LoadFrom<String, loader::StringLoader>;
+/// }
+///
+/// impl RegistryEntry for Biome {
+///     const REQUIRED: &'static [&'static str] = &["plains"];
+///
+///     fn validate(_name: &str, biome: &Biome) -> Result<(), String> {
+///         if biome.0.is_empty() {
+///             return Err("empty description".into());
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+///
+/// [`Registry`]: struct.Registry.html
+/// [`AssetCache::load_registry`]: struct.AssetCache.html#method.load_registry
+pub trait RegistryEntry: Asset {
+    /// Names of the entries that must be present in the registry.
+    const REQUIRED: &'static [&'static str] = &[];
+
+    /// Whether an entry can be defined in several layers, in which case the
+    /// last layer wins.
+    ///
+    /// If `false`, such entries are an error.
+    const ALLOW_OVERRIDES: bool = false;
+
+    /// Checks an entry of the registry.
+    ///
+    /// `name` is the name of the entry, ie the last segment of its id.
+    #[inline]
+    fn validate(name: &str, entry: &Self) -> Result<(), String> {
+        let _ = (name, entry);
+        Ok(())
+    }
+}
+
+
+/// A set of assets loaded from directories and keyed by name.
+///
+/// A registry is loaded with [`AssetCache::load_registry`]. Each asset is
+/// stored with the last segment of its id (its file stem) as key, so it can
+/// be retrieved with [`get`] without building full ids.
+///
+/// Each entry is also given a [`RegistryId`], a small integer that can be
+/// stored instead of its name for cheap lookups. Ids are given in the order
+/// of the layers, then in alphabetical order in each layer, and an entry
+/// overriden by a later layer keeps the id of its first definition.
+///
+/// Entries are references to cached assets, so they are updated when the
+/// assets are reloaded, and `RegistryId`s stay valid. However, files added or
+/// removed after loading are not taken into account, and validation hooks
+/// are not checked again.
+///
+/// [`AssetCache::load_registry`]: struct.AssetCache.html#method.load_registry
+/// [`get`]: #method.get
+/// [`RegistryId`]: struct.RegistryId.html
+pub struct Registry<'a, A> {
+    entries: Vec<(Box<str>, AssetRef<'a, A>)>,
+    ids: HashMap<Box<str>, RegistryId, RandomState>,
+}
+
+impl<'a, A> Registry<'a, A> {
+    /// Returns the entry with the given name, if any.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<AssetRef<'a, A>> {
+        self.id_of(name).map(|id| self[id])
+    }
+
+    /// Returns the id of the entry with the given name, if any.
+    #[inline]
+    pub fn id_of(&self, name: &str) -> Option<RegistryId> {
+        self.ids.get(name).copied()
+    }
+
+    /// Returns the entry with the given id, if any.
+    #[inline]
+    pub fn get_by_id(&self, id: RegistryId) -> Option<AssetRef<'a, A>> {
+        self.entries.get(id.index()).map(|(_, entry)| *entry)
+    }
+
+    /// Returns the name of the entry with the given id, if any.
+    #[inline]
+    pub fn name_of(&self, id: RegistryId) -> Option<&str> {
+        self.entries.get(id.index()).map(|(name, _)| &**name)
+    }
+
+    /// Returns `true` if the registry has an entry with the given name.
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.ids.contains_key(name)
+    }
+
+    /// Returns the number of entries in the registry.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the registry has no entry.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns an iterator over the ids, the names and the entries of the
+    /// registry, ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = (RegistryId, &str, AssetRef<'a, A>)> + '_ {
+        self.entries.iter().enumerate().map(|(i, (name, entry))| (RegistryId(i as u32), &**name, *entry))
+    }
+}
+
+impl<A: RegistryEntry> Registry<'_, A> {
+    pub(crate) fn load<'a>(cache: &'a AssetCache, layers: &[&str]) -> Result<Registry<'a, A>, RegistryError> {
+        let mut entries: Vec<(Box<str>, AssetRef<A>)> = Vec::new();
+        let mut ids = HashMap::with_hasher(RandomState::new());
+        let mut origins: Vec<&str> = Vec::new();
+
+        for &layer in layers {
+            let dir = cache.load_dir::<A>(layer).map_err(|err| RegistryError::Io(layer.to_owned(), err))?;
+
+            let mut assets: Vec<_> = dir.iter_all().collect();
+            assets.sort_by_key(|&(id, _)| id);
+
+            for (id, entry) in assets {
+                let entry = entry.map_err(|err| RegistryError::Load(id.to_owned(), err.to_string()))?;
+                let name: Box<str> = id.rsplit('.').next().unwrap_or(id).into();
+
+                A::validate(&name, &entry.read()).map_err(|reason| RegistryError::Invalid(id.to_owned(), reason))?;
+
+                match ids.get(&name) {
+                    Some(&RegistryId(i)) => {
+                        if !A::ALLOW_OVERRIDES {
+                            let layers = (origins[i as usize].to_owned(), layer.to_owned());
+                            return Err(RegistryError::Duplicate(name.into(), layers));
+                        }
+                        entries[i as usize].1 = entry;
+                    },
+                    None => {
+                        let i = u32::try_from(entries.len()).expect("too many entries in registry");
+                        ids.insert(name.clone(), RegistryId(i));
+                        entries.push((name, entry));
+                        origins.push(layer);
+                    },
+                }
+            }
+        }
+
+        for &name in A::REQUIRED {
+            if !ids.contains_key(name) {
+                return Err(RegistryError::Missing(name.to_owned()));
+            }
+        }
+
+        Ok(Registry { entries, ids })
+    }
+}
+
+impl<'a, A> Index<RegistryId> for Registry<'a, A> {
+    type Output = AssetRef<'a, A>;
+
+    /// Returns the entry with the given id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id does not come from this registry.
+    #[inline]
+    fn index(&self, id: RegistryId) -> &AssetRef<'a, A> {
+        &self.entries[id.index()].1
+    }
+}
+
+impl<A> fmt::Debug for Registry<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.entries.iter().map(|(name, _)| name)).finish()
+    }
+}
+
+/// A handle to an entry of a [`Registry`].
+///
+/// Ids are small integers that can be stored in place of names, for example
+/// in components, and used to get entries without hashing.
+///
+/// [`Registry`]: struct.Registry.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RegistryId(u32);
+
+impl RegistryId {
+    /// Returns the index of the entry in its registry.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+
+/// An error which occurs when loading a [`Registry`].
+///
+/// [`Registry`]: struct.Registry.html
+#[derive(Debug)]
+pub enum RegistryError {
+    /// A layer could not be read as a directory.
+    Io(String, io::Error),
+
+    /// An asset could not be loaded.
+    ///
+    /// The id of the asset is given, along with the error of its loader as a
+    /// string.
+    Load(String, String),
+
+    /// An entry was rejected by [`RegistryEntry::validate`].
+    ///
+    /// [`RegistryEntry::validate`]: trait.RegistryEntry.html#method.validate
+    Invalid(String, String),
+
+    /// An entry is defined in two layers, which are given.
+    Duplicate(String, (String, String)),
+
+    /// A required entry is missing.
+    Missing(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(layer, err) => write!(f, "cannot read layer {:?}: {}", layer, err),
+            Self::Load(id, err) => write!(f, "cannot load {:?}: {}", id, err),
+            Self::Invalid(id, reason) => write!(f, "invalid entry {:?}: {}", id, reason),
+            Self::Duplicate(name, (first, second)) => {
+                write!(f, "entry {:?} is defined in both {:?} and {:?}", name, first, second)
+            },
+            Self::Missing(name) => write!(f, "missing required entry {:?}", name),
+        }
+    }
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Summary of the content of a cache
+
+use crate::{Asset, cache::Key, lock::CacheEntry, source::Metadata, Collision};
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    fmt,
+    mem,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::RandomState;
+
+
+struct TypeInfo {
+    name: &'static str,
+    size: usize,
+    heap_size: unsafe fn(&CacheEntry) -> usize,
+}
+
+fn no_heap_size(_: &CacheEntry) -> usize {
+    0
+}
+
+/// Returns the memory owned by the asset of an entry, or 0 if it is being
+/// written.
+///
+/// # Safety
+///
+/// The entry must contain a value of type `A`.
+unsafe fn heap_size_of<A: Asset>(entry: &CacheEntry) -> usize {
+    entry.get_ref::<A>().data.try_read().map_or(0, |asset| asset.heap_size())
+}
+
+/// Statistics gathered by a cache to build a `DebugReport`.
+pub(crate) struct Stats {
+    types: HashMap<TypeId, TypeInfo, RandomState>,
+    load_errors: usize,
+    load_warnings: usize,
+    reload_errors: usize,
+    last_reloads: HashMap<Key, SystemTime, RandomState>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::with_hasher(RandomState::new()),
+            load_errors: 0,
+            load_warnings: 0,
+            reload_errors: 0,
+            last_reloads: HashMap::with_hasher(RandomState::new()),
+        }
+    }
+
+    fn type_info<A: 'static>(&mut self) -> &mut TypeInfo {
+        self.types.entry(TypeId::of::<A>()).or_insert_with(|| TypeInfo {
+            name: std::any::type_name::<A>(),
+            size: mem::size_of::<A>(),
+            heap_size: no_heap_size,
+        })
+    }
+
+    /// Records informations about the type `A`.
+    pub fn add_type<A: 'static>(&mut self) {
+        self.type_info::<A>();
+    }
+
+    /// Records informations about the type `A`, including how to get the
+    /// memory owned by its values.
+    pub fn add_asset_type<A: Asset>(&mut self) {
+        self.type_info::<A>().heap_size = heap_size_of::<A>;
+    }
+
+    pub fn load_error(&mut self) {
+        self.load_errors += 1;
+    }
+
+    pub fn load_warnings(&mut self, count: usize) {
+        self.load_warnings += count;
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    pub fn reload_errors(&mut self, count: usize) {
+        self.reload_errors += count;
+    }
+
+    pub fn reloaded(&mut self, key: Key) {
+        self.last_reloads.insert(key, SystemTime::now());
+    }
+
+    #[cfg(feature = "leak-detection")]
+    pub fn leak(&self, key: &Key, guards: usize) -> LeakReport {
+        LeakReport {
+            id: key.id().to_owned(),
+            type_name: self.type_name(key.type_id()),
+            guards,
+        }
+    }
+
+    /// Returns the memory used by an asset, including the memory it owns if
+    /// its type tells it.
+    pub fn size_of_entry(&self, key: &Key, entry: &CacheEntry) -> usize {
+        match self.types.get(&key.type_id()) {
+            Some(t) => t.size + unsafe { (t.heap_size)(entry) },
+            None => 0,
+        }
+    }
+
+    pub fn type_name(&self, type_id: TypeId) -> &'static str {
+        self.types.get(&type_id).map_or("<unknown>", |t| t.name)
+    }
+
+    pub fn report<'a>(
+        &self,
+        path: &Path,
+        assets: impl Iterator<Item = (&'a Key, &'a CacheEntry)>,
+        dirs: usize,
+        collisions: Vec<Collision>,
+    ) -> DebugReport {
+        let mut types = HashMap::<_, _, RandomState>::default();
+        for (key, entry) in assets {
+            let (count, bytes) = types.entry(key.type_id()).or_insert((0, 0));
+            *count += 1;
+            *bytes += self.size_of_entry(key, entry);
+        }
+
+        let mut types: Vec<_> = types.into_iter().map(|(type_id, (count, estimated_bytes))| TypeReport {
+            name: self.type_name(type_id),
+            count,
+            estimated_bytes,
+        }).collect();
+        types.sort_by_key(|t| t.name);
+
+        let mut last_reloads: Vec<_> = self.last_reloads.iter().map(|(key, &time)| ReloadReport {
+            id: key.id().to_owned(),
+            type_name: self.type_name(key.type_id()),
+            time,
+        }).collect();
+        last_reloads.sort_by_key(|r| std::cmp::Reverse(r.time));
+
+        DebugReport {
+            path: path.to_owned(),
+            types,
+            dirs,
+            load_errors: self.load_errors,
+            load_warnings: self.load_warnings,
+            reload_errors: self.reload_errors,
+            last_reloads,
+            collisions,
+        }
+    }
+}
+
+
+/// A summary of the content of an [`AssetCache`].
+///
+/// It can be obtained with [`AssetCache::debug_report`]. It implements
+/// `Display`, so it can easily be printed or drawn in a debug overlay.
+///
+/// [`AssetCache`]: struct.AssetCache.html
+/// [`AssetCache::debug_report`]: struct.AssetCache.html#method.debug_report
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DebugReport {
+    /// The path of the cache's root.
+    pub path: PathBuf,
+
+    /// Statistics for each type of asset in the cache, sorted by name.
+    pub types: Vec<TypeReport>,
+
+    /// The number of directories in the cache.
+    pub dirs: usize,
+
+    /// The number of failed loads since the creation of the cache.
+    pub load_errors: usize,
+
+    /// The number of warnings reported by loaders with
+    /// [`LoadContext::warn`] since the creation of the cache.
+    ///
+    /// [`LoadContext::warn`]: loader/struct.LoadContext.html#method.warn
+    pub load_warnings: usize,
+
+    /// The number of failed reloads since the creation of the cache.
+    ///
+    /// This is only relevant when hot-reloading is used.
+    pub reload_errors: usize,
+
+    /// The last time each reloaded asset was reloaded, most recent first.
+    ///
+    /// This is only relevant when hot-reloading is used.
+    pub last_reloads: Vec<ReloadReport>,
+
+    /// The files loaded as several types of assets.
+    ///
+    /// See [`AssetCache::collisions`].
+    ///
+    /// [`AssetCache::collisions`]: struct.AssetCache.html#method.collisions
+    pub collisions: Vec<Collision>,
+}
+
+impl DebugReport {
+    /// The total number of assets in the cache.
+    pub fn asset_count(&self) -> usize {
+        self.types.iter().map(|t| t.count).sum()
+    }
+
+    /// An estimation of the memory used by the assets of the cache.
+    ///
+    /// See [`TypeReport::estimated_bytes`].
+    ///
+    /// [`TypeReport::estimated_bytes`]: struct.TypeReport.html#structfield.estimated_bytes
+    pub fn estimated_bytes(&self) -> usize {
+        self.types.iter().map(|t| t.estimated_bytes).sum()
+    }
+}
+
+/// Statistics about a type of asset in a [`DebugReport`].
+///
+/// [`DebugReport`]: struct.DebugReport.html
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TypeReport {
+    /// The name of the type.
+    pub name: &'static str,
+
+    /// The number of assets of this type in the cache.
+    pub count: usize,
+
+    /// An estimation of the memory used by the assets of this type.
+    ///
+    /// This is the size of the values themselves, plus the memory they own
+    /// as given by [`Asset::heap_size`]. For types that do not implement it,
+    /// this is a shallow size: the content of a `Vec`, for example, is not
+    /// counted.
+    ///
+    /// [`Asset::heap_size`]: trait.Asset.html#method.heap_size
+    pub estimated_bytes: usize,
+}
+
+/// The last reload of an asset in a [`DebugReport`].
+///
+/// [`DebugReport`]: struct.DebugReport.html
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ReloadReport {
+    /// The id of the asset.
+    pub id: String,
+
+    /// The name of the type of the asset.
+    pub type_name: &'static str,
+
+    /// When the asset was reloaded.
+    pub time: SystemTime,
+}
+
+/// An asset on which guards are still held, given by
+/// [`AssetCache::report_leaks`].
+///
+/// [`AssetCache::report_leaks`]: struct.AssetCache.html#method.report_leaks
+#[cfg(feature = "leak-detection")]
+#[cfg_attr(docsrs, doc(cfg(feature = "leak-detection")))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LeakReport {
+    /// The id of the asset.
+    pub id: String,
+
+    /// The name of the type of the asset.
+    pub type_name: &'static str,
+
+    /// The number of `AssetGuard`s currently held on the asset.
+    pub guards: usize,
+}
+
+#[cfg(feature = "leak-detection")]
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {} guard(s) held", self.id, self.type_name, self.guards)
+    }
+}
+
+/// How an asset would be loaded, given by [`AssetCache::resolve`].
+///
+/// [`AssetCache::resolve`]: struct.AssetCache.html#method.resolve
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Resolution {
+    /// The id of the asset.
+    pub id: String,
+
+    /// The extension of the file that would be read.
+    ///
+    /// If the type of the asset accepts any extension and no file with the id
+    /// was found, this is empty.
+    pub ext: String,
+
+    /// The name of the loader that would be used.
+    pub loader: &'static str,
+
+    /// The path of the file, if it would be read from the cache's directory.
+    pub path: Option<PathBuf>,
+
+    /// The prefix of the source mounted with [`AssetCache::mount`] from which
+    /// the file would be read, if any.
+    ///
+    /// [`AssetCache::mount`]: struct.AssetCache.html#method.mount
+    pub mount: Option<String>,
+
+    /// Informations about the file, or `None` if it does not exist.
+    pub metadata: Option<Metadata>,
+
+    /// Whether the asset is already in the cache, in which case loading it
+    /// would not read the file.
+    pub cached: bool,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> ", self.id)?;
+
+        match (&self.path, &self.mount) {
+            (Some(path), _) => write!(f, "{:?}", path)?,
+            (None, Some(mount)) => write!(f, "{:?} in source mounted at {:?}", self.ext, mount)?,
+            (None, None) => write!(f, "{:?} in source", self.ext)?,
+        }
+
+        if let Some(metadata) = &self.metadata {
+            for layer in &metadata.layers {
+                write!(f, " ({:?})", layer)?;
+            }
+        } else {
+            f.write_str(" (missing)")?;
+        }
+
+        write!(f, " with {}", self.loader)?;
+        if self.cached {
+            f.write_str(", cached")?;
+        }
+        Ok(())
+    }
+}
+
+/// The number of reloads shown by `DebugReport`'s `Display` implementation.
+const SHOWN_RELOADS: usize = 10;
+
+impl fmt::Display for DebugReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Asset cache at {:?}", self.path)?;
+        writeln!(
+            f,
+            "  {} assets, {} directories, ~{} bytes",
+            self.asset_count(), self.dirs, self.estimated_bytes(),
+        )?;
+
+        for t in &self.types {
+            writeln!(f, "    {}: {} (~{} bytes)", t.name, t.count, t.estimated_bytes)?;
+        }
+
+        writeln!(
+            f,
+            "  Load errors: {}, warnings: {}, reload errors: {}",
+            self.load_errors, self.load_warnings, self.reload_errors,
+        )?;
+
+        if !self.last_reloads.is_empty() {
+            writeln!(f, "  Last reloads:")?;
+
+            let now = SystemTime::now();
+            for reload in self.last_reloads.iter().take(SHOWN_RELOADS) {
+                let ago = now.duration_since(reload.time).unwrap_or_default();
+                writeln!(f, "    {} ({}) {}s ago", reload.id, reload.type_name, ago.as_secs())?;
+            }
+        }
+
+        if !self.collisions.is_empty() {
+            writeln!(f, "  Collisions:")?;
+
+            for collision in &self.collisions {
+                writeln!(f, "    {}", collision)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+//! Scopes that track which assets are used
+
+use crate::{
+    Asset,
+    AssetCache,
+    AssetErr,
+    AssetRef,
+    cache::Key,
+    lock::Mutex,
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::RandomState;
+
+
+/// Counts the number of live scopes using each asset.
+pub(crate) struct ScopeCounts {
+    counts: HashMap<Key, usize, RandomState>,
+}
+
+impl ScopeCounts {
+    pub fn new() -> Self {
+        Self {
+            counts: HashMap::with_hasher(RandomState::new()),
+        }
+    }
+
+    fn acquire(&mut self, key: Key) {
+        *self.counts.entry(key).or_insert(0) += 1;
+    }
+
+    fn release(&mut self, key: &Key) {
+        if let Some(count) = self.counts.get_mut(key) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Returns `true` if a live scope loaded the asset.
+    pub fn is_used(&self, key: &Key) -> bool {
+        self.counts.get(key).map_or(false, |&count| count > 0)
+    }
+
+    /// Removes and returns the keys that are not used by any scope.
+    pub fn take_unused(&mut self) -> Vec<Key> {
+        let mut unused = Vec::new();
+        let mut used = HashMap::with_hasher(RandomState::new());
+
+        for (key, count) in self.counts.drain() {
+            if count == 0 {
+                unused.push(key);
+            } else {
+                used.insert(key, count);
+            }
+        }
+
+        self.counts = used;
+        unused
+    }
+
+    /// Forgets an asset that was removed from the cache.
+    pub fn remove(&mut self, key: &Key) {
+        self.counts.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+}
+
+
+/// A scope that tracks the assets loaded through it.
+///
+/// It is created with [`AssetCache::scope`]. When a scope is dropped, the
+/// assets loaded through it are marked as unused if no other live scope loaded
+/// them, and can then be removed from the cache with
+/// [`AssetCache::remove_unused`].
+///
+/// This gives a way to tie assets to the lifetime of a state of an
+/// application, for example a menu or a level.
+///
+/// Assets that were never loaded through a scope are never considered unused.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, loader};
+///
+/// struct X(i32);
+///
+/// impl From<i32> for X {
+///     fn from(x: i32) -> X { X(x) }
+/// }
+///
+/// impl Asset for X {
+///     const EXT: &'static str = "x";
+///     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+/// }
+///
+/// let mut cache = AssetCache::new("assets")?;
+///
+/// {
+///     let level = cache.scope();
+///     let x = level.load::<X>("test.cache")?;
+///     assert_eq!(x.read().0, 42);
+/// }
+///
+/// assert_eq!(cache.remove_unused(), 1);
+/// assert!(cache.load_cached::<X>("test.cache").is_none());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache::scope`]: struct.AssetCache.html#method.scope
+/// [`AssetCache::remove_unused`]: struct.AssetCache.html#method.remove_unused
+pub struct AssetScope<'a> {
+    cache: &'a AssetCache,
+    loaded: Mutex<HashSet<Key, RandomState>>,
+}
+
+impl<'a> AssetScope<'a> {
+    #[inline]
+    pub(crate) fn new(cache: &'a AssetCache) -> Self {
+        Self {
+            cache,
+            loaded: Mutex::new(HashSet::with_hasher(RandomState::new())),
+        }
+    }
+
+    /// Returns the cache of the scope.
+    #[inline]
+    pub fn cache(&self) -> &'a AssetCache {
+        self.cache
+    }
+
+    /// Loads an asset and marks it as used by this scope.
+    ///
+    /// See [`AssetCache::load`] for more details.
+    ///
+    /// [`AssetCache::load`]: struct.AssetCache.html#method.load
+    pub fn load<A: Asset>(&self, id: &str) -> Result<AssetRef<'a, A>, AssetErr<A>> {
+        let asset = self.cache.load(id)?;
+
+        let key = Key::new::<A>(id.into());
+        let mut loaded = self.loaded.lock();
+        if !loaded.contains(&key) {
+            self.cache.scopes.lock().acquire(Key::new::<A>(id.into()));
+            loaded.insert(key);
+        }
+
+        Ok(asset)
+    }
+
+    /// Returns the number of distinct assets loaded through this scope.
+    pub fn len(&self) -> usize {
+        self.loaded.lock().len()
+    }
+
+    /// Returns `true` if no asset was loaded through this scope.
+    pub fn is_empty(&self) -> bool {
+        self.loaded.lock().is_empty()
+    }
+}
+
+impl Drop for AssetScope<'_> {
+    fn drop(&mut self) {
+        let mut scopes = self.cache.scopes.lock();
+        for key in self.loaded.get_mut().iter() {
+            scopes.release(key);
+        }
+    }
+}
+
+impl fmt::Debug for AssetScope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssetScope")
+            .field("loaded", &*self.loaded.lock())
+            .finish()
+    }
+}
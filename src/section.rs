@@ -0,0 +1,263 @@
+//! Assets that are sections of another asset
+
+use crate::{Asset, AssetErr};
+
+use std::{
+    error::Error,
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+    sync::Arc,
+};
+
+
+/// An asset that is a section of a bigger file.
+///
+/// This is useful for monolithic files such as settings, where each system
+/// only cares about a part of the file. Sections are loaded with
+/// [`AssetCache::load_section`], with an id of the form `file#section`: the
+/// file is loaded as an asset of type `Parent`, and the section is then
+/// extracted from it.
+///
+/// When the parent asset is reloaded, sections are extracted again, but they
+/// are only updated if their value changed.
+///
+/// Files that contain many assets (e.g. a sprite sheet or a stream of
+/// documents) can list their sections with [`section_names`], so they can all
+/// be loaded with [`AssetCache::load_sections`].
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, Section, loader, source::MemorySource};
+/// use std::{borrow::Cow, io, str};
+///
+/// // A file with a number on each line
+/// struct Config(Vec<i32>);
+///
+/// struct ConfigLoader;
+///
+/// impl loader::Loader<Config> for ConfigLoader {
+///     type Err = Box<dyn std::error::Error>;
+///
+///     fn load(content: io::Result<Cow<[u8]>>) -> Result<Config, Self::Err> {
+///         let content = content?;
+///         let lines = str::from_utf8(&content)?.lines();
+///         let values = lines.map(str::parse).collect::<Result<_, _>>()?;
+///         Ok(Config(values))
+///     }
+/// }
+///
+/// impl Asset for Config {
+///     const EXT: &'static str = "cfg";
+///     type Loader = ConfigLoader;
+/// }
+///
+/// // A line of this file
+/// #[derive(PartialEq)]
+/// struct Line(i32);
+///
+/// impl Section for Line {
+///     type Parent = Config;
+///
+///     fn extract(config: &Config, name: &str) -> Option<Line> {
+///         let n: usize = name.parse().ok()?;
+///         config.0.get(n).map(|&x| Line(x))
+///     }
+/// }
+///
+/// let source = MemorySource::new();
+/// source.insert("config", "cfg", "1\n2\n3");
+/// let cache = AssetCache::with_source(source);
+///
+/// let line = cache.load_section::<Line>("config#1")?;
+/// assert_eq!(line.read().0, 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache::load_section`]: struct.AssetCache.html#method.load_section
+/// [`AssetCache::load_sections`]: struct.AssetCache.html#method.load_sections
+/// [`section_names`]: #method.section_names
+pub trait Section: PartialEq + Sized + Send + Sync + 'static {
+    /// The type of the whole file.
+    type Parent: Asset;
+
+    /// Extracts the section with the given name from the parent asset.
+    ///
+    /// Returns `None` if there is no such section.
+    fn extract(parent: &Self::Parent, name: &str) -> Option<Self>;
+
+    /// Returns the names of all sections of the parent asset.
+    ///
+    /// The default implementation returns an empty list, which means that
+    /// sections cannot be enumerated.
+    fn section_names(parent: &Self::Parent) -> Vec<String> {
+        let _ = parent;
+        Vec::new()
+    }
+}
+
+/// A byte range of a bigger asset.
+///
+/// This allows one physical blob (e.g. a soundbank) to back many logical
+/// assets without duplicating its bytes in memory: all slices share the
+/// buffer of their parent asset.
+///
+/// A slice is loaded as a [`Section`] whose name is a range of bytes, in the
+/// form `start..end`. The type parameter `A` is the type of the parent asset,
+/// which has to give access to a shared buffer.
+///
+/// The parent asset is loaded as a whole, once, and slices are views into
+/// it. To read a range of a file without loading the rest of it, use
+/// [`Source::read_range`] instead.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, Sliced, loader, source::MemorySource};
+/// use std::sync::Arc;
+///
+/// struct SoundBank(Arc<[u8]>);
+///
+/// impl From<Vec<u8>> for SoundBank {
+///     fn from(bytes: Vec<u8>) -> SoundBank { SoundBank(bytes.into()) }
+/// }
+///
+/// impl AsRef<Arc<[u8]>> for SoundBank {
+///     fn as_ref(&self) -> &Arc<[u8]> { &self.0 }
+/// }
+///
+/// impl Asset for SoundBank {
+///     const EXT: &'static str = "bank";
+///     type Loader = loader::LoadFrom<Vec<u8>, loader::BytesLoader>;
+/// }
+///
+/// let source = MemorySource::new();
+/// source.insert("sounds", "bank", &b"hellobonjour"[..]);
+/// let cache = AssetCache::with_source(source);
+///
+/// let hello = cache.load_section::<Sliced<SoundBank>>("sounds#0..5")?;
+/// assert_eq!(&**hello.read(), b"hello");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`Section`]: trait.Section.html
+/// [`Source::read_range`]: source/trait.Source.html#method.read_range
+pub struct Sliced<A> {
+    bytes: Arc<[u8]>,
+    start: usize,
+    end: usize,
+    _marker: PhantomData<fn() -> A>,
+}
+
+impl<A> Sliced<A> {
+    /// Returns the offset of the slice in its parent.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the whole buffer shared with the parent asset.
+    #[inline]
+    pub fn shared_bytes(&self) -> &Arc<[u8]> {
+        &self.bytes
+    }
+}
+
+impl<A> Deref for Sliced<A> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.bytes[self.start..self.end]
+    }
+}
+
+impl<A> Clone for Sliced<A> {
+    fn clone(&self) -> Self {
+        Self {
+            bytes: self.bytes.clone(),
+            start: self.start,
+            end: self.end,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A> PartialEq for Sliced<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && **self == **other
+    }
+}
+
+impl<A> fmt::Debug for Sliced<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sliced")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+impl<A> Section for Sliced<A>
+where
+    A: Asset + AsRef<Arc<[u8]>>,
+{
+    type Parent = A;
+
+    fn extract(parent: &A, name: &str) -> Option<Self> {
+        let pos = name.find("..")?;
+        let start = name[..pos].parse().ok()?;
+        let end = name[pos + 2..].parse().ok()?;
+
+        let bytes = parent.as_ref();
+        if start > end || end > bytes.len() {
+            return None;
+        }
+
+        Some(Sliced {
+            bytes: bytes.clone(),
+            start,
+            end,
+            _marker: PhantomData,
+        })
+    }
+}
+
+
+/// Splits a section id into the id of the file and the name of the section.
+pub(crate) fn split_id(id: &str) -> Option<(&str, &str)> {
+    let pos = id.find('#')?;
+    Some((&id[..pos], &id[pos + 1..]))
+}
+
+
+/// An error which occurs when loading a section with
+/// [`AssetCache::load_section`].
+///
+/// [`AssetCache::load_section`]: struct.AssetCache.html#method.load_section
+#[derive(Debug)]
+pub enum SectionError<E> {
+    /// The parent asset could not be loaded.
+    Load(E),
+
+    /// The id is not of the form `file#section`.
+    InvalidId(String),
+
+    /// The parent asset has no section with the given name.
+    NotFound(String),
+}
+
+impl<E: fmt::Display> fmt::Display for SectionError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(err) => err.fmt(f),
+            Self::InvalidId(id) => write!(f, "invalid section id {:?}", id),
+            Self::NotFound(id) => write!(f, "section {:?} not found", id),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> Error for SectionError<E> {}
+
+pub(crate) type SectionErr<S> = SectionError<AssetErr<<S as Section>::Parent>>;
@@ -0,0 +1,71 @@
+//! Frozen copies of the values of assets
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+use crate::RandomState;
+
+
+/// A frozen copy of the values of all assets of type `A` in a cache.
+///
+/// It is created with [`AssetCache::snapshot`], and can be given back to
+/// [`AssetCache::restore`] to reset the assets to these values, which is
+/// useful for deterministic replays or rollback.
+///
+/// Cloning a `Snapshot` is cheap, as values are shared.
+///
+/// [`AssetCache::snapshot`]: struct.AssetCache.html#method.snapshot
+/// [`AssetCache::restore`]: struct.AssetCache.html#method.restore
+pub struct Snapshot<A> {
+    values: Arc<HashMap<Box<str>, A, RandomState>>,
+}
+
+impl<A> Snapshot<A> {
+    #[inline]
+    pub(crate) fn new(values: HashMap<Box<str>, A, RandomState>) -> Self {
+        Self {
+            values: Arc::new(values),
+        }
+    }
+
+    /// Returns the value of the asset with the given id in the snapshot.
+    #[inline]
+    pub fn get(&self, id: &str) -> Option<&A> {
+        self.values.get(id)
+    }
+
+    /// Returns the number of assets in the snapshot.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the snapshot contains no asset.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterates over the ids and the values of the assets in the snapshot.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &A)> {
+        self.values.iter().map(|(id, value)| (&**id, value))
+    }
+}
+
+impl<A> Clone for Snapshot<A> {
+    fn clone(&self) -> Self {
+        Self {
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<A: fmt::Debug> fmt::Debug for Snapshot<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
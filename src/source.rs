@@ -0,0 +1,836 @@
+//! Sources from which assets are read.
+//!
+//! By default, an [`AssetCache`] reads its assets from the filesystem, but it
+//! can be created with any type implementing [`Source`] with
+//! [`AssetCache::with_source`].
+//!
+//! [`AssetCache`]: ../struct.AssetCache.html
+//! [`AssetCache::with_source`]: ../struct.AssetCache.html#method.with_source
+//! [`Source`]: trait.Source.html
+
+use crate::{
+    AssetId,
+    id,
+    lock::{Mutex, RwLock},
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs,
+    io::{self, Read, Seek},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use crate::RandomState;
+
+
+/// An entry of a directory, given by [`Source::read_dir`].
+///
+/// Ids are given in full, ie they include the id of the directory.
+///
+/// [`Source::read_dir`]: trait.Source.html#tymethod.read_dir
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirEntry<'a> {
+    /// A file with an id and an extension.
+    File(&'a str, &'a str),
+
+    /// A directory with an id.
+    Directory(&'a str),
+}
+
+impl<'a> DirEntry<'a> {
+    /// Returns the id of the entry.
+    #[inline]
+    pub fn id(self) -> &'a str {
+        match self {
+            DirEntry::File(id, _) => id,
+            DirEntry::Directory(id) => id,
+        }
+    }
+}
+
+/// Informations about a file of a [`Source`], given by [`Source::metadata`].
+///
+/// [`Source`]: trait.Source.html
+/// [`Source::metadata`]: trait.Source.html#method.metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// The size of the file, in bytes.
+    pub len: u64,
+
+    /// The last modification time of the file, if the source knows it.
+    pub modified: Option<SystemTime>,
+
+    /// The layers of the nested [`FallbackSource`]s that provide the file,
+    /// outermost first.
+    ///
+    /// This is empty if the file does not come from a `FallbackSource`.
+    ///
+    /// [`FallbackSource`]: struct.FallbackSource.html
+    pub layers: Vec<Layer>,
+
+    /// The prefix of the source mounted with [`AssetCache::mount`] that
+    /// provides the file, if any.
+    ///
+    /// [`AssetCache::mount`]: ../struct.AssetCache.html#method.mount
+    pub mount: Option<String>,
+}
+
+impl Metadata {
+    /// Creates metadata for a file of `len` bytes, with no other information.
+    #[inline]
+    pub fn new(len: u64) -> Self {
+        Metadata {
+            len,
+            modified: None,
+            layers: Vec::new(),
+            mount: None,
+        }
+    }
+}
+
+/// What to do with symbolic links in a [`FileSystem`].
+///
+/// It can be set with [`FileSystem::set_symlink_policy`] or
+/// [`AssetCache::set_symlink_policy`]. The policy applies to every component
+/// of the path of a file under the root, so a symbolic link to a directory
+/// affects all files in it. It is applied in the same way when loading assets,
+/// reading directories and hot-reloading.
+///
+/// [`FileSystem`]: struct.FileSystem.html
+/// [`FileSystem::set_symlink_policy`]: struct.FileSystem.html#method.set_symlink_policy
+/// [`AssetCache::set_symlink_policy`]: ../struct.AssetCache.html#method.set_symlink_policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Symbolic links are followed.
+    ///
+    /// This is the default.
+    Follow,
+
+    /// Symbolic links are ignored, as if they did not exist.
+    Skip,
+
+    /// Reading a file through a symbolic link is an error, and directories
+    /// that contain symbolic links cannot be read.
+    Error,
+}
+
+impl Default for SymlinkPolicy {
+    #[inline]
+    fn default() -> Self {
+        SymlinkPolicy::Follow
+    }
+}
+
+fn symlink_error(path: &Path, policy: SymlinkPolicy) -> io::Error {
+    let kind = match policy {
+        SymlinkPolicy::Error => io::ErrorKind::Other,
+        _ => io::ErrorKind::NotFound,
+    };
+    io::Error::new(kind, format!("{:?} is a symbolic link", path))
+}
+
+/// Checks that no component of `path` under `root` is a symbolic link, if the
+/// policy requires it.
+fn check_symlinks(root: &Path, path: &Path, policy: SymlinkPolicy) -> io::Result<()> {
+    if policy == SymlinkPolicy::Follow {
+        return Ok(());
+    }
+
+    let rel = match path.strip_prefix(root) {
+        Ok(rel) => rel,
+        Err(_) => return Ok(()),
+    };
+
+    let mut current = root.to_owned();
+    for component in rel.components() {
+        current.push(component);
+        if fs::symlink_metadata(&current)?.file_type().is_symlink() {
+            return Err(symlink_error(&current, policy));
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Bytes sources to load assets from.
+///
+/// Files are identified by an id and an extension, in the same way as assets.
+///
+/// A source can report changes of its content with [`take_changes`], which
+/// are applied by [`AssetCache::reload_changed`].
+///
+/// [`take_changes`]: #method.take_changes
+/// [`AssetCache::reload_changed`]: ../struct.AssetCache.html#method.reload_changed
+pub trait Source: Send + Sync {
+    /// Reads the content of a file.
+    ///
+    /// The content is written in `buf`, which is given empty.
+    fn read(&self, id: &str, ext: &str, buf: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Reads `len` bytes of a file, starting at `offset`.
+    ///
+    /// The content is written in `buf`, which is given empty. An error is
+    /// returned if the file is too short.
+    ///
+    /// This allows to read an entry of a large file without reading the whole
+    /// file. The default implementation reads the whole file and only keeps
+    /// the requested range.
+    fn read_range(&self, id: &str, ext: &str, offset: u64, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        self.read(id, ext, buf)?;
+        let start = offset as usize;
+        match start.checked_add(len) {
+            Some(end) if offset <= std::usize::MAX as u64 && end <= buf.len() => {
+                buf.truncate(end);
+                buf.drain(..start);
+                Ok(())
+            },
+            _ => {
+                buf.clear();
+                Err(io::ErrorKind::UnexpectedEof.into())
+            },
+        }
+    }
+
+    /// Calls `f` with each entry of a directory.
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()>;
+
+    /// Returns informations about a file, such as its size and its last
+    /// modification time.
+    ///
+    /// The default implementation reads the whole file to get its size, and
+    /// gives no modification time.
+    fn metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        let mut buf = Vec::new();
+        self.read(id, ext, &mut buf)?;
+        Ok(Metadata::new(buf.len() as u64))
+    }
+
+    /// Calls `f` with the id and the extension of each file changed since the
+    /// last call to this method.
+    ///
+    /// The default implementation reports no change.
+    fn take_changes(&self, f: &mut dyn FnMut(&str, &str)) {
+        let _ = f;
+    }
+}
+
+impl<S: Source + ?Sized> Source for Box<S> {
+    #[inline]
+    fn read(&self, id: &str, ext: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+        (**self).read(id, ext, buf)
+    }
+
+    #[inline]
+    fn metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        (**self).metadata(id, ext)
+    }
+
+    #[inline]
+    fn read_range(&self, id: &str, ext: &str, offset: u64, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        (**self).read_range(id, ext, offset, len, buf)
+    }
+
+    #[inline]
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        (**self).read_dir(id, f)
+    }
+
+    #[inline]
+    fn take_changes(&self, f: &mut dyn FnMut(&str, &str)) {
+        (**self).take_changes(f)
+    }
+}
+
+impl<S: Source + ?Sized> Source for Arc<S> {
+    #[inline]
+    fn read(&self, id: &str, ext: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+        (**self).read(id, ext, buf)
+    }
+
+    #[inline]
+    fn metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        (**self).metadata(id, ext)
+    }
+
+    #[inline]
+    fn read_range(&self, id: &str, ext: &str, offset: u64, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        (**self).read_range(id, ext, offset, len, buf)
+    }
+
+    #[inline]
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        (**self).read_dir(id, f)
+    }
+
+    #[inline]
+    fn take_changes(&self, f: &mut dyn FnMut(&str, &str)) {
+        (**self).take_changes(f)
+    }
+}
+
+
+/// A source that reads assets from a directory of the filesystem.
+///
+/// This is the source used by [`AssetCache::new`].
+///
+/// Ids are validated with [`AssetId::parse`], so they cannot designate a path
+/// outside of the root. Symbolic links may still point outside of it: for
+/// untrusted content, the source can be confined to its root with
+/// [`set_confined`].
+///
+/// [`AssetCache::new`]: ../struct.AssetCache.html#method.new
+/// [`AssetId::parse`]: ../struct.AssetId.html#method.parse
+/// [`set_confined`]: #method.set_confined
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    path: PathBuf,
+    symlinks: SymlinkPolicy,
+    confined: bool,
+}
+
+impl FileSystem {
+    /// Creates a new `FileSystem` from a directory.
+    ///
+    /// Symbolic links will be followed. The path is canonicalized: on
+    /// Windows, this gives a verbatim path (with a `\\?\` prefix), so deep
+    /// asset trees whose paths are longer than 260 characters and network
+    /// shares are supported.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if `path` is not valid readable directory.
+    /// The error message includes the path and the reason why it cannot be
+    /// used.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<FileSystem> {
+        let path = path.as_ref();
+        let context = |err: io::Error| {
+            io::Error::new(err.kind(), format!("invalid asset directory {:?}: {}", path, err))
+        };
+
+        let path = path.canonicalize().map_err(context)?;
+        if !path.is_dir() {
+            let err = io::Error::new(io::ErrorKind::InvalidInput, "not a directory");
+            return Err(context(err));
+        }
+        let _ = path.read_dir().map_err(context)?;
+
+        Ok(FileSystem::from_canonical_root(path))
+    }
+
+    /// Creates a `FileSystem` from an already canonicalized root.
+    pub(crate) fn from_canonical_root(path: PathBuf) -> FileSystem {
+        FileSystem {
+            path,
+            symlinks: SymlinkPolicy::default(),
+            confined: false,
+        }
+    }
+
+    /// Sets what to do with symbolic links under the root.
+    ///
+    /// See [`SymlinkPolicy`] for more details.
+    ///
+    /// [`SymlinkPolicy`]: enum.SymlinkPolicy.html
+    #[inline]
+    pub fn set_symlink_policy(&mut self, policy: SymlinkPolicy) {
+        self.symlinks = policy;
+    }
+
+    /// Returns what is done with symbolic links under the root.
+    #[inline]
+    pub fn symlink_policy(&self) -> SymlinkPolicy {
+        self.symlinks
+    }
+
+    /// Sets whether files must be confined to the root.
+    ///
+    /// When enabled, the path of each file is canonicalized and files that are
+    /// not under the root (e.g. through a symbolic link) cannot be read and
+    /// are not listed in directories. This is useful for games that load
+    /// untrusted content, at the cost of a few more system calls per read.
+    ///
+    /// This is disabled by default.
+    #[inline]
+    pub fn set_confined(&mut self, confined: bool) {
+        self.confined = confined;
+    }
+
+    /// Returns `true` if files must be confined to the root.
+    #[inline]
+    pub fn is_confined(&self) -> bool {
+        self.confined
+    }
+
+    /// Checks that a path under the root can be read, according to the
+    /// symbolic link policy and the confinement to the root.
+    pub(crate) fn check_path(&self, path: &Path) -> io::Result<()> {
+        check_symlinks(&self.path, path, self.symlinks)?;
+
+        if self.confined && !path.canonicalize()?.starts_with(&self.path) {
+            let msg = format!("{:?} is outside of the asset directory", path);
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, msg));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the path of the source's root.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the path of a file from its id and extension, checking that the id
+    /// is valid so the path cannot be outside of the root.
+    fn checked_path_of(&self, id: &str, ext: &str) -> io::Result<PathBuf> {
+        if let Err(err) = AssetId::parse(id) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, err));
+        }
+        Ok(self.path_of(id, ext))
+    }
+
+    /// Gets the path of a file from its id and extension.
+    pub fn path_of(&self, id: &str, ext: &str) -> PathBuf {
+        let mut path = self.path.clone();
+        path.extend(id.split('.'));
+        path.set_extension(ext);
+        path
+    }
+}
+
+impl Source for FileSystem {
+    fn read(&self, id: &str, ext: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+        let path = self.checked_path_of(id, ext)?;
+        self.check_path(&path)?;
+        fs::File::open(path)?.read_to_end(buf)?;
+        Ok(())
+    }
+
+    fn metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        let path = self.checked_path_of(id, ext)?;
+        self.check_path(&path)?;
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_file() {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let mut result = Metadata::new(metadata.len());
+        result.modified = metadata.modified().ok();
+        Ok(result)
+    }
+
+    fn read_range(&self, id: &str, ext: &str, offset: u64, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        let path = self.checked_path_of(id, ext)?;
+        self.check_path(&path)?;
+        let mut file = fs::File::open(path)?;
+        file.seek(io::SeekFrom::Start(offset))?;
+
+        // Do not allocate `len` bytes upfront, as it may be much larger than
+        // the file.
+        if let Err(err) = file.take(len as u64).read_to_end(buf) {
+            buf.clear();
+            return Err(err);
+        }
+        if buf.len() != len {
+            buf.clear();
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        if !id::is_valid_dir(id) {
+            let msg = format!("invalid directory id {:?}", id);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+
+        let dir_path = self.path_of(id, "");
+        self.check_path(&dir_path)?;
+        let entries = fs::read_dir(dir_path)?;
+
+        let mut entry_id = id.to_owned();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if self.symlinks != SymlinkPolicy::Follow {
+                let is_symlink = entry.file_type().map_or(false, |t| t.is_symlink());
+                if is_symlink {
+                    match self.symlinks {
+                        SymlinkPolicy::Error => return Err(symlink_error(&path, self.symlinks)),
+                        _ => continue,
+                    }
+                }
+            }
+
+            if self.confined && !path.canonicalize().map_or(false, |p| p.starts_with(&self.path)) {
+                continue;
+            }
+
+            let is_dir = path.is_dir();
+
+            let name = if is_dir { path.file_name() } else { path.file_stem() };
+            let name = match name.and_then(|n| n.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            entry_id.truncate(id.len());
+            if !id.is_empty() {
+                entry_id.push('.');
+            }
+            entry_id.push_str(name);
+
+            if is_dir {
+                if !name.contains('.') {
+                    f(DirEntry::Directory(&entry_id));
+                }
+            } else if path.is_file() {
+                let ext = match path.extension() {
+                    Some(ext) => match ext.to_str() {
+                        Some(ext) => ext,
+                        None => continue,
+                    },
+                    None => "",
+                };
+
+                f(DirEntry::File(&entry_id, ext));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// A source that stores files in memory.
+///
+/// It is useful for content that never hits the disk, such as generated or
+/// downloaded data. Files can be added or removed at any time, from any
+/// thread. Each modification is recorded as a change, so it is applied to
+/// loaded assets by [`AssetCache::reload_changed`] (or [`hot_reload`]).
+///
+/// The source is typically wrapped in an `Arc` so it can be modified after
+/// the cache is created.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, loader, source::MemorySource};
+/// use std::sync::Arc;
+///
+/// # struct X(i32);
+/// # impl From<i32> for X { fn from(x: i32) -> X { X(x) } }
+/// # impl Asset for X {
+/// #     const EXT: &'static str = "x";
+/// #     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+/// # }
+/// let source = Arc::new(MemorySource::new());
+/// let cache = AssetCache::with_source(source.clone());
+///
+/// source.insert("generated.level", "x", "5");
+/// let level = cache.load::<X>("generated.level")?;
+///
+/// source.insert("generated.level", "x", "6");
+/// cache.reload_changed();
+/// assert_eq!(level.read().0, 6);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache::reload_changed`]: ../struct.AssetCache.html#method.reload_changed
+/// [`hot_reload`]: ../struct.AssetCache.html#method.hot_reload
+pub struct MemorySource {
+    files: RwLock<HashMap<(String, String), Vec<u8>, RandomState>>,
+    changes: Mutex<Vec<(String, String)>>,
+}
+
+impl MemorySource {
+    /// Creates a new empty source.
+    pub fn new() -> Self {
+        Self {
+            files: RwLock::new(HashMap::with_hasher(RandomState::new())),
+            changes: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Adds a file to the source, replacing the previous one if any.
+    pub fn insert(&self, id: &str, ext: &str, content: impl Into<Vec<u8>>) {
+        let key = (id.to_owned(), ext.to_owned());
+        self.files.write().insert(key.clone(), content.into());
+        self.changes.lock().push(key);
+    }
+
+    /// Removes a file from the source.
+    pub fn remove(&self, id: &str, ext: &str) {
+        let key = (id.to_owned(), ext.to_owned());
+        if self.files.write().remove(&key).is_some() {
+            self.changes.lock().push(key);
+        }
+    }
+
+    /// Records a change of a file without modifying it.
+    pub fn touch(&self, id: &str, ext: &str) {
+        self.changes.lock().push((id.to_owned(), ext.to_owned()));
+    }
+
+    /// Returns `true` if the source contains the given file.
+    pub fn contains(&self, id: &str, ext: &str) -> bool {
+        self.files.read().contains_key(&(id.to_owned(), ext.to_owned()))
+    }
+}
+
+impl Default for MemorySource {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Source for MemorySource {
+    fn read(&self, id: &str, ext: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+        let files = self.files.read();
+        match files.get(&(id.to_owned(), ext.to_owned())) {
+            Some(content) => {
+                buf.extend_from_slice(content);
+                Ok(())
+            },
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        let files = self.files.read();
+        match files.get(&(id.to_owned(), ext.to_owned())) {
+            Some(content) => Ok(Metadata::new(content.len() as u64)),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn read_range(&self, id: &str, ext: &str, offset: u64, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        let files = self.files.read();
+        let content = files.get(&(id.to_owned(), ext.to_owned())).ok_or(io::ErrorKind::NotFound)?;
+
+        let start = offset as usize;
+        match start.checked_add(len) {
+            Some(end) if offset <= std::usize::MAX as u64 && end <= content.len() => {
+                buf.extend_from_slice(&content[start..end]);
+                Ok(())
+            },
+            _ => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        let files = self.files.read();
+        let mut dirs = Vec::new();
+        let mut found = id.is_empty();
+
+        for (file_id, ext) in files.keys() {
+            let rest = if id.is_empty() {
+                &file_id[..]
+            } else if file_id.len() > id.len() && file_id.starts_with(id) && file_id[id.len()..].starts_with('.') {
+                &file_id[id.len() + 1..]
+            } else {
+                continue;
+            };
+            found = true;
+
+            match rest.find('.') {
+                Some(pos) => {
+                    let dir = &file_id[..file_id.len() - rest.len() + pos];
+                    if !dirs.contains(&dir) {
+                        dirs.push(dir);
+                    }
+                },
+                None => f(DirEntry::File(file_id, ext)),
+            }
+        }
+
+        if !found {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        for dir in dirs {
+            f(DirEntry::Directory(dir));
+        }
+
+        Ok(())
+    }
+
+    fn take_changes(&self, f: &mut dyn FnMut(&str, &str)) {
+        let changes = std::mem::take(&mut *self.changes.lock());
+        for (id, ext) in &changes {
+            f(id, ext);
+        }
+    }
+}
+
+impl fmt::Debug for MemorySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemorySource")
+            .field("files", &self.files.read().keys())
+            .finish()
+    }
+}
+
+
+/// The layer of a [`FallbackSource`] that provides a file.
+///
+/// [`FallbackSource`]: struct.FallbackSource.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layer {
+    /// The primary source, which overrides the secondary one.
+    Primary,
+
+    /// The secondary source.
+    Secondary,
+}
+
+
+/// A source that reads from a primary source, and falls back to a secondary
+/// one when reading fails.
+///
+/// This is useful for example to override some files of a packed archive with
+/// a local directory. Reading a directory gives the union of the entries of
+/// both sources, and changes of both sources are reported.
+///
+/// Files of the primary source override files with the same id and extension
+/// in the secondary one. The layer that provides each file can be found with
+/// [`read_dir_layers`] or [`layer_of`], for example to display where mods
+/// come from. Several layers can be stacked by nesting `FallbackSource`s.
+///
+/// [`read_dir_layers`]: #method.read_dir_layers
+/// [`layer_of`]: #method.layer_of
+///
+/// # Example
+///
+/// ```no_run
+/// use assets_manager::{AssetCache, source::{FallbackSource, FileSystem}};
+///
+/// let source = FallbackSource::new(
+///     FileSystem::new("overrides")?,
+///     FileSystem::new("assets")?,
+/// );
+/// let cache = AssetCache::with_source(source);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FallbackSource<P, S> {
+    primary: P,
+    secondary: S,
+}
+
+impl<P: Source, S: Source> FallbackSource<P, S> {
+    /// Creates a new `FallbackSource` from two sources.
+    #[inline]
+    pub fn new(primary: P, secondary: S) -> Self {
+        Self { primary, secondary }
+    }
+
+    /// Returns a reference to the primary source.
+    #[inline]
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// Returns a reference to the secondary source.
+    #[inline]
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    /// Reads the content of a directory, giving the layer from which each
+    /// entry comes.
+    ///
+    /// Entries of the secondary source that are overridden by the primary
+    /// one are skipped. A directory present in both sources is reported as
+    /// coming from the primary source.
+    pub fn read_dir_layers(&self, id: &str, f: &mut dyn FnMut(DirEntry, Layer)) -> io::Result<()> {
+        let mut seen = HashSet::<_, RandomState>::default();
+
+        let primary = self.primary.read_dir(id, &mut |entry| {
+            let key = match entry {
+                DirEntry::File(id, ext) => (id.to_owned(), Some(ext.to_owned())),
+                DirEntry::Directory(id) => (id.to_owned(), None),
+            };
+            seen.insert(key);
+            f(entry, Layer::Primary);
+        });
+
+        let secondary = self.secondary.read_dir(id, &mut |entry| {
+            let is_new = match entry {
+                DirEntry::File(id, ext) => !seen.contains(&(id.to_owned(), Some(ext.to_owned()))),
+                DirEntry::Directory(id) => !seen.contains(&(id.to_owned(), None)),
+            };
+            if is_new {
+                f(entry, Layer::Secondary);
+            }
+        });
+
+        // Only fail if the directory cannot be read in both sources
+        primary.or(secondary)
+    }
+
+    /// Returns the layer that provides the file with the given id and
+    /// extension, or `None` if no layer has it.
+    pub fn layer_of(&self, id: &str, ext: &str) -> Option<Layer> {
+        let dir = match id.rfind('.') {
+            Some(pos) => &id[..pos],
+            None => "",
+        };
+
+        let mut layer = None;
+        let _ = self.read_dir_layers(dir, &mut |entry, from| {
+            if entry == DirEntry::File(id, ext) {
+                layer = Some(from);
+            }
+        });
+        layer
+    }
+}
+
+impl<P: Source, S: Source> Source for FallbackSource<P, S> {
+    fn read(&self, id: &str, ext: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.primary.read(id, ext, buf) {
+            Ok(()) => Ok(()),
+            Err(_) => {
+                buf.clear();
+                self.secondary.read(id, ext, buf)
+            },
+        }
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        self.read_dir_layers(id, &mut |entry, _| f(entry))
+    }
+
+    fn metadata(&self, id: &str, ext: &str) -> io::Result<Metadata> {
+        let (mut metadata, layer) = match self.primary.metadata(id, ext) {
+            Ok(metadata) => (metadata, Layer::Primary),
+            Err(_) => (self.secondary.metadata(id, ext)?, Layer::Secondary),
+        };
+        metadata.layers.insert(0, layer);
+        Ok(metadata)
+    }
+
+    fn read_range(&self, id: &str, ext: &str, offset: u64, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.primary.read_range(id, ext, offset, len, buf) {
+            Ok(()) => Ok(()),
+            Err(_) => {
+                buf.clear();
+                self.secondary.read_range(id, ext, offset, len, buf)
+            },
+        }
+    }
+
+    fn take_changes(&self, f: &mut dyn FnMut(&str, &str)) {
+        self.primary.take_changes(f);
+        self.secondary.take_changes(f);
+    }
+}
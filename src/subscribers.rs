@@ -0,0 +1,192 @@
+//! Notification of reloaded assets
+
+use crate::{
+    Asset,
+    Section,
+    cache::{AccessKey, AssetMap, Key},
+    lock::CacheEntry,
+    section,
+};
+
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Weak, atomic::{AtomicBool, Ordering}, mpsc::Sender},
+};
+
+use crate::RandomState;
+
+
+/// Something that wants to be notified when an asset is reloaded.
+trait Subscriber: Send {
+    /// Notifies the subscriber that the asset in `entry` was reloaded.
+    ///
+    /// Other assets changed by the subscriber are pushed to `changed`, so
+    /// their own subscribers are notified too.
+    ///
+    /// Returns `false` if the subscriber is not interested anymore.
+    ///
+    /// # Safety
+    ///
+    /// `entry` must contain an asset of the type the subscriber was created
+    /// for, and `assets` must be the content of the cache.
+    unsafe fn notify(&self, entry: &CacheEntry, assets: &AssetMap, changed: &mut Vec<Key>) -> bool;
+}
+
+/// Sends a snapshot of the new value in a channel.
+struct Updates<A>(Sender<Arc<A>>);
+
+impl<A: Asset + Clone> Subscriber for Updates<A> {
+    unsafe fn notify(&self, entry: &CacheEntry, _: &AssetMap, _: &mut Vec<Key>) -> bool {
+        let value = entry.get_ref::<A>().snapshot();
+        self.0.send(value).is_ok()
+    }
+}
+
+/// Marks a virtual asset as needing to be computed again.
+struct Invalidate(Weak<AtomicBool>);
+
+impl Subscriber for Invalidate {
+    unsafe fn notify(&self, _: &CacheEntry, _: &AssetMap, _: &mut Vec<Key>) -> bool {
+        match self.0.upgrade() {
+            Some(dirty) => {
+                dirty.store(true, Ordering::Release);
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+/// Extracts a section again when its parent is reloaded.
+struct SectionUpdater<S> {
+    id: Box<str>,
+    _marker: PhantomData<fn() -> S>,
+}
+
+impl<S: Section> Subscriber for SectionUpdater<S> {
+    unsafe fn notify(&self, entry: &CacheEntry, assets: &AssetMap, changed: &mut Vec<Key>) -> bool {
+        // The section was removed from the cache
+        let section_entry = match assets.get(&AccessKey::new::<S>(&self.id)) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        let name = match section::split_id(&self.id) {
+            Some((_, name)) => name,
+            None => return false,
+        };
+
+        let parent = entry.get_ref::<S::Parent>();
+        let new = match S::extract(&parent.read(), name) {
+            Some(section) => section,
+            // Keep the last value if the section disappeared
+            None => return true,
+        };
+
+        if *section_entry.get_ref::<S>().read() != new {
+            section_entry.write(new, None);
+            changed.push(Key::new::<S>(self.id.clone()));
+        }
+
+        true
+    }
+}
+
+
+type PrefixCallback = Box<dyn FnMut(&str) + Send>;
+
+/// The list of subscribers of each asset in a cache.
+pub(crate) struct Subscribers {
+    map: HashMap<Key, Vec<Box<dyn Subscriber>>, RandomState>,
+    prefixes: Vec<(Box<str>, PrefixCallback)>,
+}
+
+impl Subscribers {
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::with_hasher(RandomState::new()),
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Calls `callback` with the id of each reloaded asset whose id starts
+    /// with `prefix`.
+    pub fn add_prefix(&mut self, prefix: &str, callback: PrefixCallback) {
+        self.prefixes.push((prefix.into(), callback));
+    }
+
+    /// Sends the new value of an asset to `sender` each time it is reloaded.
+    pub fn add_updates<A: Asset + Clone>(&mut self, id: &str, sender: Sender<Arc<A>>) {
+        self.map
+            .entry(Key::new::<A>(id.into()))
+            .or_default()
+            .push(Box::new(Updates(sender)));
+    }
+
+    /// Sets `dirty` each time the asset is reloaded, as long as it is alive.
+    pub fn add_invalidation(&mut self, key: Key, dirty: Weak<AtomicBool>) {
+        self.map
+            .entry(key)
+            .or_default()
+            .push(Box::new(Invalidate(dirty)));
+    }
+
+    /// Updates the section with the given id each time its parent is
+    /// reloaded.
+    pub fn add_section<S: Section>(&mut self, parent_id: &str, id: &str) {
+        let updater = SectionUpdater::<S> {
+            id: id.into(),
+            _marker: PhantomData,
+        };
+
+        self.map
+            .entry(Key::new::<S::Parent>(parent_id.into()))
+            .or_default()
+            .push(Box::new(updater));
+    }
+
+    /// Removes all subscribers of an asset.
+    pub fn remove(&mut self, key: &AccessKey) {
+        self.map.remove(key);
+    }
+
+    /// Removes all subscribers, including prefix callbacks.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.prefixes.clear();
+    }
+
+    /// Notifies all subscribers of an asset that it was reloaded.
+    ///
+    /// Subscribers that are not interested anymore are removed.
+    ///
+    /// # Safety
+    ///
+    /// `entry` must be the cache entry that matches `key`, and `assets` the
+    /// content of the cache.
+    pub unsafe fn notify(&mut self, key: &AccessKey, entry: &CacheEntry, assets: &AssetMap) {
+        let mut changed = Vec::new();
+        self.notify_one(key, entry, assets, &mut changed);
+
+        // Sections updated because their parent was reloaded
+        while let Some(key) = changed.pop() {
+            if let Some(entry) = assets.get(&key) {
+                self.notify_one(key.borrow(), entry, assets, &mut changed);
+            }
+        }
+    }
+
+    unsafe fn notify_one(&mut self, key: &AccessKey, entry: &CacheEntry, assets: &AssetMap, changed: &mut Vec<Key>) {
+        if let Some(subscribers) = self.map.get_mut(key) {
+            subscribers.retain(|sub| sub.notify(entry, assets, changed));
+        }
+
+        for (prefix, callback) in &mut self.prefixes {
+            if key.id().starts_with(&**prefix) {
+                callback(key.id());
+            }
+        }
+    }
+}
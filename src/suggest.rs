@@ -0,0 +1,117 @@
+//! Suggestions for ids of missing assets
+
+use crate::source::{DirEntry, Source};
+
+
+/// Builds the message of the panic raised for a missing asset when the cache
+/// panics on missing assets.
+///
+/// The message includes the closest existing file in the same directory, if
+/// there is one.
+pub(crate) fn missing_message(source: &dyn Source, id: &str, ext: &str, type_name: &str) -> String {
+    let mut message = format!("asset \"{}\" ({}) not found: no file \"{}.{}\"", id, type_name, id, ext);
+
+    if let Some(file) = near_misses(source, id, ext).first() {
+        message.push_str(&format!("; did you mean \"{}\"?", file));
+    }
+
+    message
+}
+
+/// The maximum number of files listed in errors.
+#[cfg(feature = "rich-errors")]
+const MAX_LISTED: usize = 3;
+
+/// Adds the files with a name close to the one of a missing asset to its
+/// "not found" error.
+///
+/// Other errors are returned unchanged.
+#[cfg(feature = "rich-errors")]
+pub(crate) fn enrich_error(source: &dyn Source, id: &str, ext: &str, err: &std::io::Error) -> Option<std::io::Error> {
+    if err.kind() != std::io::ErrorKind::NotFound {
+        return None;
+    }
+
+    let files = near_misses(source, id, ext);
+    if files.is_empty() {
+        return None;
+    }
+
+    let files: Vec<_> = files.iter().take(MAX_LISTED).map(|file| format!("\"{}\"", file)).collect();
+    let message = if files.len() == 1 {
+        format!("{} (\"{}.{}\"); did you mean {}?", err, id, ext, files[0])
+    } else {
+        format!("{} (\"{}.{}\"); did you mean one of {}?", err, id, ext, files.join(", "))
+    };
+    Some(std::io::Error::new(err.kind(), message))
+}
+
+/// Lists the files of the directory of `id` with a name close to the one of
+/// the asset, closest first.
+///
+/// Files with the same name and another extension come first, then files
+/// with the same extension whose name is at most a third of its length away
+/// from the requested one.
+fn near_misses(source: &dyn Source, id: &str, ext: &str) -> Vec<String> {
+    let (dir, name) = match id.rfind('.') {
+        Some(pos) => (&id[..pos], &id[pos + 1..]),
+        None => ("", id),
+    };
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    let mut files = Vec::new();
+    let result = source.read_dir(dir, &mut |entry| {
+        if let DirEntry::File(file_id, file_ext) = entry {
+            let file_name = file_id.rsplit('.').next().unwrap_or(file_id);
+
+            let distance = if file_name == name {
+                if file_ext == ext {
+                    return;
+                }
+                0
+            } else if file_ext == ext {
+                edit_distance(name, file_name)
+            } else {
+                return;
+            };
+
+            if distance <= max_distance {
+                files.push((distance, format!("{}.{}", file_id, file_ext)));
+            }
+        }
+    });
+    if result.is_err() {
+        return Vec::new();
+    }
+
+    files.sort();
+    files.into_iter().map(|(_, file)| file).collect()
+}
+
+/// The edit distance between two strings, where swapping two adjacent
+/// characters counts as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Rows i - 2, i - 1 and i of the distance matrix
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 0..a.len() {
+        current[0] = i + 1;
+        for j in 0..b.len() {
+            let cost = (a[i] != b[j]) as usize;
+            let mut distance = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = distance.min(before[j - 1] + 1);
+            }
+            current[j + 1] = distance;
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
@@ -0,0 +1,89 @@
+//! Tools to test code that uses assets without touching the filesystem.
+//!
+//! # Example
+//!
+//! ```
+//! use assets_manager::{Asset, AssetCache, loader, testing::{self, MapSource}};
+//! use std::sync::Arc;
+//!
+//! struct X(i32);
+//!
+//! impl From<i32> for X {
+//!     fn from(x: i32) -> X { X(x) }
+//! }
+//!
+//! impl Asset for X {
+//!     const EXT: &'static str = "x";
+//!     type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+//! }
+//!
+//! let source = Arc::new(MapSource::new());
+//! source.insert("a.b", "x", "42");
+//!
+//! let cache = AssetCache::with_source(source.clone());
+//! let x = testing::assert_loads::<X>(&cache, "a.b");
+//! assert_eq!(x.read().0, 42);
+//!
+//! // Simulate a change of the file
+//! source.insert("a.b", "x", "-7");
+//! cache.reload_changed();
+//! assert_eq!(x.read().0, -7);
+//! ```
+
+use crate::{
+    Asset,
+    AssetCache,
+    AssetErr,
+    AssetRef,
+    source::MemorySource,
+};
+
+use std::fmt;
+
+
+/// An in-memory source, to be used in tests.
+///
+/// This is the same type as [`MemorySource`]: files are added with
+/// [`insert`], and each modification of the source is recorded as a change,
+/// so [`AssetCache::reload_changed`] simulates hot-reloading. Changes can also
+/// be simulated with [`touch`].
+///
+/// [`MemorySource`]: ../source/struct.MemorySource.html
+/// [`insert`]: ../source/struct.MemorySource.html#method.insert
+/// [`touch`]: ../source/struct.MemorySource.html#method.touch
+/// [`AssetCache::reload_changed`]: ../struct.AssetCache.html#method.reload_changed
+pub type MapSource = MemorySource;
+
+
+/// Loads an asset, panicking with a helpful message if it fails.
+pub fn assert_loads<'a, A: Asset>(cache: &'a AssetCache, id: &str) -> AssetRef<'a, A> {
+    match cache.load::<A>(id) {
+        Ok(asset) => asset,
+        Err(err) => panic!("failed to load {:?} as {}: {}", id, std::any::type_name::<A>(), err),
+    }
+}
+
+/// Tries to load an asset, panicking if it succeeds.
+///
+/// The error is returned so it can be further inspected.
+pub fn assert_load_fails<A: Asset>(cache: &AssetCache, id: &str) -> AssetErr<A> {
+    match cache.load::<A>(id) {
+        Ok(_) => panic!("{:?} was unexpectedly loaded as {}", id, std::any::type_name::<A>()),
+        Err(err) => err,
+    }
+}
+
+/// Applies the changes of the cache's source and checks that the given asset
+/// changed.
+///
+/// The value of the asset is compared before and after the reload.
+pub fn assert_reloads<A>(cache: &AssetCache, id: &str)
+where
+    A: Asset + Clone + PartialEq + fmt::Debug,
+{
+    let asset = assert_loads::<A>(cache, id);
+    let before = asset.read().clone();
+    cache.reload_changed();
+    let after = asset.read();
+    assert!(before != *after, "{:?} was not reloaded: its value is still {:?}", id, before);
+}
@@ -37,6 +37,27 @@ mod asset_cache {
         assert!(cache.is_err());
     }
 
+    #[test]
+    fn new_error_message() {
+        let err = AssetCache::new("asset").unwrap_err();
+        assert!(err.to_string().contains("\"asset\""));
+
+        let err = AssetCache::new("src/lib.rs").unwrap_err();
+        assert!(err.to_string().contains("not a directory"));
+    }
+
+    #[test]
+    fn new_or_create() {
+        let tmp = std::env::temp_dir().join(format!("assets_manager_new_or_create_{}", std::process::id()));
+        let path = tmp.join("a").join("b");
+
+        assert!(AssetCache::new(&path).is_err());
+        assert!(AssetCache::new_or_create(&path).is_ok());
+        assert!(AssetCache::new(&path).is_ok());
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
     #[test]
     fn load() {
         let cache = AssetCache::new("assets").unwrap();
@@ -86,6 +107,508 @@ mod asset_cache {
         assert!(loaded.next().is_none());
     }
 
+    #[test]
+    fn load_dirs() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let dirs = cache.load_dirs::<X>("").unwrap();
+        let mut ids: Vec<_> = dirs.iter().map(|dir| dir.id()).collect();
+        ids.sort();
+        assert_eq!(ids, ["common", "example", "test"]);
+
+        let test = dirs.iter().find(|dir| dir.id() == "test").unwrap();
+        assert!(test.contains("test.cache"));
+    }
+
+    #[test]
+    fn load_dir_ids() {
+        let cache = AssetCache::new("assets").unwrap();
+        let dir = cache.load_dir::<X>("test").unwrap();
+
+        let mut ids: Vec<_> = dir.ids().collect();
+        ids.sort();
+        assert_eq!(ids, ["test.a", "test.b", "test.cache"]);
+
+        assert!(dir.contains("test.a"));
+        assert!(!dir.contains("a"));
+        assert!(!dir.contains("test.d"));
+    }
+
+    #[test]
+    fn lazy_asset() {
+        use crate::LazyAsset;
+
+        let cache = AssetCache::new("assets").unwrap();
+        let lazy = LazyAsset::<X>::new("test.cache");
+        let missing = LazyAsset::<X>::from("test.missing");
+
+        let x = lazy.get(&cache).unwrap();
+        assert!(x.ptr_eq(&cache.load_cached("test.cache").unwrap()));
+        assert!(lazy.is_resolved());
+        assert!(lazy.clone().get(&cache).unwrap().ptr_eq(&x));
+
+        assert!(missing.get(&cache).is_err());
+        assert!(!missing.is_resolved());
+    }
+
+    #[test]
+    fn load_with() {
+        use crate::ContextAsset;
+        use std::{borrow::Cow, io, sync::{Arc, Mutex}};
+
+        struct Scaled(i32);
+
+        impl ContextAsset<i32> for Scaled {
+            const EXT: &'static str = "x";
+            type Err = Box<dyn std::error::Error>;
+
+            fn load_with(content: io::Result<Cow<[u8]>>, scale: &i32) -> Result<Self, Self::Err> {
+                let x: i32 = std::str::from_utf8(&content?)?.trim().parse()?;
+                Ok(Scaled(x * scale))
+            }
+        }
+
+        let cache = AssetCache::new("assets").unwrap();
+
+        let x = cache.load_with::<Scaled, _>("test.cache", &2).unwrap();
+        assert_eq!(x.read().0, 84);
+        assert_eq!(cache.load_with::<Scaled, _>("test.cache", &3).unwrap().read().0, 84);
+
+        let reloaded = Arc::new(Mutex::new(Vec::new()));
+        let ids = reloaded.clone();
+        cache.subscribe_prefix("test", move |id| ids.lock().unwrap().push(id.to_owned()));
+        let double = cache.define("double", |cache| {
+            cache.load_with::<Scaled, _>("test.cache", &1).unwrap().read().0 * 2
+        });
+        assert_eq!(*double.read(), 168);
+
+        cache.reload_with::<Scaled, _>("test.cache", &3).unwrap();
+        assert_eq!(x.read().0, 126);
+        assert_eq!(*reloaded.lock().unwrap(), ["test.cache"]);
+        assert_eq!(*double.read(), 252);
+
+        assert!(cache.load_with::<Scaled, _>("test.missing", &1).is_err());
+
+        // Any extension is resolved as with `load`
+        struct AnyExt(i32);
+
+        impl ContextAsset<i32> for AnyExt {
+            const EXT: &'static str = crate::ANY_EXTENSION;
+            type Err = Box<dyn std::error::Error>;
+
+            fn load_with(content: io::Result<Cow<[u8]>>, scale: &i32) -> Result<Self, Self::Err> {
+                Scaled::load_with(content, scale).map(|x| AnyExt(x.0))
+            }
+        }
+
+        assert_eq!(cache.load_with::<AnyExt, _>("test.cache", &1).unwrap().read().0, 42);
+    }
+
+    #[test]
+    fn load_dyn() {
+        use crate::LoadDynError;
+
+        let cache = AssetCache::new("assets").unwrap();
+        cache.register_type::<X>("x");
+
+        let asset = cache.load_dyn("x", "test.cache").unwrap();
+        assert!(asset.is::<X>());
+        assert_eq!(*asset.downcast::<X>().unwrap().read(), X(42));
+        assert!(asset.downcast::<X>().unwrap().ptr_eq(&cache.load("test.cache").unwrap()));
+
+        assert!(matches!(cache.load_dyn("x", "test.a"), Err(LoadDynError::Load(_))));
+        assert!(matches!(cache.load_dyn("y", "test.cache"), Err(LoadDynError::UnknownType(_))));
+    }
+
+    #[test]
+    fn usage_trace() {
+        use crate::source::MemorySource;
+
+        let trace = std::env::temp_dir().join(format!("assets_manager_usage_{}.trace", std::process::id()));
+
+        let source = MemorySource::new();
+        source.insert("b", "x", "2");
+        source.insert("a", "x", "1");
+        source.insert("broken", "x", "?");
+        let source = std::sync::Arc::new(source);
+
+        let cache = AssetCache::with_source(source.clone());
+        cache.register_type::<X>("x");
+        cache.record_usage(&trace).unwrap();
+        cache.load::<X>("b").unwrap();
+        cache.load::<X>("a").unwrap();
+        cache.load::<X>("b").unwrap();
+        cache.load::<X>("broken").unwrap_err();
+        cache.stop_recording_usage();
+
+        assert_eq!(std::fs::read_to_string(&trace).unwrap(), "x\tb\nx\ta\n");
+
+        source.remove("a", "x");
+        let cache = AssetCache::with_source(source);
+        cache.register_type::<X>("x");
+        assert_eq!(cache.replay_warmup(&trace).unwrap(), 1);
+        assert!(cache.load_cached::<X>("b").is_some());
+
+        std::fs::remove_file(&trace).unwrap();
+    }
+
+    #[test]
+    fn content_hash() {
+        use crate::source::MemorySource;
+
+        let source = std::sync::Arc::new(MemorySource::new());
+        source.insert("a", "x", "1");
+        source.insert("b", "x", "1");
+        let cache = AssetCache::with_source(source.clone());
+
+        let a = cache.load::<X>("a").unwrap();
+        let hash = a.content_hash().unwrap();
+        assert_eq!(hash, 0xaf63_ac4c_8601_9afc);
+        assert_eq!(cache.load::<X>("b").unwrap().content_hash(), Some(hash));
+
+        source.insert("a", "x", "2");
+        cache.force_reload::<X>("a").unwrap();
+        assert_ne!(a.content_hash(), Some(hash));
+
+        assert_eq!(cache.define("c", |_| 0).content_hash(), None);
+    }
+
+    #[test]
+    fn debug_report() {
+        let cache = AssetCache::new("assets").unwrap();
+        cache.load_dir::<X>("test").unwrap();
+
+        let report = cache.debug_report();
+        assert_eq!(report.asset_count(), 2);
+        assert_eq!(report.dirs, 1);
+        assert_eq!(report.load_errors, 1);
+        assert_eq!(report.types.len(), 1);
+        assert_eq!(report.types[0].name, std::any::type_name::<X>());
+        assert_eq!(report.types[0].estimated_bytes, 2 * std::mem::size_of::<X>());
+
+        assert!(report.to_string().contains("2 assets"));
+    }
+
+    #[cfg(feature = "leak-detection")]
+    #[test]
+    fn report_leaks() {
+        let cache = AssetCache::new("assets").unwrap();
+        let x = cache.load::<X>("test.cache").unwrap();
+        cache.load::<X>("test.b").unwrap();
+
+        let guard = x.read();
+        let leaks = cache.report_leaks();
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].id, "test.cache");
+        assert_eq!(leaks[0].guards, 1);
+
+        drop(guard);
+        assert!(cache.report_leaks().is_empty());
+    }
+
+    #[test]
+    fn buffered() {
+        use crate::source::MemorySource;
+        use std::sync::Arc;
+
+        let source = Arc::new(MemorySource::new());
+        source.insert("a", "x", "1");
+        let cache = AssetCache::with_source(source.clone());
+        let a = cache.load::<X>("a").unwrap().buffered();
+
+        source.insert("a", "x", "2");
+        cache.reload_changed();
+        assert_eq!(*a.read(), X(2));
+        assert_eq!(*a.read_previous(), X(1));
+
+        a.flip();
+        assert_eq!(*a.read_previous(), X(2));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlink_policy() {
+        use crate::source::{DirEntry, SymlinkPolicy};
+        use std::{fs, io, os::unix::fs::symlink};
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_symlinks_{}", std::process::id()));
+        let real = tmp.join("real");
+        fs::create_dir_all(&real).unwrap();
+        fs::write(real.join("a.x"), "1").unwrap();
+        symlink(&real, tmp.join("link")).unwrap();
+
+        let list = |cache: &AssetCache| {
+            let mut ids = Vec::new();
+            cache.source().read_dir("", &mut |entry| ids.push(entry.id().to_owned())).map(|_| {
+                ids.sort();
+                ids
+            })
+        };
+
+        let mut cache = AssetCache::new(&tmp).unwrap();
+        assert!(cache.load::<X>("link.a").is_ok());
+        assert_eq!(list(&cache).unwrap(), ["link", "real"]);
+
+        cache.clear();
+        cache.set_symlink_policy(SymlinkPolicy::Skip);
+        assert!(cache.load::<X>("real.a").is_ok());
+        assert!(cache.load::<X>("link.a").is_err());
+        assert_eq!(list(&cache).unwrap(), ["real"]);
+
+        cache.set_symlink_policy(SymlinkPolicy::Error);
+        let mut buf = Vec::new();
+        let err = cache.source().read("link.a", "x", &mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert!(list(&cache).is_err());
+        assert!(cache.source().read_dir("real", &mut |_: DirEntry| ()).is_ok());
+
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn confined() {
+        use std::{fs, io, os::unix::fs::symlink};
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_confined_{}", std::process::id()));
+        let (root, outside) = (tmp.join("root"), tmp.join("outside"));
+        fs::create_dir_all(root.join("inner")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("inner").join("a.x"), "1").unwrap();
+        fs::write(outside.join("secret.x"), "2").unwrap();
+        symlink(root.join("inner"), root.join("shared")).unwrap();
+        symlink(&outside, root.join("escape")).unwrap();
+
+        let mut cache = AssetCache::new(&root).unwrap();
+        assert!(cache.load::<X>("escape.secret").is_ok());
+
+        cache.clear();
+        cache.set_confined(true);
+        assert!(cache.load::<X>("inner.a").is_ok());
+        assert!(cache.load::<X>("shared.a").is_ok());
+
+        let mut buf = Vec::new();
+        let err = cache.source().read("escape.secret", "x", &mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        let ids = cache.find_ids("**");
+        assert!(ids.iter().any(|id| id == "inner.a"));
+        assert!(!ids.iter().any(|id| id.starts_with("escape")));
+
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn panic_on_missing() {
+        use crate::{Asset, loader};
+        use std::panic::{self, AssertUnwindSafe};
+
+        #[derive(Default)]
+        struct Y(i32);
+
+        impl From<i32> for Y {
+            fn from(n: i32) -> Y {
+                Y(n)
+            }
+        }
+
+        impl Asset for Y {
+            type Loader = loader::LoadOrDefault<loader::LoadFrom<i32, loader::ParseLoader>>;
+            const EXT: &'static str = "x";
+        }
+
+        let mut cache = AssetCache::new("assets").unwrap();
+        assert!(cache.load::<X>("test.cahce").is_err());
+
+        cache.set_panic_on_missing(true);
+        let message = |id: &'static str| {
+            let err = panic::catch_unwind(AssertUnwindSafe(|| cache.load::<X>(id).map(|_| ()))).unwrap_err();
+            err.downcast::<String>().unwrap()
+        };
+        assert!(message("test.cahce").ends_with("did you mean \"test.cache.x\"?"));
+        assert!(!message("test.unrelated").contains("did you mean"));
+
+        assert!(cache.load::<X>("test.cache").is_ok());
+        assert_eq!(cache.load::<Y>("test.cahce").unwrap().read().0, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "rich-errors")]
+    fn rich_errors() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let err = cache.load::<X>("test.cahce").unwrap_err().to_string();
+        assert!(err.ends_with("(\"test.cahce.x\"); did you mean \"test.cache.x\"?"), "{}", err);
+
+        let err = cache.load::<X>("test.unrelated").unwrap_err().to_string();
+        assert!(!err.contains("did you mean"), "{}", err);
+    }
+
+    #[test]
+    fn set_root() {
+        use std::fs;
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_set_root_{}", std::process::id()));
+        let (first, second) = (tmp.join("first"), tmp.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+
+        fs::write(first.join("a.x"), "1").unwrap();
+        fs::write(first.join("b.x"), "2").unwrap();
+        fs::write(first.join("c.x"), "3").unwrap();
+        fs::write(second.join("a.x"), "1").unwrap();
+        fs::write(second.join("b.x"), "20").unwrap();
+
+        let mut cache = AssetCache::new(&first).unwrap();
+        for id in &["a", "b", "c"] {
+            cache.load::<X>(id).unwrap();
+        }
+        let updates = cache.updates::<X>("c");
+
+        cache.set_root(&second).unwrap();
+        assert_eq!(cache.path(), second.canonicalize().unwrap());
+
+        assert_eq!(*cache.load_cached::<X>("a").unwrap().read(), X(1));
+        assert_eq!(*cache.load_cached::<X>("b").unwrap().read(), X(20));
+        assert!(cache.load_cached::<X>("c").is_none());
+        assert_eq!(updates.try_recv(), Err(std::sync::mpsc::TryRecvError::Disconnected));
+
+        let reloaded: Vec<_> = cache.debug_report().last_reloads.into_iter().map(|r| r.id).collect();
+        assert_eq!(reloaded, ["b"]);
+
+        assert!(cache.set_root(tmp.join("none")).is_err());
+        assert_eq!(cache.path(), second.canonicalize().unwrap());
+
+        let _ = fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn set_root_other_entries() {
+        use crate::ContextAsset;
+        use std::{borrow::Cow, fs, io};
+
+        struct Scaled(i32);
+
+        impl ContextAsset<i32> for Scaled {
+            const EXT: &'static str = "x";
+            type Err = Box<dyn std::error::Error>;
+
+            fn load_with(content: io::Result<Cow<[u8]>>, scale: &i32) -> Result<Self, Self::Err> {
+                let x: i32 = std::str::from_utf8(&content?)?.trim().parse()?;
+                Ok(Scaled(x * scale))
+            }
+        }
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_set_root_other_{}", std::process::id()));
+        let (first, second) = (tmp.join("first"), tmp.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+
+        fs::write(first.join("a.x"), "1").unwrap();
+        fs::write(first.join("b.x"), "2").unwrap();
+        fs::write(second.join("a.x"), "1").unwrap();
+        fs::write(second.join("b.x"), "20").unwrap();
+
+        let mut cache = AssetCache::new(&first).unwrap();
+        assert_eq!(cache.load_with::<Scaled, _>("a", &3).unwrap().read().0, 3);
+        cache.define("double", |cache| cache.load::<X>("b").map_or(0, |b| b.read().0 * 2));
+        assert_eq!(*cache.load_virtual::<i32>("double").unwrap().read(), 4);
+
+        cache.set_root(&second).unwrap();
+
+        // Entries loaded with a context are removed, even if unchanged
+        assert_eq!(cache.load_with::<Scaled, _>("a", &4).unwrap().read().0, 4);
+        // Virtual assets are kept and recomputed
+        assert_eq!(*cache.load_virtual::<i32>("double").unwrap().read(), 40);
+
+        let _ = fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn scope() {
+        let mut cache = AssetCache::new("assets").unwrap();
+
+        let menu = cache.scope();
+        let level = cache.scope();
+        menu.load::<X>("test.b").unwrap();
+        menu.load::<X>("test.cache").unwrap();
+        level.load::<X>("test.cache").unwrap();
+        assert!(level.load::<X>("test.a").is_err());
+        assert_eq!(menu.len(), 2);
+        assert_eq!(level.len(), 1);
+
+        drop(menu);
+        let remaining = cache.scope();
+        remaining.load::<X>("test.cache").unwrap();
+        drop(remaining);
+        drop(level);
+
+        cache.load::<X>("test.b").unwrap();
+        let updates = cache.updates::<X>("test.cache");
+        assert_eq!(cache.remove_unused(), 2);
+        assert!(cache.load_cached::<X>("test.cache").is_none());
+        assert_eq!(updates.try_recv(), Err(std::sync::mpsc::TryRecvError::Disconnected));
+        assert_eq!(cache.remove_unused(), 0);
+    }
+
+    #[test]
+    fn snapshot() {
+        use crate::source::MemorySource;
+        use std::sync::Arc;
+
+        let source = Arc::new(MemorySource::new());
+        source.insert("a", "x", "1");
+        source.insert("b", "x", "2");
+        let cache = AssetCache::with_source(source.clone());
+
+        let a = cache.load::<X>("a").unwrap();
+        cache.load::<X>("b").unwrap();
+
+        let snapshot = cache.snapshot::<X>();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.get("a"), Some(&X(1)));
+
+        source.insert("a", "x", "10");
+        cache.reload_changed();
+        assert_eq!(*a.read(), X(10));
+        assert_eq!(snapshot.get("a"), Some(&X(1)));
+
+        cache.restore(&snapshot.clone());
+        assert_eq!(*a.read(), X(1));
+    }
+
+    #[test]
+    fn updates() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let updates = cache.updates::<X>("test.cache");
+        cache.load::<X>("test.cache").unwrap();
+        assert!(updates.try_recv().is_err());
+
+        cache.force_reload::<X>("test.cache").unwrap();
+        assert_eq!(*updates.try_recv().unwrap(), X(42));
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[test]
+    fn clear_drops_subscribers() {
+        use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+
+        let mut cache = AssetCache::new("assets").unwrap();
+        let updates = cache.updates::<X>("test.cache");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        cache.subscribe_prefix("test", move |_| { counter.fetch_add(1, Ordering::Relaxed); });
+        cache.load::<X>("test.cache").unwrap();
+
+        cache.clear();
+        assert_eq!(updates.try_recv(), Err(std::sync::mpsc::TryRecvError::Disconnected));
+
+        cache.load::<X>("test.cache").unwrap();
+        cache.force_reload::<X>("test.cache").unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+    }
+
     #[test]
     fn take() {
         let mut cache = AssetCache::new("assets").unwrap();
@@ -105,56 +628,1941 @@ mod asset_cache {
         cache.remove::<X>("test.cache");
         assert!(cache.load_cached::<X>("test.cache").is_none());
     }
-}
 
-mod cache_entry {
-    use std::sync::{Arc, Mutex};
-    use crate::lock::CacheEntry;
+    #[cfg(feature = "hot-reloading")]
+    #[test]
+    fn hot_reload_status() {
+        use crate::HotReloadStatus;
 
-    #[derive(Clone)]
-    struct DropCounter(Arc<Mutex<usize>>);
+        let cache = AssetCache::new("assets").unwrap();
 
-    impl Drop for DropCounter {
-        fn drop(&mut self) {
-            let mut count = self.0.lock().unwrap();
-            *count += 1;
+        assert_eq!(cache.hot_reload_status(), HotReloadStatus::Stopped);
+        cache.hot_reload().unwrap();
+        assert_ne!(cache.hot_reload_status(), HotReloadStatus::Stopped);
+        cache.stop_hot_reloading();
+        assert_eq!(cache.hot_reload_status(), HotReloadStatus::Stopped);
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[test]
+    fn hot_reload_hub() {
+        use crate::{HotReloadHub, HotReloadStatus};
+
+        let hub = HotReloadHub::new().unwrap();
+        let first = AssetCache::new("assets").unwrap();
+        let second = AssetCache::new("assets/test").unwrap();
+
+        first.load::<X>("test.b").unwrap();
+        for _ in 0..2 {
+            first.hot_reload_with(&hub).unwrap();
+            second.hot_reload_with(&hub).unwrap();
         }
+        assert_eq!(first.hot_reload_status(), hub.status());
+        assert_ne!(second.hot_reload_status(), HotReloadStatus::Stopped);
+
+        first.stop_hot_reloading();
+        assert_eq!(first.hot_reload_status(), HotReloadStatus::Stopped);
+
+        drop(hub);
+        second.hot_reload().unwrap();
     }
 
+    #[cfg(feature = "hot-reloading")]
     #[test]
-    fn drop_inner() {
-        let count = DropCounter(Arc::new(Mutex::new(0)));
+    fn hot_reload_restart() {
+        use crate::HotReloadStatus;
+        use std::{sync::atomic::{AtomicBool, Ordering}, thread, time::{Duration, Instant}};
 
-        let entry_1 = CacheEntry::new(count.clone());
-        let entry_2 = CacheEntry::new(count.clone());
-        assert_eq!(*count.0.lock().unwrap(), 0);
-        drop(entry_1);
-        assert_eq!(*count.0.lock().unwrap(), 1);
-        drop(entry_2);
-        assert_eq!(*count.0.lock().unwrap(), 2);
+        let tmp = std::env::temp_dir().join(format!("assets_manager_restart_{}", std::process::id()));
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(tmp.join("a.x"), "1").unwrap();
+
+        let cache = AssetCache::new(&tmp).unwrap();
+        let a = cache.load::<X>("a").unwrap();
+
+        // Panic once in the hot-reloading thread
+        let panicked = AtomicBool::new(false);
+        cache.subscribe_prefix("a", move |_| {
+            if !panicked.swap(true, Ordering::Relaxed) {
+                panic!("subscriber panicked");
+            }
+        });
+
+        let wait_for = |condition: &dyn Fn() -> bool| {
+            let start = Instant::now();
+            while !condition() {
+                assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+                cache.hot_reload().unwrap();
+                thread::sleep(Duration::from_millis(20));
+            }
+        };
+
+        cache.hot_reload().unwrap();
+        std::fs::write(tmp.join("a.x"), "2").unwrap();
+        wait_for(&|| cache.hot_reload_status() == HotReloadStatus::Restarting);
+
+        wait_for(&|| cache.hot_reload_status() != HotReloadStatus::Restarting);
+        assert_eq!(cache.hot_reload_restarts(), 1);
+
+        std::fs::write(tmp.join("a.x"), "3").unwrap();
+        wait_for(&|| *a.read() == X(3));
+
+        cache.stop_hot_reloading();
+        assert_eq!(cache.hot_reload_restarts(), 1);
+        assert_eq!(cache.hot_reload_status(), HotReloadStatus::Stopped);
+        std::fs::remove_dir_all(&tmp).unwrap();
     }
 
+    #[cfg(feature = "hot-reloading")]
     #[test]
-    fn read() {
-        let val = rand::random::<i32>();
+    fn watch_loaded_dirs() {
+        use crate::WatchMode;
+        use std::{thread, time::{Duration, Instant}};
 
-        let entry = CacheEntry::new(val);
-        let guard = unsafe { entry.get_ref::<i32>() };
+        let tmp = std::env::temp_dir().join(format!("assets_manager_loaded_dirs_{}", std::process::id()));
+        std::fs::create_dir_all(tmp.join("a")).unwrap();
+        std::fs::write(tmp.join("a/b.x"), "1").unwrap();
 
-        assert_eq!(*guard.read(), val);
+        let mut cache = AssetCache::new(&tmp).unwrap();
+        cache.set_watch_mode(WatchMode::LoadedDirs);
+        let b = cache.load::<X>("a.b").unwrap();
+        cache.hot_reload().unwrap();
+
+        std::fs::write(tmp.join("a/b.x"), "2").unwrap();
+        let start = Instant::now();
+        while *b.read() != X(2) {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            cache.hot_reload().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        cache.stop_hot_reloading();
+        std::fs::remove_dir_all(&tmp).unwrap();
     }
 
     #[test]
-    fn write() {
-        let x = rand::random::<i32>();
-        let y = rand::random::<i32>();
+    #[cfg(all(unix, feature = "hot-reloading"))]
+    fn confined_reload() {
+        use crate::{Asset, loader::{LoadContext, Loader}};
+        use std::{borrow::Cow, fs, io, os::unix::fs::symlink, thread, time::{Duration, Instant}};
 
-        let entry = CacheEntry::new(x);
-        unsafe {
-            let guard = entry.write(y);
-            assert_eq!(*guard.read(), y);
-            let guard = entry.get_ref::<i32>();
-            assert_eq!(*guard.read(), y);
+        struct Text(String);
+        struct TextLoader;
+
+        impl Loader<Text> for TextLoader {
+            type Err = Box<dyn std::error::Error>;
+
+            fn load(content: io::Result<Cow<[u8]>>) -> Result<Text, Self::Err> {
+                Ok(Text(String::from_utf8(content?.into_owned())?))
+            }
+
+            fn load_with_context(context: &LoadContext) -> Result<Text, Self::Err> {
+                let mut text = String::from_utf8(context.content()?.into_owned())?;
+                if let Ok(extra) = context.sibling("extra") {
+                    text.push_str(std::str::from_utf8(&extra)?);
+                }
+                Ok(Text(text))
+            }
+        }
+
+        impl Asset for Text {
+            const EXT: &'static str = "txt";
+            type Loader = TextLoader;
+        }
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_confined_reload_{}", std::process::id()));
+        let (root, outside) = (tmp.join("root"), tmp.join("outside"));
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("a.txt"), "1").unwrap();
+        fs::write(outside.join("secret"), "secret").unwrap();
+        symlink(outside.join("secret"), root.join("a.extra")).unwrap();
+
+        let mut cache = AssetCache::new(&root).unwrap();
+        cache.set_confined(true);
+        let text = cache.load::<Text>("a").unwrap();
+        assert_eq!(text.read().0, "1");
+        cache.hot_reload().unwrap();
+
+        fs::write(root.join("a.txt"), "2").unwrap();
+        let start = Instant::now();
+        while text.read().0 == "1" {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            cache.hot_reload().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(text.read().0, "2");
+
+        cache.stop_hot_reloading();
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[test]
+    fn dependency_reload_hash() {
+        use crate::{Asset, loader::{LoadContext, Loader}};
+        use std::{borrow::Cow, fs, io, thread, time::{Duration, Instant}};
+
+        struct Text(String);
+        struct TextLoader;
+
+        impl Loader<Text> for TextLoader {
+            type Err = Box<dyn std::error::Error>;
+
+            fn load(content: io::Result<Cow<[u8]>>) -> Result<Text, Self::Err> {
+                Ok(Text(String::from_utf8(content?.into_owned())?))
+            }
+
+            fn load_with_context(context: &LoadContext) -> Result<Text, Self::Err> {
+                let mut text = String::from_utf8(context.content()?.into_owned())?;
+                text.push_str(std::str::from_utf8(&context.sibling("extra")?)?);
+                Ok(Text(text))
+            }
+        }
+
+        impl Asset for Text {
+            const EXT: &'static str = "txt";
+            type Loader = TextLoader;
+        }
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_dependency_hash_{}", std::process::id()));
+        fs::create_dir_all(&tmp).unwrap();
+        fs::write(tmp.join("a.txt"), "1").unwrap();
+        fs::write(tmp.join("a.extra"), "2").unwrap();
+
+        let cache = AssetCache::new(&tmp).unwrap();
+        let text = cache.load::<Text>("a").unwrap();
+        cache.hot_reload().unwrap();
+
+        fs::write(tmp.join("a.extra"), "3").unwrap();
+        let start = Instant::now();
+        while text.read().0 != "13" {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            cache.hot_reload().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let fresh = AssetCache::new(&tmp).unwrap();
+        assert_eq!(text.content_hash(), fresh.load::<Text>("a").unwrap().content_hash());
+        assert!(text.content_hash().is_some());
+
+        cache.stop_hot_reloading();
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[test]
+    fn compound_reload_hash() {
+        use crate::{CompoundAsset, loader::LoadContext};
+        use std::{fs, thread, time::{Duration, Instant}};
+
+        struct Sum(i32);
+
+        impl CompoundAsset for Sum {
+            const EXT: &'static str = "a";
+            type Err = Box<dyn std::error::Error>;
+
+            fn load(context: &LoadContext) -> Result<Self, Self::Err> {
+                let a: i32 = std::str::from_utf8(&context.content()?)?.parse()?;
+                let b: i32 = std::str::from_utf8(&context.sibling("b")?)?.parse()?;
+                Ok(Sum(a + b))
+            }
+        }
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_compound_hash_{}", std::process::id()));
+        fs::create_dir_all(&tmp).unwrap();
+        fs::write(tmp.join("sum.a"), "1").unwrap();
+        fs::write(tmp.join("sum.b"), "2").unwrap();
+
+        let cache = AssetCache::new(&tmp).unwrap();
+        let sum = cache.load_compound::<Sum>("sum").unwrap();
+        cache.hot_reload().unwrap();
+
+        fs::write(tmp.join("sum.b"), "10").unwrap();
+        let start = Instant::now();
+        while sum.read().0 != 11 {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            cache.hot_reload().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let fresh = AssetCache::new(&tmp).unwrap();
+        assert_eq!(sum.content_hash(), fresh.load_compound::<Sum>("sum").unwrap().content_hash());
+
+        cache.stop_hot_reloading();
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    /// Creates a directory with `a.x` and `b.x`, both containing `1`.
+    #[cfg(feature = "hot-reloading")]
+    fn removal_dir(name: &str) -> std::path::PathBuf {
+        let tmp = std::env::temp_dir().join(format!("assets_manager_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(tmp.join("a.x"), "1").unwrap();
+        std::fs::write(tmp.join("b.x"), "1").unwrap();
+        tmp
+    }
+
+    /// Removes `a.x` and waits until a change of `b.x`, made after it, was
+    /// applied to the cache.
+    #[cfg(feature = "hot-reloading")]
+    fn remove_watched(cache: &AssetCache, dir: &std::path::Path) {
+        use std::{fs, thread, time::{Duration, Instant}};
+
+        // Makes sure that the reloader knows the watched files
+        cache.hot_reload().unwrap();
+
+        fs::remove_file(dir.join("a.x")).unwrap();
+        fs::write(dir.join("b.x"), "2").unwrap();
+
+        let start = Instant::now();
+        while *cache.load_cached::<X>("b").unwrap().read() != X(2) {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            cache.hot_reload().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "hot-reloading")]
+    fn removal_keep_last_value() {
+        use crate::RemovalPolicy;
+
+        let tmp = removal_dir("removal_keep_last_value");
+        let mut cache = AssetCache::new(&tmp).unwrap();
+        cache.set_removal_policy(RemovalPolicy::KeepLastValue);
+
+        let a = cache.load::<X>("a").unwrap();
+        cache.load::<X>("b").unwrap();
+        let updates = cache.updates::<X>("a");
+        cache.hot_reload().unwrap();
+
+        remove_watched(&cache, &tmp);
+        assert_eq!(*a.read(), X(1));
+        assert!(a.ptr_eq(&cache.load_cached::<X>("a").unwrap()));
+        assert_eq!(updates.try_recv(), Err(std::sync::mpsc::TryRecvError::Empty));
+
+        cache.stop_hot_reloading();
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "hot-reloading")]
+    fn removal_remove_entry() {
+        use crate::RemovalPolicy;
+
+        let tmp = removal_dir("removal_remove_entry");
+        let mut cache = AssetCache::new(&tmp).unwrap();
+        cache.set_removal_policy(RemovalPolicy::RemoveEntry);
+
+        let updates = cache.updates::<X>("a");
+        let scope = cache.scope();
+        let a = scope.load::<X>("a").unwrap();
+        cache.load::<X>("b").unwrap();
+        cache.hot_reload().unwrap();
+
+        remove_watched(&cache, &tmp);
+        assert_eq!(*a.read(), X(1));
+        assert!(cache.load_cached::<X>("a").is_none());
+        assert_eq!(updates.try_recv(), Err(std::sync::mpsc::TryRecvError::Disconnected));
+
+        // The new entry was not loaded through the scope, so it is not
+        // removed when the scope is dropped.
+        std::fs::write(tmp.join("a.x"), "3").unwrap();
+        assert_eq!(*cache.load::<X>("a").unwrap().read(), X(3));
+        drop(scope);
+        cache.stop_hot_reloading();
+        assert_eq!(cache.remove_unused(), 0);
+        assert_eq!(*cache.load_cached::<X>("a").unwrap().read(), X(3));
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "hot-reloading")]
+    fn removal_use_fallback() {
+        use crate::{Asset, RemovalPolicy, loader};
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Fallible(i32);
+
+        impl From<i32> for Fallible {
+            fn from(n: i32) -> Fallible {
+                Fallible(n)
+            }
+        }
+
+        impl Asset for Fallible {
+            const EXT: &'static str = "x";
+            type Loader = loader::LoadOrDefault<loader::LoadFrom<i32, loader::ParseLoader>>;
+        }
+
+        let tmp = removal_dir("removal_use_fallback");
+        let mut cache = AssetCache::new(&tmp).unwrap();
+        cache.set_removal_policy(RemovalPolicy::UseFallback);
+
+        let a = cache.load::<Fallible>("a").unwrap();
+        cache.load::<X>("b").unwrap();
+        cache.hot_reload().unwrap();
+
+        remove_watched(&cache, &tmp);
+        assert_eq!(*a.read(), Fallible(0));
+        assert!(a.ptr_eq(&cache.load_cached::<Fallible>("a").unwrap()));
+
+        cache.stop_hot_reloading();
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "hot-reloading")]
+    fn pause_hot_reload() {
+        use std::{fs, thread, time::{Duration, Instant}};
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_pause_hot_reload_{}", std::process::id()));
+        fs::create_dir_all(&tmp).unwrap();
+        fs::write(tmp.join("a.x"), "1").unwrap();
+
+        let cache = AssetCache::new(&tmp).unwrap();
+        let a = cache.load::<X>("a").unwrap();
+        cache.hot_reload().unwrap();
+        cache.hot_reload().unwrap();
+
+        cache.pause_hot_reload();
+        assert!(cache.is_hot_reload_paused());
+        fs::write(tmp.join("a.x"), "2").unwrap();
+
+        // Give the watcher time to see the change
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(500) {
+            cache.hot_reload().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(*a.read(), X(1));
+
+        cache.resume_hot_reload();
+        assert!(!cache.is_hot_reload_paused());
+        cache.hot_reload().unwrap();
+        assert_eq!(*a.read(), X(2));
+
+        cache.stop_hot_reloading();
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "hot-reloading")]
+    fn ignore_reloads_under() {
+        use std::{fs, thread, time::{Duration, Instant}};
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_ignore_reloads_{}", std::process::id()));
+        fs::create_dir_all(tmp.join("generated")).unwrap();
+        fs::write(tmp.join("generated/a.x"), "1").unwrap();
+        fs::write(tmp.join("generated_b.x"), "1").unwrap();
+
+        let cache = AssetCache::new(&tmp).unwrap();
+        cache.ignore_reloads_under("generated.");
+        let ignored = cache.load::<X>("generated.a").unwrap();
+        let sibling = cache.load::<X>("generated_b").unwrap();
+        cache.hot_reload().unwrap();
+        cache.hot_reload().unwrap();
+
+        fs::write(tmp.join("generated/a.x"), "2").unwrap();
+        fs::write(tmp.join("generated_b.x"), "2").unwrap();
+
+        let start = Instant::now();
+        while *sibling.read() != X(2) {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            cache.hot_reload().unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(*ignored.read(), X(1));
+
+        cache.stop_hot_reloading();
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+}
+
+mod asset_id {
+    use crate::{AssetCache, AssetId};
+    use super::X;
+
+    #[test]
+    fn parse() {
+        assert!(AssetId::parse("a").is_ok());
+        assert!(AssetId::parse("a.b.c").is_ok());
+
+        for id in &["", ".a", "a.", "a..b", "../secrets", "a/b", "a\\b", "C:"] {
+            let err = AssetId::parse(id).unwrap_err();
+            assert_eq!(err.id(), *id);
+        }
+
+        assert_eq!(AssetId::normalize("a/b\\c").unwrap(), "a.b.c");
+        assert!(AssetId::normalize("../a").is_err());
+    }
+
+    #[test]
+    fn filesystem_rejects_invalid_ids() {
+        let cache = AssetCache::new("assets/test").unwrap();
+
+        assert!(cache.load::<X>("..cache").is_err());
+        assert!(cache.load::<X>("/etc/passwd").is_err());
+        assert!(cache.load_dir::<X>("..").is_err());
+        assert!(cache.load_dir::<X>("").is_ok());
+    }
+}
+
+mod source {
+    use crate::{AssetCache, source::{FallbackSource, FileSystem, MemorySource, Source}, testing::{self, MapSource}};
+    use super::X;
+    use std::sync::Arc;
+
+    fn source() -> Arc<MapSource> {
+        let source = Arc::new(MapSource::new());
+        source.insert("a.x", "x", "1");
+        source.insert("a.y", "x", "2");
+        source.insert("a.b.z", "x", "3");
+        source.insert("a.text", "txt", "hello");
+        source
+    }
+
+    #[test]
+    fn load() {
+        let cache = AssetCache::with_source(source());
+
+        assert_eq!(*testing::assert_loads::<X>(&cache, "a.x").read(), X(1));
+        testing::assert_load_fails::<X>(&cache, "a.text");
+        testing::assert_load_fails::<X>(&cache, "b");
+    }
+
+    #[test]
+    fn validate() {
+        let source = source();
+        source.insert("a.b.w", "x", "not a number");
+        let cache = AssetCache::with_source(source);
+
+        let errors = cache.validate::<X>("a").unwrap();
+        let ids: Vec<_> = errors.iter().map(|(id, _)| &**id).collect();
+        assert_eq!(ids, ["a.b.w"]);
+        assert!(cache.validate::<X>("b").is_err());
+
+        // Validation has no effect on the cache
+        assert_eq!(cache.debug_report().load_errors, 0);
+        assert!(cache.load_cached::<X>("a.x").is_none());
+    }
+
+    #[test]
+    fn load_dir() {
+        let cache = AssetCache::with_source(source());
+
+        let mut ids: Vec<_> = cache.load_dir::<X>("a").unwrap().ids().collect();
+        ids.sort();
+        assert_eq!(ids, ["a.x", "a.y"]);
+
+        let dirs = cache.load_dirs::<X>("a").unwrap();
+        assert_eq!(dirs.len(), 1);
+        assert_eq!(dirs[0].id(), "a.b");
+
+        assert!(cache.load_dir::<X>("b").is_err());
+    }
+
+    #[test]
+    fn reload_changed() {
+        let source = source();
+        let cache = AssetCache::with_source(source.clone());
+        let x = cache.load::<X>("a.x").unwrap();
+        cache.reload_changed();
+
+        source.insert("a.x", "x", "-1");
+        testing::assert_reloads::<X>(&cache, "a.x");
+        assert_eq!(*x.read(), X(-1));
+
+        // Failed reloads leave the asset unchanged
+        source.insert("a.x", "x", "a");
+        cache.reload_changed();
+        assert_eq!(*x.read(), X(-1));
+    }
+
+    #[test]
+    fn subscribe_prefix() {
+        use std::sync::Mutex;
+
+        let source = source();
+        let cache = AssetCache::with_source(source.clone());
+        cache.load::<X>("a.x").unwrap();
+        cache.load::<X>("a.b.z").unwrap();
+
+        let reloaded = Arc::new(Mutex::new(Vec::new()));
+        let r = reloaded.clone();
+        cache.subscribe_prefix("a.b.", move |id| r.lock().unwrap().push(id.to_owned()));
+
+        source.insert("a.x", "x", "10");
+        source.insert("a.b.z", "x", "30");
+        cache.reload_changed();
+
+        assert_eq!(*reloaded.lock().unwrap(), ["a.b.z"]);
+    }
+
+    #[test]
+    fn load_context() {
+        use crate::{Asset, loader::{LoadContext, LoadFrom, Loader}};
+        use std::{borrow::Cow, io};
+
+        struct Pair(String);
+
+        struct PairLoader;
+
+        impl Loader<Pair> for PairLoader {
+            type Err = Box<dyn std::error::Error>;
+
+            fn load(_: io::Result<Cow<[u8]>>) -> Result<Pair, Self::Err> {
+                unreachable!()
+            }
+
+            fn load_with_context(context: &LoadContext) -> Result<Pair, Self::Err> {
+                let first = String::from_utf8(context.content()?.into_owned())?;
+                let second = String::from_utf8(context.sibling("txt")?.into_owned())?;
+                if second.is_empty() {
+                    context.warn("empty sibling");
+                }
+                Ok(Pair(format!("{}.{}:{}{}", context.id(), context.ext(), first, second)))
+            }
+        }
+
+        struct Wrapper(Pair);
+
+        impl From<Pair> for Wrapper {
+            fn from(pair: Pair) -> Wrapper {
+                Wrapper(pair)
+            }
+        }
+
+        impl Asset for Wrapper {
+            const EXT: &'static str = "x";
+            type Loader = LoadFrom<Pair, PairLoader>;
+        }
+
+        let source = source();
+        source.insert("b", "x", "1");
+        source.insert("b", "txt", "");
+        let cache = AssetCache::with_source(source);
+
+        assert!(cache.load::<Wrapper>("a.text").is_err());
+        assert_eq!(cache.load::<Wrapper>("b").unwrap().read().0 .0, "b.x:1");
+        assert_eq!(cache.debug_report().load_warnings, 1);
+    }
+
+    #[test]
+    fn compound() {
+        use crate::{CompoundAsset, LoadContext};
+
+        struct Sum(i32);
+
+        impl CompoundAsset for Sum {
+            const EXT: &'static str = "a";
+            type Err = Box<dyn std::error::Error>;
+
+            fn load(context: &LoadContext) -> Result<Self, Self::Err> {
+                let a: i32 = std::str::from_utf8(&context.content()?)?.parse()?;
+                let b: i32 = match context.sibling("b") {
+                    Ok(b) => std::str::from_utf8(&b)?.parse()?,
+                    Err(_) => 0,
+                };
+                Ok(Sum(a + b))
+            }
+        }
+
+        let source = source();
+        source.insert("sum", "a", "1");
+        let cache = AssetCache::with_source(source.clone());
+
+        let sum = cache.load_compound::<Sum>("sum").unwrap();
+        assert_eq!(sum.read().0, 1);
+
+        source.insert("sum", "b", "2");
+        cache.reload_changed();
+        assert_eq!(sum.read().0, 3);
+
+        source.insert("sum", "a", "x");
+        cache.reload_changed();
+        assert_eq!(sum.read().0, 3);
+        assert!(cache.load_compound::<Sum>("missing").is_err());
+    }
+
+    #[test]
+    fn collisions() {
+        use crate::{CollisionPolicy, loader};
+
+        struct Text(String);
+
+        impl From<String> for Text {
+            fn from(s: String) -> Text {
+                Text(s)
+            }
+        }
+
+        impl crate::Asset for Text {
+            const EXT: &'static str = "x";
+            type Loader = loader::LoadFrom<String, loader::StringLoader>;
+        }
+
+        let source = source();
+        let mut cache = AssetCache::with_source(source.clone());
+        cache.load::<X>("a.x").unwrap();
+        cache.load::<X>("a.y").unwrap();
+        cache.load::<Text>("a.x").unwrap();
+
+        let collisions = cache.collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].id, "a.x");
+        assert_eq!(collisions[0].type_names.len(), 2);
+        assert_eq!(cache.debug_report().collisions.len(), 1);
+
+        source.insert("a.x", "x", "10");
+        cache.reload_changed();
+        assert_eq!(cache.load::<Text>("a.x").unwrap().read().0, "10");
+
+        cache.set_collision_policy(CollisionPolicy::SkipReload);
+        source.insert("a.x", "x", "20");
+        source.insert("a.y", "x", "21");
+        cache.reload_changed();
+        assert_eq!(cache.load::<Text>("a.x").unwrap().read().0, "10");
+        assert_eq!(*cache.load::<X>("a.x").unwrap().read(), X(10));
+        assert_eq!(*cache.load::<X>("a.y").unwrap().read(), X(21));
+    }
+
+    #[test]
+    fn mount() {
+        use crate::source::DirEntry;
+
+        let source = source();
+        source.insert("dlc.x", "x", "0");
+        let mut cache = AssetCache::with_source(source);
+        assert_eq!(*cache.load::<X>("dlc.x").unwrap().read(), X(0));
+
+        let dlc = MapSource::new();
+        dlc.insert("x", "x", "10");
+        dlc.insert("maps.forest", "x", "20");
+        cache.mount("dlc", dlc);
+
+        assert_eq!(*cache.load::<X>("dlc.x").unwrap().read(), X(10));
+        assert_eq!(*cache.load::<X>("dlc.maps.forest").unwrap().read(), X(20));
+        assert_eq!(*cache.load::<X>("a.x").unwrap().read(), X(1));
+
+        let ids: Vec<_> = cache.load_dir::<X>("dlc.maps").unwrap().ids().collect();
+        assert_eq!(ids, ["dlc.maps.forest"]);
+
+        let mut dirs = Vec::new();
+        cache.source().read_dir("", &mut |entry| {
+            if let DirEntry::Directory(id) = entry {
+                dirs.push(id.to_owned());
+            }
+        }).unwrap();
+        dirs.sort();
+        assert_eq!(dirs, ["a", "dlc"]);
+
+        let updates = cache.updates::<X>("dlc.maps.forest");
+        assert!(cache.unmount("dlc"));
+        assert!(!cache.unmount("dlc"));
+        assert!(updates.recv().is_err());
+
+        assert_eq!(*cache.load::<X>("dlc.x").unwrap().read(), X(0));
+        assert!(cache.load::<X>("dlc.maps.forest").is_err());
+    }
+
+    #[test]
+    fn define() {
+        let source = source();
+        let cache = AssetCache::with_source(source.clone());
+
+        let double = cache.define("a.double", |cache| {
+            cache.load::<X>("a.x").map_or(0, |x| 2 * x.read().0)
+        });
+        let quad = cache.define("a.quad", |cache| {
+            cache.load_virtual::<i32>("a.double").map_or(0, |x| 2 * *x.read())
+        });
+        assert_eq!(*double.read(), 2);
+        assert_eq!(*quad.read(), 4);
+        assert!(double.ptr_eq(&cache.load_virtual("a.double").unwrap()));
+
+        source.insert("a.x", "x", "5");
+        cache.reload_changed();
+        assert_eq!(*double.read(), 10);
+        assert_eq!(*quad.read(), 20);
+
+        source.insert("a.y", "x", "7");
+        cache.reload_changed();
+        assert_eq!(*quad.read(), 20);
+    }
+
+    #[test]
+    fn load_dir_any() {
+        let source = source();
+        source.insert("a.w", "x", "not a number");
+        let cache = AssetCache::with_source(source);
+        cache.register_type::<X>("x");
+
+        let assets = cache.load_dir_any("a").unwrap();
+        let ids: Vec<_> = assets.iter().map(|(id, _)| &**id).collect();
+        assert_eq!(ids, ["a.w", "a.x", "a.y"]);
+
+        assert!(assets[0].1.is_err());
+        let x = assets[1].1.as_ref().unwrap().downcast::<X>().unwrap();
+        assert_eq!(*x.read(), X(1));
+
+        assert!(cache.load_dir_any("b").is_err());
+    }
+
+    #[test]
+    fn load_as() {
+        use crate::{Asset, LoadDynError, loader};
+
+        trait Value: Send + Sync {
+            fn value(&self) -> i32;
+        }
+
+        impl Value for X {
+            fn value(&self) -> i32 {
+                self.0
+            }
+        }
+
+        struct Text(String);
+
+        impl From<String> for Text {
+            fn from(s: String) -> Text {
+                Text(s)
+            }
+        }
+
+        impl Asset for Text {
+            type Loader = loader::LoadFrom<String, loader::StringLoader>;
+            const EXT: &'static str = "txt";
+        }
+
+        impl Value for Text {
+            fn value(&self) -> i32 {
+                self.0.len() as i32
+            }
+        }
+
+        let source = source();
+        let cache = AssetCache::with_source(source.clone());
+        assert!(matches!(cache.load_as::<dyn Value>("a.x"), Err(LoadDynError::UnknownType(_))));
+
+        cache.register_dyn::<dyn Value, X>(|x| Box::new(x));
+        cache.register_dyn::<dyn Value, Text>(|text| Box::new(text));
+
+        let x = cache.load_as::<dyn Value>("a.x").unwrap();
+        assert_eq!(x.read().value(), 1);
+        assert_eq!(cache.load_as::<dyn Value>("a.text").unwrap().read().value(), 5);
+        assert!(x.ptr_eq(&cache.load_as::<dyn Value>("a.x").unwrap()));
+        assert!(matches!(cache.load_as::<dyn Value>("a.b"), Err(LoadDynError::Load(_))));
+
+        source.insert("a.x", "x", "10");
+        cache.reload_changed();
+        assert_eq!(x.read().value(), 10);
+    }
+
+    #[test]
+    fn budgets() {
+        let source = source();
+        source.insert("a.b.w", "x", "4");
+        let mut cache = AssetCache::with_source(source);
+
+        cache.tag_prefix("a.b.", "B");
+        cache.tag_type::<X>("X");
+        cache.set_budget("B", 4);
+
+        for id in &["a.x", "a.y", "a.b.z", "a.b.w"] {
+            cache.load::<X>(id).unwrap();
+        }
+
+        let report = cache.budget_report();
+        let groups: Vec<_> = report.iter().map(|g| (&*g.name, g.count, g.is_over_budget())).collect();
+        assert_eq!(groups, [("B", 2, true), ("X", 2, false)]);
+
+        // Keep the scope alive
+        let scope = cache.scope();
+        scope.load::<X>("a.b.w").unwrap();
+        std::mem::forget(scope);
+
+        let updates = cache.updates::<X>("a.b.z");
+        assert_eq!(cache.evict_over_budget(), 1);
+        assert!(cache.load_cached::<X>("a.b.z").is_none());
+        assert!(cache.load_cached::<X>("a.b.w").is_some());
+        assert!(!cache.budget_report()[0].is_over_budget());
+        assert_eq!(updates.try_recv(), Err(std::sync::mpsc::TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn heap_size() {
+        struct Text(String);
+
+        impl From<String> for Text {
+            fn from(s: String) -> Text {
+                Text(s)
+            }
+        }
+
+        impl crate::Asset for Text {
+            const EXT: &'static str = "txt";
+            type Loader = crate::loader::LoadFrom<String, crate::loader::StringLoader>;
+
+            fn heap_size(&self) -> usize {
+                self.0.capacity()
+            }
+        }
+
+        let cache = AssetCache::with_source(source());
+        cache.tag_type::<Text>("Text");
+        let text = cache.load::<Text>("a.text").unwrap();
+
+        let expected = std::mem::size_of::<Text>() + text.read().0.capacity();
+        assert_eq!(cache.budget_report()[0].estimated_bytes, expected);
+        assert_eq!(cache.debug_report().estimated_bytes(), expected);
+    }
+
+    #[test]
+    fn generate_ids() {
+        let source = source();
+        source.insert("type.1st-file", "x", "");
+        let code = crate::build::generate_code(&*source).unwrap();
+
+        assert_eq!(code, "\
+// Generated by assets_manager. Do not edit.
+pub mod a {
+    pub const TEXT: &str = \"a.text\";
+    pub const X: &str = \"a.x\";
+    pub const Y: &str = \"a.y\";
+    pub mod b {
+        pub const Z: &str = \"a.b.z\";
+    }
+}
+pub mod r#type {
+    pub const _1ST_FILE: &str = \"type.1st-file\";
+}
+");
+    }
+
+    #[test]
+    fn skipped_files() {
+        let source = source();
+        source.insert("a.w", "josn", "");
+        let cache = AssetCache::with_source(source);
+        cache.load::<Text>("a.text").unwrap();
+
+        let dir = cache.load_dir::<X>("a").unwrap();
+        let skipped: Vec<_> = dir.skipped().iter().map(|f| (f.id(), f.ext(), f.other_type().is_some())).collect();
+        assert_eq!(skipped, [("a.text", "txt", true), ("a.w", "josn", false)]);
+
+        struct Text;
+
+        impl From<String> for Text {
+            fn from(_: String) -> Text {
+                Text
+            }
+        }
+
+        impl crate::Asset for Text {
+            const EXT: &'static str = "txt";
+            type Loader = crate::loader::LoadFrom<String, crate::loader::StringLoader>;
+        }
+    }
+
+    #[test]
+    fn find_ids() {
+        let source = source();
+        source.insert("b.x", "x", "4");
+        let cache = AssetCache::with_source(source);
+
+        assert_eq!(cache.find_ids("a."), ["a.b.z", "a.text", "a.x", "a.y"]);
+        assert_eq!(cache.find_ids("a.*"), ["a.text", "a.x", "a.y"]);
+        assert_eq!(cache.find_ids("*.x"), ["a.x", "b.x"]);
+        assert_eq!(cache.find_ids("**z"), ["a.b.z"]);
+        assert!(cache.find_ids("c.").is_empty());
+        assert_eq!(cache.find_ids("").len(), 5);
+    }
+
+    #[test]
+    fn load_dir_lazy() {
+        let cache = AssetCache::with_source(source());
+
+        let dir = cache.load_dir_lazy::<X>("a").unwrap();
+        assert_eq!(dir.ids(), ["a.x", "a.y"]);
+        assert!(cache.load_cached::<X>("a.x").is_none());
+
+        assert_eq!(*dir.get(1).unwrap().unwrap().read(), X(2));
+        assert!(cache.load_cached::<X>("a.x").is_none());
+
+        let values: Vec<_> = dir.iter().map(|(id, x)| (id, *x.unwrap().read())).collect();
+        assert_eq!(values, [("a.x", X(1)), ("a.y", X(2))]);
+        assert!(cache.load_dir_lazy::<X>("b").is_err());
+    }
+
+    #[test]
+    fn fallback_source() {
+        let primary = MemorySource::new();
+        primary.insert("a.x", "x", "10");
+        primary.insert("a.w", "x", "11");
+        let cache = AssetCache::with_source(FallbackSource::new(primary, source()));
+
+        assert_eq!(*cache.load::<X>("a.x").unwrap().read(), X(10));
+        assert_eq!(*cache.load::<X>("a.y").unwrap().read(), X(2));
+
+        let mut ids: Vec<_> = cache.load_dir::<X>("a").unwrap().ids().collect();
+        ids.sort();
+        assert_eq!(ids, ["a.w", "a.x", "a.y"]);
+    }
+
+    #[test]
+    fn fallback_layers() {
+        use crate::source::{DirEntry, Layer};
+
+        let primary = MemorySource::new();
+        primary.insert("a.x", "x", "10");
+        let source = FallbackSource::new(primary, source());
+
+        let mut entries = Vec::new();
+        source.read_dir_layers("a", &mut |entry: DirEntry, layer| {
+            entries.push((entry.id().to_owned(), layer));
+        }).unwrap();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(entries, [
+            ("a.b".to_owned(), Layer::Secondary),
+            ("a.text".to_owned(), Layer::Secondary),
+            ("a.x".to_owned(), Layer::Primary),
+            ("a.y".to_owned(), Layer::Secondary),
+        ]);
+
+        assert_eq!(source.layer_of("a.x", "x"), Some(Layer::Primary));
+        assert_eq!(source.layer_of("a.b.z", "x"), Some(Layer::Secondary));
+        assert_eq!(source.layer_of("a.z", "x"), None);
+    }
+
+    #[test]
+    fn metadata() {
+        use crate::source::Layer;
+
+        let cache = AssetCache::new("assets").unwrap();
+        let metadata = cache.source_metadata("test.cache", "x").unwrap();
+        assert_eq!(metadata.len, std::fs::metadata("assets/test/cache.x").unwrap().len());
+        assert!(metadata.modified.is_some());
+        assert!(metadata.layers.is_empty());
+        assert!(cache.source_metadata("test", "").is_err());
+
+        let primary = MemorySource::new();
+        primary.insert("a.x", "x", "10");
+        let mut cache = AssetCache::with_source(FallbackSource::new(primary, source()));
+        let dlc = MemorySource::new();
+        dlc.insert("x", "x", "100");
+        cache.mount("dlc", dlc);
+
+        let metadata = cache.source_metadata("a.x", "x").unwrap();
+        assert_eq!((metadata.len, metadata.layers), (2, vec![Layer::Primary]));
+        assert_eq!(cache.source_metadata("a.y", "x").unwrap().layers, [Layer::Secondary]);
+        assert_eq!(cache.source_metadata("dlc.x", "x").unwrap().mount.as_deref(), Some("dlc"));
+        assert!(cache.source_metadata("a.z", "x").is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let mut cache = AssetCache::with_source(source());
+        let dlc = MemorySource::new();
+        dlc.insert("x", "x", "100");
+        cache.mount("dlc", dlc);
+
+        let resolution = cache.resolve::<X>("dlc.x");
+        assert_eq!(resolution.ext, "x");
+        assert_eq!(resolution.mount.as_deref(), Some("dlc"));
+        assert_eq!(resolution.path, None);
+        assert!(resolution.metadata.is_some());
+        assert!(resolution.loader.contains("ParseLoader"));
+
+        cache.load::<X>("a.x").unwrap();
+        let resolution = cache.resolve::<X>("a.x");
+        assert!(resolution.cached && resolution.mount.is_none());
+        assert_eq!(resolution.to_string(), format!("a.x -> \"x\" in source with {}, cached", resolution.loader));
+
+        assert!(cache.resolve::<X>("a.z").metadata.is_none());
+    }
+
+    #[test]
+    fn empty_ext() {
+        struct License(String);
+
+        impl From<String> for License {
+            fn from(s: String) -> License {
+                License(s)
+            }
+        }
+
+        impl crate::Asset for License {
+            const EXT: &'static str = "";
+            type Loader = crate::loader::LoadFrom<String, crate::loader::StringLoader>;
+        }
+
+        let source = MemorySource::new();
+        source.insert("doc.license", "", "MIT");
+        source.insert("doc.notes", "txt", "text");
+        let cache = AssetCache::with_source(source);
+
+        assert_eq!(cache.load::<License>("doc.license").unwrap().read().0, "MIT");
+        assert!(cache.load::<License>("doc.notes").is_err());
+
+        let ids: Vec<_> = cache.load_dir::<License>("doc").unwrap().ids().collect();
+        assert_eq!(ids, ["doc.license"]);
+    }
+
+    #[test]
+    fn any_ext() {
+        #[derive(Debug, PartialEq)]
+        struct Text(String);
+
+        impl From<String> for Text {
+            fn from(s: String) -> Text {
+                Text(s)
+            }
+        }
+
+        impl crate::Asset for Text {
+            const EXT: &'static str = crate::ANY_EXTENSION;
+            type Loader = crate::loader::LoadFrom<String, crate::loader::StringLoader>;
+        }
+
+        let source = Arc::new(MemorySource::new());
+        source.insert("doc.license", "", "MIT");
+        source.insert("doc.notes", "txt", "text");
+        source.insert("doc.notes", "md", "markdown");
+        let mut cache = AssetCache::with_source(source.clone());
+
+        assert_eq!(cache.load::<Text>("doc.license").unwrap().read().0, "MIT");
+        assert_eq!(cache.load::<Text>("doc.notes").unwrap().read().0, "markdown");
+
+        let mut ids: Vec<_> = cache.load_dir::<Text>("doc").unwrap().ids().collect();
+        ids.sort();
+        assert_eq!(ids, ["doc.license", "doc.notes"]);
+
+        // The listing of the directory is refreshed with changes
+        source.insert("doc.readme", "txt", "readme");
+        cache.reload_changed();
+        assert_eq!(cache.load::<Text>("doc.readme").unwrap().read().0, "readme");
+
+        cache.clear();
+        cache.set_strict_extensions(true);
+        assert_eq!(cache.load::<Text>("doc.license").unwrap().read().0, "MIT");
+        let err = cache.load::<Text>("doc.notes").unwrap_err().to_string();
+        assert!(err.contains("several files for asset \"doc.notes\": \"doc.notes.md\", \"doc.notes.txt\""), "{}", err);
+    }
+
+    #[test]
+    fn read_range() {
+        let mut buf = Vec::new();
+
+        let fs = FileSystem::new("assets").unwrap();
+        fs.read_range("test.cache", "x", 1, 1, &mut buf).unwrap();
+        assert_eq!(buf, b"2");
+
+        buf.clear();
+        assert!(fs.read_range("test.cache", "x", 1, 2, &mut buf).is_err());
+        assert!(buf.is_empty());
+        let err = fs.read_range("test.cache", "x", 0, std::usize::MAX, &mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        buf.clear();
+        let source = source();
+        source.read_range("a.text", "txt", 1, 3, &mut buf).unwrap();
+        assert_eq!(buf, b"ell");
+
+        buf.clear();
+        assert!(source.read_range("a.text", "txt", 4, 2, &mut buf).is_err());
+    }
+
+    #[test]
+    fn memory_source() {
+        let source = Arc::new(MemorySource::new());
+        let cache = AssetCache::with_source(source.clone());
+
+        let writer = source.clone();
+        std::thread::spawn(move || writer.insert("generated", "x", "5")).join().unwrap();
+        let x = cache.load::<X>("generated").unwrap();
+
+        source.remove("generated", "x");
+        assert!(!source.contains("generated", "x"));
+        cache.reload_changed();
+        assert_eq!(*x.read(), X(5));
+        testing::assert_load_fails::<X>(&cache, "other");
+    }
+}
+
+mod interpolated {
+    use crate::{AssetCache, Interpolated, InterpolationError, Variables, source::MemorySource};
+    use super::X;
+
+    #[test]
+    fn expand() {
+        let mut vars = Variables::new();
+        vars.insert("HOST", "example.com");
+        vars.insert("PORT", "8080");
+
+        assert_eq!(vars.expand("${HOST}:${PORT}/$${HOST}").unwrap(), "example.com:8080/${HOST}");
+        assert!(matches!(vars.expand("${USER}"), Err(InterpolationError::Undefined(name)) if name == "USER"));
+        assert!(matches!(vars.expand("${HOST"), Err(InterpolationError::Unclosed)));
+    }
+
+    #[test]
+    fn load_with() {
+        let source = MemorySource::new();
+        source.insert("count", "x", "${COUNT}");
+        let cache = AssetCache::with_source(source);
+
+        let vars: Variables = vec![("COUNT", "42")].into_iter().collect();
+        let count = cache.load_with::<Interpolated<X>, _>("count", &vars).unwrap();
+        assert_eq!(count.read().0, X(42));
+
+        let vars: Variables = vec![("COUNT", "many")].into_iter().collect();
+        let err = cache.reload_with::<Interpolated<X>, _>("count", &vars).unwrap_err();
+        assert!(matches!(err, InterpolationError::Load(_)));
+    }
+}
+
+mod units {
+    use crate::units::{Angle, Length, Mass, Speed, Time};
+
+    #[test]
+    fn parse() {
+        assert_eq!("1.5m".parse(), Ok(Length::new(1.5)));
+        assert_eq!("25 cm".parse(), Ok(Length::new(0.25)));
+        assert_eq!("200ms".parse(), Ok(Time::new(0.2)));
+        assert_eq!("2min".parse(), Ok(Time::new(120.0)));
+        assert_eq!("500g".parse(), Ok(Mass::new(0.5)));
+        assert_eq!("36km/h".parse(), Ok(Speed::new(10.0)));
+        assert_eq!("180deg".parse::<Angle>().unwrap().get(), std::f32::consts::PI);
+
+        let err = "200ms".parse::<Length>().unwrap_err();
+        assert_eq!(err.to_string(), r#"invalid length "200ms": expected a number followed by one of km, cm, mm, m"#);
+        assert!("12".parse::<Time>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn deserialize() {
+        #[derive(serde::Deserialize)]
+        struct Settings {
+            #[allow(dead_code)]
+            gravity: Length,
+        }
+
+        let err = serde_json::from_str::<Settings>("{\n  \"gravity\": \"9.8s\"\n}").err().unwrap();
+        let err = err.to_string();
+        assert!(err.contains("invalid length"));
+        assert!(err.contains("line 2"));
+    }
+}
+
+mod value {
+    use crate::AssetCache;
+
+    #[test]
+    fn palette() {
+        use crate::{Color, Palette, source::MemorySource};
+
+        let source = MemorySource::new();
+        source.insert("sunset", "hex", "; Sunset\n#ff7b00\n\n2d1b69cc\n");
+        source.insert("broken", "hex", "ff7b00\n#12345\n");
+        let cache = AssetCache::with_source(source);
+
+        let palette = cache.load::<Palette>("sunset").unwrap().read();
+        assert_eq!(palette.colors(), [
+            Color { r: 0xff, g: 0x7b, b: 0x00, a: 0xff },
+            Color { r: 0x2d, g: 0x1b, b: 0x69, a: 0xcc },
+        ]);
+        assert_eq!(palette.get(2), None);
+
+        let err = cache.load::<Palette>("broken").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn curve() {
+        use crate::Curve;
+
+        let curve: Curve = "; t v\n0 0\n0.5 1\n2 0.25\n".parse().unwrap();
+        assert_eq!(curve.eval(-1.0), 0.0);
+        assert_eq!(curve.eval(0.25), 0.5);
+        assert_eq!(curve.eval(1.25), 0.625);
+        assert_eq!(curve.eval(5.0), 0.25);
+
+        assert_eq!("0 0\n0 1\n".parse::<Curve>().unwrap_err().line(), 2);
+        assert_eq!("0 0 0\n".parse::<Curve>().unwrap_err().line(), 1);
+        assert!("; empty\n".parse::<Curve>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_value() {
+        use crate::{JsonValue, source::MemorySource};
+
+        let source = MemorySource::new();
+        source.insert("data", "json", r#"{"name": "goblin", "hp": [10, 20]}"#);
+        let cache = AssetCache::with_source(source);
+
+        let value = cache.load::<JsonValue>("data").unwrap().read();
+        assert_eq!(value["name"], "goblin");
+        assert_eq!(value["hp"][1], 20);
+    }
+
+    #[test]
+    #[cfg(feature = "ron")]
+    fn ron_value() {
+        use crate::RonValue;
+        use serde_ron::value::{Number, Value};
+
+        let cache = AssetCache::new("assets").unwrap();
+        let value = cache.load::<RonValue>("common.position").unwrap().read();
+
+        let map = match &**value {
+            Value::Map(map) => map,
+            _ => panic!("expected a map"),
+        };
+        assert_eq!(map.get(&Value::String("x".into())), Some(&Value::Number(Number::new(5.0))));
+    }
+}
+
+#[cfg(feature = "json")]
+mod compose {
+    use crate::{AssetCache, source::MemorySource};
+    use std::{collections::HashMap, sync::Arc};
+
+    type Map = HashMap<String, serde_json::Value>;
+
+    #[test]
+    fn include() {
+        let source = Arc::new(MemorySource::new());
+        source.insert("base", "json", r#"{"hp": 10, "speed": 1}"#);
+        source.insert("goblin", "json", r#"{"$include": "base", "hp": 5, "loot": [{"$include": "coin"}]}"#);
+        source.insert("coin", "json", r#""gold""#);
+        let cache = AssetCache::with_source(source.clone());
+
+        let goblin = cache.compose_json::<Map>("goblin");
+        assert_eq!(goblin.read().as_ref().unwrap()["hp"], 5);
+        assert_eq!(goblin.read().as_ref().unwrap()["speed"], 1);
+        assert_eq!(goblin.read().as_ref().unwrap()["loot"][0], "gold");
+
+        source.insert("base", "json", r#"{"hp": 10, "speed": 2}"#);
+        cache.force_reload::<crate::JsonValue>("base").unwrap();
+        assert_eq!(goblin.read().as_ref().unwrap()["speed"], 2);
+    }
+
+    #[test]
+    fn cycle() {
+        let source = MemorySource::new();
+        source.insert("a", "json", r#"{"$include": "b"}"#);
+        source.insert("b", "json", r#"{"x": {"$include": "a"}}"#);
+        let cache = AssetCache::with_source(source);
+
+        let a = cache.compose_json::<Map>("a");
+        let err = a.read().as_ref().unwrap_err().to_string();
+        assert!(err.contains("a -> b -> a"), "{}", err);
+    }
+}
+
+mod merge {
+    use crate::{Asset, AssetCache, Merge, loader, source::MemorySource};
+    use std::collections::HashMap;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Stats(HashMap<String, i32>);
+
+    impl Merge for Stats {
+        fn merge(&mut self, other: Self) {
+            self.0.extend(other.0);
+        }
+    }
+
+    impl std::str::FromStr for Stats {
+        type Err = std::num::ParseIntError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(2, '=');
+                    Some((parts.next()?, parts.next()?))
+                })
+                .map(|(k, v)| Ok((k.to_owned(), v.parse()?)))
+                .collect::<Result<_, _>>()
+                .map(Stats)
+        }
+    }
+
+    impl Asset for Stats {
+        const EXT: &'static str = "stats";
+        type Loader = loader::ParseLoader;
+    }
+
+    #[test]
+    fn load_merged() {
+        let source = MemorySource::new();
+        source.insert("base", "stats", "hp=10\nspeed=1");
+        source.insert("goblin", "stats", "speed=3");
+        let cache = AssetCache::with_source(source);
+
+        let goblin = cache.load_merged::<Stats>(&["base", "goblin"]).unwrap();
+        assert_eq!(goblin.0["hp"], 10);
+        assert_eq!(goblin.0["speed"], 3);
+
+        assert!(cache.load_merged::<Stats>(&["base", "missing"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json_deep_merge() {
+        let mut base = serde_json::json!({"a": {"b": 1, "c": [1, 2]}, "d": 0});
+        base.merge(serde_json::json!({"a": {"c": [3], "e": 4}}));
+        assert_eq!(base, serde_json::json!({"a": {"b": 1, "c": [3], "e": 4}, "d": 0}));
+    }
+}
+
+mod registry {
+    use crate::{AssetCache, RegistryEntry, RegistryError, source::MemorySource};
+    use super::X;
+
+    #[derive(Debug, PartialEq)]
+    struct Item(i32);
+
+    impl From<i32> for Item {
+        fn from(n: i32) -> Item {
+            Item(n)
+        }
+    }
+
+    impl crate::Asset for Item {
+        const EXT: &'static str = "x";
+        type Loader = crate::loader::LoadFrom<i32, crate::loader::ParseLoader>;
+    }
+
+    impl RegistryEntry for Item {
+        const REQUIRED: &'static [&'static str] = &["sword"];
+
+        fn validate(_: &str, item: &Item) -> Result<(), String> {
+            if item.0 < 0 {
+                return Err("negative price".into());
+            }
+            Ok(())
+        }
+    }
+
+    impl RegistryEntry for X {
+        const ALLOW_OVERRIDES: bool = true;
+    }
+
+    fn source() -> MemorySource {
+        let source = MemorySource::new();
+        source.insert("base.sword", "x", "10");
+        source.insert("base.shield", "x", "5");
+        source.insert("mod.shield", "x", "7");
+        source
+    }
+
+    #[test]
+    fn layers() {
+        let cache = AssetCache::with_source(source());
+
+        let registry = cache.load_registry::<X>(&["base", "mod"]).unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get("sword").unwrap().read().0, 10);
+        assert_eq!(registry.get("shield").unwrap().read().0, 7);
+        assert!(registry.get("helmet").is_none());
+
+        let registry = cache.load_registry::<Item>(&["base"]).unwrap();
+        assert!(registry.contains("shield"));
+    }
+
+    #[test]
+    fn ids() {
+        let source = std::sync::Arc::new(source());
+        source.insert("mod.helmet", "x", "3");
+        let cache = AssetCache::with_source(source.clone());
+
+        let registry = cache.load_registry::<X>(&["base", "mod"]).unwrap();
+        let names: Vec<_> = registry.iter().map(|(_, name, _)| name).collect();
+        assert_eq!(names, ["shield", "sword", "helmet"]);
+
+        let shield = registry.id_of("shield").unwrap();
+        assert_eq!(shield.index(), 0);
+        assert_eq!(registry.name_of(shield), Some("shield"));
+        assert_eq!(registry[shield].read().0, 7);
+
+        source.insert("mod.shield", "x", "8");
+        cache.force_reload::<X>("mod.shield").unwrap();
+        assert_eq!(registry.get_by_id(shield).unwrap().read().0, 8);
+    }
+
+    #[test]
+    fn validation() {
+        let source = source();
+        source.insert("broken.sword", "x", "-1");
+        let cache = AssetCache::with_source(source);
+
+        let err = cache.load_registry::<Item>(&["base", "mod"]).unwrap_err();
+        assert!(matches!(&err, RegistryError::Duplicate(name, _) if name == "shield"), "{}", err);
+
+        let err = cache.load_registry::<Item>(&["mod"]).unwrap_err();
+        assert!(matches!(&err, RegistryError::Missing(name) if name == "sword"), "{}", err);
+
+        let err = cache.load_registry::<Item>(&["broken"]).unwrap_err();
+        assert!(matches!(&err, RegistryError::Invalid(id, _) if id == "broken.sword"), "{}", err);
+    }
+}
+
+mod section {
+    use crate::{Asset, AssetCache, Section, SectionError, loader, source::MemorySource};
+    use std::{borrow::Cow, collections::HashMap, io, str, sync::Arc};
+
+    /// A file with `key=value` lines
+    struct Config(HashMap<String, i32>);
+
+    struct ConfigLoader;
+
+    impl loader::Loader<Config> for ConfigLoader {
+        type Err = Box<dyn std::error::Error>;
+
+        fn load(content: io::Result<Cow<[u8]>>) -> Result<Config, Self::Err> {
+            let mut map = HashMap::new();
+            for line in str::from_utf8(&content?)?.lines() {
+                let mut parts = line.splitn(2, '=');
+                let key = parts.next().unwrap_or_default();
+                let value = parts.next().ok_or("missing value")?.parse()?;
+                map.insert(key.to_owned(), value);
+            }
+            Ok(Config(map))
+        }
+    }
+
+    impl Asset for Config {
+        const EXT: &'static str = "cfg";
+        type Loader = ConfigLoader;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Value(i32);
+
+    impl Section for Value {
+        type Parent = Config;
+
+        fn extract(config: &Config, name: &str) -> Option<Value> {
+            config.0.get(name).map(|&x| Value(x))
+        }
+
+        fn section_names(config: &Config) -> Vec<String> {
+            let mut names: Vec<_> = config.0.keys().cloned().collect();
+            names.sort();
+            names
+        }
+    }
+
+    #[test]
+    fn load_section() {
+        let source = Arc::new(MemorySource::new());
+        source.insert("config", "cfg", "a=1\nb=2");
+        let cache = AssetCache::with_source(source.clone());
+
+        let a = cache.load_section::<Value>("config#a").unwrap();
+        let b = cache.load_section::<Value>("config#b").unwrap();
+        assert_eq!(*a.read(), Value(1));
+        assert!(a.ptr_eq(&cache.load_section::<Value>("config#a").unwrap()));
+
+        assert!(matches!(cache.load_section::<Value>("config#c"), Err(SectionError::NotFound(_))));
+        assert!(matches!(cache.load_section::<Value>("config"), Err(SectionError::InvalidId(_))));
+        assert!(matches!(cache.load_section::<Value>("other#a"), Err(SectionError::Load(_))));
+
+        source.insert("config", "cfg", "a=1\nb=3");
+        cache.reload_changed();
+        assert_eq!(*a.read(), Value(1));
+        assert_eq!(*b.read(), Value(3));
+    }
+
+    #[test]
+    fn set_root() {
+        use std::{fs, sync::atomic::{AtomicUsize, Ordering}};
+
+        static EXTRACTED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, PartialEq)]
+        struct Counted(i32);
+
+        impl Section for Counted {
+            type Parent = Config;
+
+            fn extract(config: &Config, name: &str) -> Option<Counted> {
+                EXTRACTED.fetch_add(1, Ordering::Relaxed);
+                config.0.get(name).map(|&x| Counted(x))
+            }
+        }
+
+        let tmp = std::env::temp_dir().join(format!("assets_manager_section_root_{}", std::process::id()));
+        let (first, second) = (tmp.join("first"), tmp.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        fs::write(first.join("config.cfg"), "a=1\nb=2").unwrap();
+        fs::write(second.join("config.cfg"), "a=1\nb=2").unwrap();
+
+        let mut cache = AssetCache::new(&first).unwrap();
+        assert_eq!(*cache.load_section::<Counted>("config#a").unwrap().read(), Counted(1));
+        assert_eq!(*cache.load_section::<Counted>("config#a").unwrap().read(), Counted(1));
+        assert_eq!(EXTRACTED.load(Ordering::Relaxed), 1);
+
+        cache.set_root(&second).unwrap();
+
+        // Sections cannot be reloaded on their own, so they are always removed
+        assert!(cache.load_cached::<Config>("config").is_some());
+        assert_eq!(*cache.load_section::<Counted>("config#a").unwrap().read(), Counted(1));
+        assert_eq!(EXTRACTED.load(Ordering::Relaxed), 2);
+
+        let _ = fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn load_sections() {
+        let source = Arc::new(MemorySource::new());
+        source.insert("config", "cfg", "a=1\nb=2");
+        let cache = AssetCache::with_source(source.clone());
+
+        let sections = cache.load_sections::<Value>("config").unwrap();
+        let ids: Vec<_> = sections.iter().map(|(id, _)| &**id).collect();
+        assert_eq!(ids, ["config#a", "config#b"]);
+        assert_eq!(*sections[1].1.read(), Value(2));
+
+        source.insert("config", "cfg", "a=1\nb=2\nc=3");
+        cache.reload_changed();
+        assert_eq!(cache.load_sections::<Value>("config").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn section_notifications() {
+        use std::sync::{Mutex, atomic::{AtomicUsize, Ordering}};
+
+        let source = Arc::new(MemorySource::new());
+        source.insert("config", "cfg", "a=1\nb=2");
+        let cache = AssetCache::with_source(source.clone());
+
+        let reloaded = Arc::new(Mutex::new(Vec::new()));
+        let ids = reloaded.clone();
+        cache.subscribe_prefix("config#", move |id| ids.lock().unwrap().push(id.to_owned()));
+
+        let computed = Arc::new(AtomicUsize::new(0));
+        let count = computed.clone();
+        let double = cache.define("double_a", move |cache| {
+            count.fetch_add(1, Ordering::Relaxed);
+            cache.load_section::<Value>("config#a").unwrap().read().0 * 2
+        });
+        cache.load_section::<Value>("config#b").unwrap();
+        assert_eq!(*double.read(), 2);
+
+        // Only `b` changed
+        source.insert("config", "cfg", "a=1\nb=3");
+        cache.reload_changed();
+        assert_eq!(*reloaded.lock().unwrap(), ["config#b"]);
+        assert_eq!(computed.load(Ordering::Relaxed), 1);
+
+        source.insert("config", "cfg", "a=5\nb=3");
+        cache.reload_changed();
+        assert_eq!(*reloaded.lock().unwrap(), ["config#b", "config#a"]);
+        assert_eq!(*double.read(), 10);
+        assert_eq!(computed.load(Ordering::Relaxed), 2);
+    }
+}
+
+mod sliced {
+    use crate::{Asset, AssetCache, SectionError, Sliced, loader, source::MemorySource};
+    use std::sync::Arc;
+
+    struct Bank(Arc<[u8]>);
+
+    impl From<Vec<u8>> for Bank {
+        fn from(bytes: Vec<u8>) -> Bank {
+            Bank(bytes.into())
+        }
+    }
+
+    impl AsRef<Arc<[u8]>> for Bank {
+        fn as_ref(&self) -> &Arc<[u8]> {
+            &self.0
+        }
+    }
+
+    impl Asset for Bank {
+        const EXT: &'static str = "bank";
+        type Loader = loader::LoadFrom<Vec<u8>, loader::BytesLoader>;
+    }
+
+    #[test]
+    fn load() {
+        let source = MemorySource::new();
+        source.insert("bank", "bank", &b"hellobonjour"[..]);
+        let cache = AssetCache::with_source(source);
+
+        let hello = cache.load_section::<Sliced<Bank>>("bank#0..5").unwrap();
+        let bonjour = cache.load_section::<Sliced<Bank>>("bank#5..12").unwrap();
+        assert_eq!(&**hello.read(), b"hello");
+        assert_eq!(&**bonjour.read(), b"bonjour");
+        assert_eq!(bonjour.read().offset(), 5);
+
+        let bank = cache.load::<Bank>("bank").unwrap();
+        assert!(Arc::ptr_eq(&bank.read().0, hello.read().shared_bytes()));
+
+        assert!(matches!(cache.load_section::<Sliced<Bank>>("bank#5..13"), Err(SectionError::NotFound(_))));
+        assert!(matches!(cache.load_section::<Sliced<Bank>>("bank#5"), Err(SectionError::NotFound(_))));
+    }
+}
+
+mod background {
+    use crate::{AssetCache, BackgroundLoader};
+    use super::X;
+
+    fn leaked_cache() -> &'static AssetCache {
+        Box::leak(Box::new(AssetCache::new("assets").unwrap()))
+    }
+
+    #[test]
+    fn load() {
+        let cache = leaked_cache();
+        let loader = BackgroundLoader::new(cache);
+
+        let ok = loader.load::<X>("test.cache");
+        let err = loader.load::<X>("test.a");
+
+        let asset = ok.wait().unwrap();
+        assert_eq!(*asset.read(), X(42));
+        assert!(asset.ptr_eq(&cache.load_cached("test.cache").unwrap()));
+        assert!(err.wait().is_err());
+    }
+
+    #[test]
+    fn cancel() {
+        use crate::{Asset, loader::Loader};
+        use std::{borrow::Cow, io, sync::atomic::{AtomicBool, Ordering}};
+
+        static OPEN: AtomicBool = AtomicBool::new(false);
+
+        // An asset whose loading blocks the loader until `OPEN` is set
+        struct Gate;
+        struct GateLoader;
+
+        impl Loader<Gate> for GateLoader {
+            type Err = io::Error;
+
+            fn load(_: io::Result<Cow<[u8]>>) -> io::Result<Gate> {
+                while !OPEN.load(Ordering::Acquire) {
+                    std::thread::yield_now();
+                }
+                Ok(Gate)
+            }
+        }
+
+        impl Asset for Gate {
+            const EXT: &'static str = "x";
+            type Loader = GateLoader;
+        }
+
+        let cache = leaked_cache();
+        let loader = BackgroundLoader::new(cache);
+
+        let gate = loader.load::<Gate>("test.cache");
+        drop(loader.load::<X>("test.b"));
+
+        OPEN.store(true, Ordering::Release);
+        gate.wait().unwrap();
+        drop(loader);
+
+        assert!(cache.load_cached::<X>("test.b").is_none());
+    }
+
+    #[test]
+    fn wait_for() {
+        use crate::{Asset, loader::Loader};
+        use std::{borrow::Cow, io, sync::atomic::{AtomicBool, Ordering}, time::Duration};
+
+        static OPEN: AtomicBool = AtomicBool::new(false);
+
+        // An asset whose loading blocks the loader until `OPEN` is set
+        struct Gate;
+        struct GateLoader;
+
+        impl Loader<Gate> for GateLoader {
+            type Err = io::Error;
+
+            fn load(_: io::Result<Cow<[u8]>>) -> io::Result<Gate> {
+                while !OPEN.load(Ordering::Acquire) {
+                    std::thread::yield_now();
+                }
+                Ok(Gate)
+            }
+        }
+
+        impl Asset for Gate {
+            const EXT: &'static str = "x";
+            type Loader = GateLoader;
+        }
+
+        let cache = leaked_cache();
+        let loader = BackgroundLoader::new(cache);
+
+        let _gate = loader.load::<Gate>("test.cache");
+        let _pending = loader.load::<X>("test.cache");
+
+        assert!(loader.wait_for::<X>("test.b", None).is_none());
+        assert!(loader.wait_for::<X>("test.cache", Some(Duration::from_millis(10))).is_none());
+
+        OPEN.store(true, Ordering::Release);
+        let asset = loader.wait_for::<X>("test.cache", None).unwrap();
+        assert_eq!(*asset.read(), X(42));
+    }
+
+    #[test]
+    fn load_cached() {
+        let cache = leaked_cache();
+        let loader = BackgroundLoader::new(cache);
+
+        let asset = cache.load::<X>("test.cache").unwrap();
+        let mut pending = loader.load::<X>("test.cache");
+        assert!(pending.is_ready());
+        assert!(pending.wait().unwrap().ptr_eq(&asset));
+    }
+
+    #[test]
+    fn concurrency() {
+        let cache = leaked_cache();
+        let loader = BackgroundLoader::builder(cache)
+            .max_io_concurrency(3)
+            .max_decode_concurrency(2)
+            .build();
+
+        let pending: Vec<_> = ["test.a", "test.b", "test.cache"].iter().map(|id| loader.load::<X>(id)).collect();
+        let results: Vec<_> = pending.into_iter().map(|p| p.wait().map(|x| x.read().0)).collect();
+        assert!(results[0].is_err());
+        assert_eq!(results[2].as_ref().ok(), Some(&42));
+        drop(loader);
+
+        assert!(cache.load_cached::<X>("test.cache").is_some());
+    }
+
+    #[test]
+    fn scheduling() {
+        use crate::{LoadCost, background::Queue};
+        use std::sync::{Arc, Mutex};
+
+        let queue = Queue::default();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let push = |cost, n| {
+            let log = log.clone();
+            queue.push(cost, Box::new(move || log.lock().unwrap().push(n)));
+        };
+
+        push(LoadCost::Huge, 0);
+        push(LoadCost::Huge, 1);
+        push(LoadCost::Medium, 2);
+        for n in 3..23 {
+            push(LoadCost::Tiny, n);
+        }
+
+        let mut state = queue.state.lock().unwrap();
+        let mut run = || {
+            let (jobs, huge) = state.take()?;
+            let len = jobs.len();
+            jobs.into_iter().for_each(|job| job());
+            Some((len, huge))
+        };
+
+        assert_eq!(run(), Some((16, false)));
+        assert_eq!(run(), Some((4, false)));
+        assert_eq!(run(), Some((1, false)));
+        assert_eq!(run(), Some((1, true)));
+        // Only one huge job at a time
+        assert_eq!(run(), None);
+
+        let expected: Vec<_> = (3..23).chain(vec![2, 0]).collect();
+        assert_eq!(*log.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn load_dir() {
+        let cache = leaked_cache();
+        let loader = BackgroundLoader::new(cache);
+
+        let pending = loader.load_dir::<X>("test").unwrap();
+        let total = pending.remaining();
+        assert!(total > 0);
+
+        let mut loaded: Vec<_> = pending.map(|(id, result)| (id, result.is_ok())).collect();
+        loaded.sort();
+        assert_eq!(loaded.len(), total);
+        assert!(loaded.contains(&("test.cache".into(), true)));
+
+        assert!(loader.load_dir::<X>("test.cache").is_err());
+    }
+}
+
+mod cache_entry {
+    use std::sync::{Arc, Mutex};
+    use crate::lock::CacheEntry;
+
+    #[derive(Clone)]
+    struct DropCounter(Arc<Mutex<usize>>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            let mut count = self.0.lock().unwrap();
+            *count += 1;
+        }
+    }
+
+    #[test]
+    fn drop_inner() {
+        let count = DropCounter(Arc::new(Mutex::new(0)));
+
+        let entry_1 = CacheEntry::new(count.clone());
+        let entry_2 = CacheEntry::new(count.clone());
+        assert_eq!(*count.0.lock().unwrap(), 0);
+        drop(entry_1);
+        assert_eq!(*count.0.lock().unwrap(), 1);
+        drop(entry_2);
+        assert_eq!(*count.0.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn read() {
+        let val = rand::random::<i32>();
+
+        let entry = CacheEntry::new(val);
+        let guard = unsafe { entry.get_ref::<i32>() };
+
+        assert_eq!(*guard.read(), val);
+    }
+
+    #[test]
+    fn write() {
+        let x = rand::random::<i32>();
+        let y = rand::random::<i32>();
+
+        let entry = CacheEntry::new(x);
+        unsafe {
+            let guard = entry.write(y, None);
+            assert_eq!(*guard.read(), y);
+            let guard = entry.get_ref::<i32>();
+            assert_eq!(*guard.read(), y);
+        }
+    }
+
+    #[test]
+    fn snapshot() {
+        let x = rand::random::<i32>();
+        let y = rand::random::<i32>();
+
+        let entry = CacheEntry::new(x);
+        unsafe {
+            let snapshot = entry.get_ref::<i32>().snapshot();
+            entry.write(y, None);
+            assert_eq!(*snapshot, x);
+            assert_eq!(*entry.get_ref::<i32>().snapshot(), y);
         }
     }
 
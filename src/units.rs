@@ -0,0 +1,169 @@
+//! Physical quantities with units
+//!
+//! This module defines types that parse values with a unit suffix, such as
+//! `"1.5m"` or `"200ms"`, and store them in a base unit. They can be used in
+//! settings assets so that the unit of each value is explicit in the file.
+//!
+//! With feature `serde`, these types can be deserialized from strings. A
+//! value with a missing or mismatched unit (e.g. a length given in seconds)
+//! is rejected, and the error usually contains the location of the value in
+//! the file, depending on the format.
+//!
+//! # Example
+//!
+//! ```
+//! # cfg_if::cfg_if! { if #[cfg(feature = "ron")] {
+//! use assets_manager::{Asset, AssetCache, loader, units::{Length, Time}};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Jump {
+//!     height: Length,
+//!     duration: Time,
+//! }
+//!
+//! impl Asset for Jump {
+//!     const EXT: &'static str = "ron";
+//!     type Loader = loader::RonLoader;
+//! }
+//!
+//! let source = assets_manager::source::MemorySource::new();
+//! source.insert("jump", "ron", r#"(height: "1.5m", duration: "400ms")"#);
+//! let cache = AssetCache::with_source(source);
+//!
+//! let jump = cache.load::<Jump>("jump")?.read();
+//! assert_eq!(jump.height.get(), 1.5);
+//! assert_eq!(jump.duration.get(), 0.4);
+//! # }}
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{error::Error, fmt, str::FromStr};
+
+
+/// An error which occurs when parsing a quantity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseUnitError {
+    quantity: &'static str,
+    input: String,
+    units: &'static [(&'static str, f64)],
+}
+
+impl fmt::Display for ParseUnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} {:?}: expected a number followed by one of ", self.quantity, self.input)?;
+
+        for (i, (unit, _)) in self.units.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(unit)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseUnitError {}
+
+fn parse(s: &str, quantity: &'static str, units: &'static [(&'static str, f64)]) -> Result<f32, ParseUnitError> {
+    let trimmed = s.trim();
+
+    for (unit, factor) in units {
+        if trimmed.ends_with(unit) {
+            let number = trimmed[..trimmed.len() - unit.len()].trim_end();
+            if let Ok(value) = number.parse::<f64>() {
+                return Ok((value * factor) as f32);
+            }
+        }
+    }
+
+    Err(ParseUnitError { quantity, input: s.to_owned(), units })
+}
+
+macro_rules! quantity {
+    ($(#[$attr:meta])* $name:ident, $quantity:literal, [$(($unit:literal, $factor:expr)),+ $(,)?]) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+        pub struct $name(f32);
+
+        impl $name {
+            const UNITS: &'static [(&'static str, f64)] = &[$(($unit, $factor)),+];
+
+            /// Creates a new value, in the base unit.
+            #[inline]
+            pub const fn new(value: f32) -> Self {
+                Self(value)
+            }
+
+            /// Returns the value, in the base unit.
+            #[inline]
+            pub const fn get(self) -> f32 {
+                self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseUnitError;
+
+            fn from_str(s: &str) -> Result<Self, ParseUnitError> {
+                parse(s, $quantity, Self::UNITS).map(Self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl serde::de::Visitor<'_> for Visitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str(concat!("a ", $quantity, " with a unit"))
+                    }
+
+                    fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<$name, E> {
+                        s.parse().map_err(E::custom)
+                    }
+                }
+
+                deserializer.deserialize_str(Visitor)
+            }
+        }
+    };
+}
+
+quantity! {
+    /// A length, stored in meters.
+    ///
+    /// Accepted units are `km`, `m`, `cm` and `mm`.
+    Length, "length", [("km", 1000.0), ("cm", 0.01), ("mm", 0.001), ("m", 1.0)]
+}
+
+quantity! {
+    /// A duration, stored in seconds.
+    ///
+    /// Accepted units are `h`, `min`, `s`, `ms` and `us`.
+    Time, "duration", [("h", 3600.0), ("min", 60.0), ("ms", 0.001), ("us", 0.000_001), ("s", 1.0)]
+}
+
+quantity! {
+    /// A mass, stored in kilograms.
+    ///
+    /// Accepted units are `t`, `kg` and `g`.
+    Mass, "mass", [("kg", 1.0), ("t", 1000.0), ("g", 0.001)]
+}
+
+quantity! {
+    /// An angle, stored in radians.
+    ///
+    /// Accepted units are `rad` and `deg`.
+    Angle, "angle", [("rad", 1.0), ("deg", std::f64::consts::PI / 180.0)]
+}
+
+quantity! {
+    /// A speed, stored in meters per second.
+    ///
+    /// Accepted units are `m/s` and `km/h`.
+    Speed, "speed", [("m/s", 1.0), ("km/h", 1.0 / 3.6)]
+}
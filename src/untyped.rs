@@ -0,0 +1,265 @@
+//! Loading of assets whose type is not known at compile time
+
+use crate::{
+    ANY_EXTENSION,
+    Asset,
+    AssetCache,
+    CompoundAsset,
+    AssetRef,
+    cache::Key,
+    lock::EntryData,
+};
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::Arc,
+};
+
+use crate::RandomState;
+
+
+/// A reference to an asset whose type is not statically known.
+///
+/// It can be obtained by calling [`AssetCache::load_dyn`], and converted
+/// back to a typed [`AssetRef`] with [`downcast`].
+///
+/// [`AssetCache::load_dyn`]: struct.AssetCache.html#method.load_dyn
+/// [`AssetRef`]: struct.AssetRef.html
+/// [`downcast`]: #method.downcast
+#[derive(Clone, Copy)]
+pub struct UntypedRef<'a> {
+    data: &'a (dyn Any + Send + Sync),
+    type_id: TypeId,
+}
+
+impl<'a> UntypedRef<'a> {
+    #[inline]
+    pub(crate) fn new<A: Asset>(asset: AssetRef<'a, A>) -> Self {
+        Self {
+            data: asset.data,
+            type_id: TypeId::of::<A>(),
+        }
+    }
+
+    /// Returns the `TypeId` of the referenced asset.
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Returns `true` if the referenced asset has type `A`.
+    #[inline]
+    pub fn is<A: Asset>(&self) -> bool {
+        self.type_id == TypeId::of::<A>()
+    }
+
+    /// Returns a typed reference to the asset if it has type `A`.
+    #[inline]
+    pub fn downcast<A: Asset>(self) -> Option<AssetRef<'a, A>> {
+        let data = self.data.downcast_ref::<EntryData<A>>()?;
+        Some(AssetRef { data })
+    }
+}
+
+impl fmt::Debug for UntypedRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UntypedRef")
+            .field("type_id", &self.type_id)
+            .finish()
+    }
+}
+
+
+/// An error which occurs when loading an asset with [`AssetCache::load_dyn`].
+///
+/// [`AssetCache::load_dyn`]: struct.AssetCache.html#method.load_dyn
+#[derive(Debug)]
+pub enum LoadDynError {
+    /// No asset type was registered with the given name.
+    UnknownType(String),
+
+    /// The asset could not be loaded.
+    ///
+    /// The error returned by the asset's loader is given as a string, because
+    /// its type is not known.
+    Load(String),
+}
+
+impl fmt::Display for LoadDynError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownType(name) => write!(f, "unknown asset type {:?}", name),
+            Self::Load(err) => f.write_str(err),
+        }
+    }
+}
+
+impl Error for LoadDynError {}
+
+
+type LoadFn = for<'a> fn(&'a AssetCache, &str) -> Result<UntypedRef<'a>, LoadDynError>;
+
+fn load<'a, A: Asset>(cache: &'a AssetCache, id: &str) -> Result<UntypedRef<'a>, LoadDynError> {
+    match cache.load::<A>(id) {
+        Ok(asset) => Ok(UntypedRef::new(asset)),
+        Err(err) => Err(LoadDynError::Load(err.to_string())),
+    }
+}
+
+pub(crate) type ReloadFn = fn(&AssetCache, &str) -> bool;
+
+/// Reloads a cached asset, returning `true` on success.
+fn reload<A: Asset>(cache: &AssetCache, id: &str) -> bool {
+    let reloaded = cache.load_cached::<A>(id).is_some() && cache.force_reload::<A>(id).is_ok();
+    if reloaded {
+        cache.stats.lock().reloaded(Key::new::<A>(id.into()));
+    }
+    reloaded
+}
+
+/// Reloads a cached compound asset, returning `true` on success.
+fn reload_compound<A: CompoundAsset>(cache: &AssetCache, id: &str) -> bool {
+    let reloaded = cache.reload_compound::<A>(id);
+    if reloaded {
+        cache.stats.lock().reloaded(Key::new::<A>(id.into()));
+    }
+    reloaded
+}
+
+/// Creates a trait object from an asset with a given id.
+pub(crate) type DynCtor<T> = Arc<dyn Fn(&AssetCache, &str) -> Result<Box<T>, String> + Send + Sync>;
+
+/// The constructors registered for trait objects `T`, with the extension of
+/// their asset type.
+struct DynCtors<T: ?Sized>(Vec<(&'static str, DynCtor<T>)>);
+
+/// Reloads a cached trait object, returning `true` on success.
+fn reload_dyn<T: ?Sized + Send + Sync + 'static>(cache: &AssetCache, id: &str) -> bool {
+    let reloaded = cache.reload_as::<T>(id);
+    if reloaded {
+        cache.stats.lock().reloaded(Key::new::<Box<T>>(id.into()));
+    }
+    reloaded
+}
+
+/// Informations about a registered asset type.
+#[derive(Clone, Copy)]
+pub(crate) struct TypeInfo {
+    pub load: LoadFn,
+    pub ext: &'static str,
+}
+
+/// Maps names to asset types.
+///
+/// It also remembers the extension of each type of loaded asset, so assets
+/// can be reloaded when a file changes.
+pub(crate) struct TypeRegistry {
+    types: HashMap<Box<str>, TypeInfo, RandomState>,
+    names: HashMap<TypeId, Box<str>, RandomState>,
+    kinds: HashMap<TypeId, (&'static str, ReloadFn), RandomState>,
+    dyn_ctors: HashMap<TypeId, Box<dyn Any + Send + Sync>, RandomState>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::with_hasher(RandomState::new()),
+            names: HashMap::with_hasher(RandomState::new()),
+            kinds: HashMap::with_hasher(RandomState::new()),
+            dyn_ctors: HashMap::with_hasher(RandomState::new()),
+        }
+    }
+
+    pub fn register<A: Asset>(&mut self, name: &str) {
+        let info = TypeInfo {
+            load: load::<A>,
+            ext: A::EXT,
+        };
+        self.types.insert(name.into(), info);
+        self.names.insert(TypeId::of::<A>(), name.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<TypeInfo> {
+        self.types.get(name).copied()
+    }
+
+    /// Returns the name under which a type was last registered, if any.
+    pub fn name_of(&self, type_id: TypeId) -> Option<&str> {
+        self.names.get(&type_id).map(|name| &**name)
+    }
+
+    /// Returns the registered type with extension `ext`.
+    ///
+    /// If several types have this extension, the one with the smallest name is
+    /// returned, so the result does not depend on the order of registration.
+    pub fn get_with_ext(&self, ext: &str) -> Option<TypeInfo> {
+        self.types.iter()
+            .filter(|(_, info)| info.ext == ext)
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, &info)| info)
+    }
+
+    /// Registers `A` as a way to create `Box<T>`.
+    ///
+    /// Trait objects are reloaded with any file, because the type used to
+    /// create them is only known when they are loaded.
+    pub fn register_dyn<T, A>(&mut self, ctor: DynCtor<T>)
+    where
+        T: ?Sized + Send + Sync + 'static,
+        A: Asset,
+    {
+        let ctors = self.dyn_ctors
+            .entry(TypeId::of::<Box<T>>())
+            .or_insert_with(|| Box::new(DynCtors::<T>(Vec::new())));
+        if let Some(ctors) = ctors.downcast_mut::<DynCtors<T>>() {
+            ctors.0.push((A::EXT, ctor));
+        }
+
+        self.kinds.insert(TypeId::of::<Box<T>>(), (ANY_EXTENSION, reload_dyn::<T>));
+    }
+
+    /// Returns the constructors of `Box<T>`, in registration order.
+    pub fn dyn_ctors<T: ?Sized + Send + Sync + 'static>(&self) -> Vec<(&'static str, DynCtor<T>)> {
+        self.dyn_ctors.get(&TypeId::of::<Box<T>>())
+            .and_then(|ctors| ctors.downcast_ref::<DynCtors<T>>())
+            .map_or_else(Vec::new, |ctors| ctors.0.clone())
+    }
+
+    pub fn has_kind<T: 'static>(&self) -> bool {
+        self.kinds.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn add_kind<A: Asset>(&mut self) {
+        self.kinds.insert(TypeId::of::<A>(), (A::EXT, reload::<A>));
+    }
+
+    /// Compound assets are reloaded when any file with their id changes, as
+    /// they may read files with any extension.
+    pub fn add_compound_kind<A: CompoundAsset>(&mut self) {
+        self.kinds.insert(TypeId::of::<A>(), (ANY_EXTENSION, reload_compound::<A>));
+    }
+
+    /// Returns the extension and the reload function of a type of asset.
+    pub fn kind(&self, type_id: TypeId) -> Option<(&'static str, ReloadFn)> {
+        self.kinds.get(&type_id).copied()
+    }
+
+    /// Returns a loaded type of asset with extension `ext`, if any.
+    pub fn kind_with_ext(&self, ext: &str) -> Option<TypeId> {
+        self.kinds.iter()
+            .find(|(_, &(kind_ext, _))| kind_ext == ext)
+            .map(|(&type_id, _)| type_id)
+    }
+
+    /// Calls `f` with each type with extension `ext` and its reload function.
+    pub fn reloaders(&self, ext: &str, mut f: impl FnMut(TypeId, ReloadFn)) {
+        for (&type_id, &(kind_ext, reload)) in &self.kinds {
+            if kind_ext == ext || kind_ext == ANY_EXTENSION {
+                f(type_id, reload);
+            }
+        }
+    }
+}
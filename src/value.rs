@@ -0,0 +1,349 @@
+//! Built-in asset types
+
+use crate::{Asset, loader};
+
+use std::{error::Error, fmt, str::FromStr};
+
+#[cfg(any(feature = "json", feature = "ron"))]
+use std::ops::{Deref, DerefMut};
+
+
+macro_rules! value_asset {
+    ($feature:literal, $doc:literal, $name:ident, $value:ty, $ext:literal, $loader:ty) => {
+        #[doc = $doc]
+        ///
+        /// This is useful for tools and debug overlays that inspect arbitrary
+        /// files through the cache, without defining typed structs. The
+        /// value can be accessed through `Deref` or with the public field.
+        #[cfg(feature = $feature)]
+        #[cfg_attr(docsrs, doc(cfg(feature = $feature)))]
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name(pub $value);
+
+        #[cfg(feature = $feature)]
+        impl From<$value> for $name {
+            #[inline]
+            fn from(value: $value) -> Self {
+                Self(value)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl Deref for $name {
+            type Target = $value;
+
+            #[inline]
+            fn deref(&self) -> &$value {
+                &self.0
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl DerefMut for $name {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut $value {
+                &mut self.0
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl Asset for $name {
+            const EXT: &'static str = $ext;
+            type Loader = loader::LoadFrom<$value, $loader>;
+        }
+    }
+}
+
+value_asset!("json", "Any JSON document, loaded from a `.json` file.", JsonValue, serde_json::Value, "json", loader::JsonLoader);
+value_asset!("ron", "Any RON document, loaded from a `.ron` file.", RonValue, serde_ron::Value, "ron", loader::RonLoader);
+
+
+/// A table of strings, loaded from a `.csv` file.
+///
+/// The first line of the file gives the names of the columns, and each
+/// following line is a row. All rows have the same number of cells as the
+/// header.
+///
+/// Use [`CsvLoader`] directly to deserialize rows to a struct instead. To
+/// load a TSV file, use [`TsvLoader`] in your own `Asset` implementation.
+///
+/// [`CsvLoader`]: loader/struct.CsvLoader.html
+/// [`TsvLoader`]: loader/struct.TsvLoader.html
+#[cfg(feature = "csv")]
+#[cfg_attr(docsrs, doc(cfg(feature = "csv")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+#[cfg(feature = "csv")]
+impl Table {
+    pub(crate) fn from_reader(mut reader: serde_csv::Reader<&[u8]>) -> Result<Self, serde_csv::Error> {
+        let headers = reader.headers()?.iter().map(String::from).collect();
+        let rows = reader.records()
+            .map(|record| Ok(record?.iter().map(String::from).collect()))
+            .collect::<Result<_, serde_csv::Error>>()?;
+
+        Ok(Table { headers, rows })
+    }
+
+    /// Returns the names of the columns.
+    #[inline]
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Returns the rows of the table, without the header.
+    #[inline]
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    /// Returns the index of the column named `name`, if any.
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|h| h == name)
+    }
+
+    /// Returns the cell at row `row` in the column named `column`.
+    pub fn get(&self, row: usize, column: &str) -> Option<&str> {
+        let column = self.column(column)?;
+        Some(self.rows.get(row)?[column].as_str())
+    }
+}
+
+#[cfg(feature = "csv")]
+impl Asset for Table {
+    const EXT: &'static str = "csv";
+    type Loader = loader::CsvLoader;
+}
+
+
+/// A text document with an optional typed header.
+///
+/// This is useful for dialogue, tutorials or lore, where writers need some
+/// metadata along with the text. See [`FrontMatterLoader`] for the format.
+///
+/// With feature `yaml`, `Document<M>` is an asset loaded from `.md` files
+/// with a YAML header. Use [`FrontMatterLoader`] with another loader in your
+/// own `Asset` implementation to use another format.
+///
+/// [`FrontMatterLoader`]: loader/struct.FrontMatterLoader.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document<M> {
+    /// The header of the document, if any.
+    pub meta: Option<M>,
+
+    /// The text of the document, after the header.
+    pub body: String,
+}
+
+#[cfg(feature = "yaml")]
+#[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+impl<M> Asset for Document<M>
+where
+    M: for<'de> serde::Deserialize<'de> + Send + Sync + 'static,
+{
+    const EXT: &'static str = "md";
+    type Loader = loader::FrontMatterLoader<loader::YamlLoader>;
+}
+
+
+/// An error which occurs when parsing a [`Palette`] or a [`Curve`].
+///
+/// [`Palette`]: struct.Palette.html
+/// [`Curve`]: struct.Curve.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDataError {
+    line: usize,
+    reason: &'static str,
+}
+
+impl ParseDataError {
+    /// Returns the line (starting from 1) where the error occured.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for ParseDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl Error for ParseDataError {}
+
+/// Iterates over meaningful lines of a text, with their line number.
+///
+/// Empty lines and comments (starting with `;`) are skipped.
+fn data_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with(';'))
+}
+
+
+/// A RGBA color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    /// The red component.
+    pub r: u8,
+    /// The green component.
+    pub g: u8,
+    /// The blue component.
+    pub b: u8,
+    /// The alpha component.
+    pub a: u8,
+}
+
+impl Color {
+    /// Parses a color from its hexadecimal notation: `RRGGBB` or `RRGGBBAA`,
+    /// with an optional leading `#`.
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let hex = if hex.starts_with('#') { &hex[1..] } else { hex };
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let component = |i: usize| u8::from_str_radix(&hex[i..i+2], 16).ok();
+        let a = if hex.len() == 8 { component(6)? } else { 255 };
+        Some(Color { r: component(0)?, g: component(2)?, b: component(4)?, a })
+    }
+
+    /// Returns the components as floats between `0.0` and `1.0`.
+    pub fn to_f32(self) -> [f32; 4] {
+        let f = |c: u8| f32::from(c) / 255.0;
+        [f(self.r), f(self.g), f(self.b), f(self.a)]
+    }
+}
+
+/// A list of colors, loaded from a `.hex` file.
+///
+/// The file contains a color per line in hexadecimal notation (see
+/// [`Color::from_hex`]). Empty lines and lines starting with `;` are ignored.
+///
+/// ```text
+/// ; Sunset
+/// #ff7b00
+/// ffb347
+/// 2d1b69cc
+/// ```
+///
+/// [`Color::from_hex`]: struct.Color.html#method.from_hex
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Palette {
+    colors: Vec<Color>,
+}
+
+impl Palette {
+    /// Returns the colors of the palette.
+    #[inline]
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    /// Returns the color at index `i`, if any.
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<Color> {
+        self.colors.get(i).copied()
+    }
+}
+
+impl FromStr for Palette {
+    type Err = ParseDataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colors = data_lines(s)
+            .map(|(line, text)| {
+                Color::from_hex(text).ok_or(ParseDataError { line, reason: "invalid hexadecimal color" })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Palette { colors })
+    }
+}
+
+impl Asset for Palette {
+    const EXT: &'static str = "hex";
+    type Loader = loader::ParseLoader;
+}
+
+
+/// An animation curve, loaded from a `.curve` file.
+///
+/// The file contains a keyframe per line, made of a time and a value
+/// separated by whitespace. Times must be increasing, and there must be at
+/// least one keyframe. Empty lines and lines starting with `;` are ignored.
+///
+/// ```text
+/// ; time value
+/// 0.0  0.0
+/// 0.5  1.0
+/// 2.0  0.25
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    keys: Vec<(f32, f32)>,
+}
+
+impl Curve {
+    /// Returns the keyframes of the curve, as `(time, value)` pairs.
+    #[inline]
+    pub fn keys(&self) -> &[(f32, f32)] {
+        &self.keys
+    }
+
+    /// Evaluates the curve at time `t`.
+    ///
+    /// Values are interpolated linearly between keyframes, and the curve is
+    /// constant before its first keyframe and after its last one.
+    pub fn eval(&self, t: f32) -> f32 {
+        let next = self.keys.iter().position(|&(time, _)| time > t);
+
+        match next {
+            Some(0) => self.keys[0].1,
+            Some(i) => {
+                let (t0, v0) = self.keys[i - 1];
+                let (t1, v1) = self.keys[i];
+                v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+            },
+            None => self.keys[self.keys.len() - 1].1,
+        }
+    }
+}
+
+impl FromStr for Curve {
+    type Err = ParseDataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys: Vec<(f32, f32)> = Vec::new();
+
+        for (line, text) in data_lines(s) {
+            let error = |reason| ParseDataError { line, reason };
+
+            let mut parts = text.split_whitespace().map(str::parse::<f32>);
+            let key = match (parts.next(), parts.next(), parts.next()) {
+                (Some(Ok(time)), Some(Ok(value)), None) => (time, value),
+                _ => return Err(error("expected a time and a value")),
+            };
+            if !key.0.is_finite() || keys.last().map_or(false, |last| last.0 >= key.0) {
+                return Err(error("times must be finite and increasing"));
+            }
+            keys.push(key);
+        }
+
+        if keys.is_empty() {
+            return Err(ParseDataError { line: s.lines().count(), reason: "a curve needs at least one keyframe" });
+        }
+
+        Ok(Curve { keys })
+    }
+}
+
+impl Asset for Curve {
+    const EXT: &'static str = "curve";
+    type Loader = loader::ParseLoader;
+}
@@ -0,0 +1,45 @@
+//! Recording of loads to warm up the cache on next startup
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    path::Path,
+};
+
+
+/// A file in which loads are recorded.
+///
+/// Each line contains the name of the type of an asset, as given to
+/// `register_type`, and its id, separated by a tab.
+pub(crate) struct UsageTrace {
+    out: LineWriter<File>,
+}
+
+impl UsageTrace {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let out = LineWriter::new(File::create(path)?);
+        Ok(UsageTrace { out })
+    }
+
+    pub fn record(&mut self, type_name: &str, id: &str) -> io::Result<()> {
+        writeln!(self.out, "{}\t{}", type_name, id)
+    }
+}
+
+/// Reads the entries of a trace, as `(type_name, id)` pairs.
+///
+/// Malformed lines are skipped.
+pub(crate) fn read_trace(path: &Path) -> io::Result<Vec<(String, String)>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+
+    for line in file.lines() {
+        let line = line?;
+        let mut parts = line.splitn(2, '\t');
+        if let (Some(type_name), Some(id)) = (parts.next(), parts.next()) {
+            entries.push((type_name.to_owned(), id.to_owned()));
+        }
+    }
+
+    Ok(entries)
+}